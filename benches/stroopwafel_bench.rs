@@ -3,6 +3,7 @@ use std::collections::HashMap;
 use stroopwafel::{
     Stroopwafel,
     verifier::{AcceptAllVerifier, ContextVerifier},
+    view::StroopwafelRef,
 };
 
 fn bench_stroopwafel_new(c: &mut Criterion) {
@@ -123,6 +124,47 @@ fn bench_serialization_msgpack(c: &mut Criterion) {
     c.bench_function("deserialize_from_msgpack", |b| {
         b.iter(|| black_box(Stroopwafel::from_msgpack(black_box(&msgpack)).unwrap()))
     });
+
+    c.bench_function("view_from_msgpack", |b| {
+        b.iter(|| black_box(StroopwafelRef::from_msgpack(black_box(&msgpack)).unwrap()))
+    });
+}
+
+fn bench_decode_and_verify(c: &mut Criterion) {
+    let root_key = b"super_secret_key_for_benchmarking";
+    let mut group = c.benchmark_group("decode_and_verify");
+
+    for num_caveats in [1, 5, 10, 20].iter() {
+        let mut s = Stroopwafel::new(root_key, b"identifier", Some("https://example.com"));
+        for i in 0..*num_caveats {
+            s.add_first_party_caveat(format!("resource{i} = /api/data/{i}").as_bytes());
+        }
+        let msgpack = s.to_msgpack().unwrap();
+        let verifier = AcceptAllVerifier;
+
+        group.bench_with_input(
+            BenchmarkId::new("owned", num_caveats),
+            &msgpack,
+            |b, msgpack| {
+                b.iter(|| {
+                    let token = Stroopwafel::from_msgpack(black_box(msgpack)).unwrap();
+                    token.verify(root_key, &verifier, &[]).unwrap()
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("view", num_caveats),
+            &msgpack,
+            |b, msgpack| {
+                b.iter(|| {
+                    let token = StroopwafelRef::from_msgpack(black_box(msgpack)).unwrap();
+                    token.verify(root_key, &verifier, &[]).unwrap()
+                })
+            },
+        );
+    }
+
+    group.finish();
 }
 
 fn bench_serialization_base64(c: &mut Criterion) {
@@ -277,6 +319,71 @@ fn bench_context_verifier(c: &mut Criterion) {
     });
 }
 
+fn bench_batch_verify(c: &mut Criterion) {
+    use stroopwafel::batch::BatchVerifier;
+
+    let root_key = b"super_secret_key_for_benchmarking";
+    let mut base = Stroopwafel::new(root_key, b"identifier", Some("https://example.com"));
+    base.add_first_party_caveat(b"account = alice");
+    base.add_first_party_caveat(b"action = read");
+    base.add_first_party_caveat(b"count < 100");
+
+    // Attenuations of one token share its caveat prefix
+    let tokens: Vec<Stroopwafel> = (0..1000)
+        .map(|i| {
+            let mut token = base.clone();
+            token.add_first_party_caveat(format!("resource ^= /api/{i}").as_bytes());
+            token
+        })
+        .collect();
+    let verifier = AcceptAllVerifier;
+
+    let mut group = c.benchmark_group("verify_1000_tokens");
+    group.bench_function("individual", |b| {
+        b.iter(|| {
+            for token in &tokens {
+                token.verify(black_box(root_key), &verifier, &[]).unwrap();
+            }
+        })
+    });
+    group.bench_function("batch", |b| {
+        let batch = BatchVerifier::new(root_key);
+        b.iter(|| black_box(batch.verify_tokens(&verifier, black_box(&tokens))))
+    });
+    group.finish();
+}
+
+fn bench_checkpoint_verify(c: &mut Criterion) {
+    use std::sync::Arc;
+    use stroopwafel::checkpoint::CheckpointCache;
+    use stroopwafel::options::VerifyOptions;
+
+    let root_key = b"super_secret_key_for_benchmarking";
+    let mut base = Stroopwafel::new(root_key, b"identifier", Some("https://example.com"));
+    for i in 0..20 {
+        base.add_first_party_caveat(format!("scope{i} = granted").as_bytes());
+    }
+    let mut token = base.clone();
+    token.add_first_party_caveat(b"action = read");
+    let verifier = AcceptAllVerifier;
+
+    let mut group = c.benchmark_group("verify_21_caveats");
+    group.bench_function("uncached", |b| {
+        b.iter(|| token.verify(black_box(root_key), &verifier, &[]).unwrap())
+    });
+    group.bench_function("checkpoint", |b| {
+        let cache = Arc::new(CheckpointCache::new(root_key, 1000));
+        cache.warm(&base);
+        let options = VerifyOptions::new().with_checkpoint_cache(cache);
+        b.iter(|| {
+            token
+                .verify_with_options(black_box(root_key), &verifier, &[], &options)
+                .unwrap()
+        })
+    });
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_stroopwafel_new,
@@ -286,11 +393,14 @@ criterion_group!(
     bench_verify_with_caveats,
     bench_serialization_json,
     bench_serialization_msgpack,
+    bench_decode_and_verify,
     bench_serialization_base64,
     bench_third_party_caveats,
     bench_predicate_parsing,
     bench_predicate_evaluation,
     bench_context_verifier,
+    bench_batch_verify,
+    bench_checkpoint_verify,
 );
 
 criterion_main!(benches);
@@ -0,0 +1,284 @@
+use crate::crypto::{PreparedKey, SIGNATURE_SIZE, bind_caveat, signatures_equal};
+use crate::options::VerifyOptions;
+use crate::stroopwafel::Token;
+use crate::verifier::CaveatVerifier;
+use crate::{Result, Stroopwafel, StroopwafelError};
+use std::collections::HashMap;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// A token to verify in a batch, with the discharges it needs
+#[derive(Debug, Clone, Copy)]
+pub struct BatchItem<'a> {
+    /// The token presented
+    pub token: &'a Stroopwafel,
+    /// Discharges for the token's third-party caveats
+    pub discharges: &'a [Stroopwafel],
+}
+
+impl<'a> BatchItem<'a> {
+    /// Creates an item for a token without discharges
+    pub fn new(token: &'a Stroopwafel) -> Self {
+        Self {
+            token,
+            discharges: &[],
+        }
+    }
+
+    /// Creates an item for a token with its discharges
+    pub fn with_discharges(token: &'a Stroopwafel, discharges: &'a [Stroopwafel]) -> Self {
+        Self { token, discharges }
+    }
+}
+
+impl<'a> From<&'a Stroopwafel> for BatchItem<'a> {
+    fn from(token: &'a Stroopwafel) -> Self {
+        Self::new(token)
+    }
+}
+
+/// Verifies many tokens minted with the same root key
+///
+/// Each token gets the same result as
+/// [`Stroopwafel::verify_with_options`], in input order, but the signature
+/// chains are computed together: the root key is set up once, and tokens
+/// that share an identifier and a caveat prefix (e.g. attenuations of one
+/// token) compute the shared part of the chain once.
+///
+/// With the `rayon` feature, tokens are verified on the rayon thread pool.
+/// Stores that record presentations (replay and quota stores) are then
+/// updated in no particular order across tokens.
+///
+/// # Example
+/// ```
+/// use stroopwafel::Stroopwafel;
+/// use stroopwafel::batch::BatchVerifier;
+/// use stroopwafel::verifier::ContextVerifier;
+///
+/// let root_key = b"secret";
+/// let mut alice = Stroopwafel::new(root_key, b"identifier", None::<String>);
+/// alice.add_first_party_caveat(b"account = alice");
+/// let mut bob = alice.clone();
+/// bob.add_first_party_caveat(b"account = bob");
+///
+/// let verifier = ContextVerifier::empty().with("account", "alice");
+/// let results = BatchVerifier::new(root_key).verify_tokens(&verifier, &[alice, bob]);
+/// assert!(results[0].is_ok());
+/// assert!(results[1].is_err());
+/// ```
+#[derive(Clone)]
+pub struct BatchVerifier {
+    root_key: PreparedKey,
+    options: VerifyOptions,
+}
+
+impl BatchVerifier {
+    /// Creates a batch verifier for tokens minted with `root_key`, using the
+    /// default options
+    pub fn new(root_key: &[u8]) -> Self {
+        Self {
+            root_key: PreparedKey::new(root_key),
+            options: VerifyOptions::default(),
+        }
+    }
+
+    /// Verifies every token with `options`
+    pub fn with_options(mut self, options: VerifyOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Returns the options every token is verified with
+    pub fn options(&self) -> &VerifyOptions {
+        &self.options
+    }
+
+    /// Verifies tokens without discharges
+    pub fn verify_tokens<V>(&self, verifier: &V, tokens: &[Stroopwafel]) -> Vec<Result<()>>
+    where
+        V: CaveatVerifier + Sync,
+    {
+        let items: Vec<BatchItem<'_>> = tokens.iter().map(BatchItem::new).collect();
+        self.verify(verifier, &items)
+    }
+
+    /// Verifies tokens with their discharges, returning one result per item
+    /// in input order
+    pub fn verify<V>(&self, verifier: &V, items: &[BatchItem<'_>]) -> Vec<Result<()>>
+    where
+        V: CaveatVerifier + Sync,
+    {
+        // Step 1: Check the signature chains, one identifier group at a time
+        let mut groups: HashMap<&[u8], Vec<usize>> = HashMap::new();
+        for (index, item) in items.iter().enumerate() {
+            groups
+                .entry(item.token.identifier.as_slice())
+                .or_default()
+                .push(index);
+        }
+        let groups: Vec<Vec<usize>> = groups.into_values().collect();
+
+        let mut signed = vec![false; items.len()];
+        for group in map(&groups, |group| self.check_group(items, group)) {
+            for (index, valid) in group {
+                signed[index] = valid;
+            }
+        }
+
+        // Step 2: Verify everything else for tokens with a valid chain
+        let indexed: Vec<(usize, &BatchItem<'_>)> = items.iter().enumerate().collect();
+        map(&indexed, |&(index, item)| {
+            if !signed[index] {
+                return Err(StroopwafelError::InvalidSignature);
+            }
+            item.token
+                .verify_signed(verifier, item.discharges, &self.options)
+        })
+    }
+
+    /// Checks the signature chains of a group of tokens sharing an
+    /// identifier, computing each shared chain prefix once
+    fn check_group(&self, items: &[BatchItem<'_>], group: &[usize]) -> Vec<(usize, bool)> {
+        let first = items[group[0]].token;
+        let root = self.root_key.sign(&first.identifier);
+
+        // Links already computed: (previous signature, caveat bytes) -> signature
+        let mut links: HashMap<([u8; SIGNATURE_SIZE], &[u8]), [u8; SIGNATURE_SIZE]> =
+            HashMap::new();
+        let shared = group.len() > 1;
+
+        group
+            .iter()
+            .map(|&index| {
+                let token = items[index].token;
+                let signature = token.chain_elements().fold(root, |signature, element| {
+                    if !shared {
+                        return bind_caveat(&signature, element);
+                    }
+                    *links
+                        .entry((signature, element))
+                        .or_insert_with(|| bind_caveat(&signature, element))
+                });
+                (index, signatures_equal(&signature, &token.signature))
+            })
+            .collect()
+    }
+}
+
+impl std::fmt::Debug for BatchVerifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BatchVerifier")
+            .field("options", &self.options)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Maps `f` over `items`, on the rayon thread pool if the feature is enabled
+fn map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync + Send) -> Vec<R> {
+    #[cfg(feature = "rayon")]
+    {
+        items.par_iter().map(f).collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        items.iter().map(f).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::UnrecognizedPolicy;
+    use crate::verifier::{AcceptAllVerifier, ContextVerifier};
+
+    const ROOT_KEY: &[u8] = b"root_secret";
+
+    fn token(identifier: &[u8], caveats: &[&str]) -> Stroopwafel {
+        let mut token = Stroopwafel::new(ROOT_KEY, identifier, None::<String>);
+        for caveat in caveats {
+            token.add_first_party_caveat(caveat.as_bytes());
+        }
+        token
+    }
+
+    #[test]
+    fn test_batch_matches_individual_verification() {
+        let mut forged = token(b"b", &["account = alice"]);
+        forged.caveats[0].caveat_id = b"account = mallory".to_vec();
+
+        let tokens = [
+            token(b"a", &[]),
+            token(b"a", &["account = alice"]),
+            token(b"a", &["account = alice", "action = read"]),
+            token(b"a", &["account = bob"]),
+            forged,
+            token(b"c", &["not a predicate"]),
+            Stroopwafel::new(b"other key", b"a", None::<String>),
+        ];
+        let verifier = ContextVerifier::empty()
+            .with("account", "alice")
+            .with("action", "read");
+
+        for options in [
+            VerifyOptions::new(),
+            VerifyOptions::new().with_unrecognized_policy(UnrecognizedPolicy::Accept),
+        ] {
+            let results = BatchVerifier::new(ROOT_KEY)
+                .with_options(options.clone())
+                .verify_tokens(&verifier, &tokens);
+
+            assert_eq!(results.len(), tokens.len());
+            for (token, result) in tokens.iter().zip(results) {
+                assert_eq!(
+                    result,
+                    token.verify_with_options(ROOT_KEY, &verifier, &[], &options)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_batch_with_discharges() {
+        let verification_key = b"verification_secret";
+        let mut primary = token(b"primary", &[]);
+        primary.add_third_party_caveat(b"auth", verification_key, "https://auth.example.com");
+
+        let mut discharge =
+            Stroopwafel::create_discharge(verification_key, b"auth", None::<String>);
+        discharge.add_first_party_caveat(b"account = alice");
+        let discharges = [primary.bind_discharge(&discharge)];
+
+        let items = [
+            BatchItem::with_discharges(&primary, &discharges),
+            BatchItem::new(&primary),
+        ];
+        let verifier = ContextVerifier::empty().with("account", "alice");
+        let results = BatchVerifier::new(ROOT_KEY).verify(&verifier, &items);
+
+        assert!(results[0].is_ok());
+        assert_eq!(
+            results[1],
+            Err(StroopwafelError::MissingDischarge {
+                caveat_id: b"auth".to_vec()
+            })
+        );
+    }
+
+    #[test]
+    fn test_batch_empty() {
+        let results = BatchVerifier::new(ROOT_KEY).verify_tokens(&AcceptAllVerifier, &[]);
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn test_batch_wrong_root_key() {
+        let tokens = [token(b"a", &[]), token(b"a", &["account = alice"])];
+        let results = BatchVerifier::new(b"wrong").verify_tokens(&AcceptAllVerifier, &tokens);
+        assert!(
+            results
+                .iter()
+                .all(|result| result == &Err(StroopwafelError::InvalidSignature))
+        );
+    }
+}
@@ -0,0 +1,509 @@
+use crate::caveat::ParsedCaveat;
+use crate::clock::{Clock, SystemClock};
+use crate::crypto::{SIGNATURE_SIZE, hmac_sha3};
+use crate::options::VerifyOptions;
+use crate::verifier::{AcceptAllVerifier, CaveatVerifier};
+use crate::{Result, Stroopwafel};
+use lru::LruCache;
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+type Key = [u8; SIGNATURE_SIZE];
+
+/// Remembers which tokens the verifier accepted, so repeated presentations
+/// skip the verifier
+///
+/// Caveat checks may call out to other systems, while the same token is
+/// presented on every request. The cache is a bounded LRU map from a token's
+/// signature, the signatures of the discharges bound to it, and a context
+/// fingerprint to "accepted". A hit skips only the verifier: signatures,
+/// discharges, revocation, epochs, the token policy, nonces and quotas are
+/// all checked again, so a token that was altered, revoked or used up fails
+/// as usual (and its entry is dropped).
+///
+/// The fingerprint must capture everything the verifier's answer depends on
+/// for the request (e.g. a hash of the request context), and one cache
+/// should only be used with one verifier. Entries expire with the token, at
+/// its earliest expiry caveat (`time <` or `time <=`) across the token and
+/// its discharges, or after the maximum TTL if that is sooner. Only
+/// successful verifications are cached.
+///
+/// Revocations recorded in the
+/// [revocation store](crate::options::VerifyOptions::with_revocation_store)
+/// of the cache's options take effect on the next presentation. Tokens
+/// revoked by other means can be dropped with
+/// [`invalidate`](Self::invalidate).
+///
+/// # Example
+/// ```
+/// use stroopwafel::Stroopwafel;
+/// use stroopwafel::cache::VerificationCache;
+/// use stroopwafel::verifier::ContextVerifier;
+///
+/// let root_key = b"secret";
+/// let mut token = Stroopwafel::new(root_key, b"identifier", None::<String>);
+/// token.add_first_party_caveat(b"account = alice");
+///
+/// let cache = VerificationCache::new(10_000);
+/// let verifier = ContextVerifier::empty().with("account", "alice");
+///
+/// // The first presentation calls the verifier, later ones reuse its answer
+/// assert!(cache.verify(&token, root_key, &verifier, &[], b"alice").is_ok());
+/// assert!(cache.verify(&token, root_key, &verifier, &[], b"alice").is_ok());
+/// assert_eq!(cache.len(), 1);
+/// ```
+pub struct VerificationCache {
+    entries: Mutex<LruCache<Key, Entry>>,
+    options: VerifyOptions,
+    max_ttl: Option<Duration>,
+    clock: Box<dyn Clock>,
+}
+
+struct Entry {
+    /// Identifiers of the token and its discharges
+    identifiers: Vec<Vec<u8>>,
+    expires_at: Option<SystemTime>,
+}
+
+impl Entry {
+    fn is_live(&self, now: SystemTime) -> bool {
+        self.expires_at.is_none_or(|expires_at| now < expires_at)
+    }
+}
+
+impl VerificationCache {
+    /// Creates an empty cache holding at most `capacity` entries (at least
+    /// one), verifying with the default options
+    pub fn new(capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            entries: Mutex::new(LruCache::new(capacity)),
+            options: VerifyOptions::default(),
+            max_ttl: None,
+            clock: Box::new(SystemClock),
+        }
+    }
+
+    /// Verifies every token with `options`
+    pub fn with_options(mut self, options: VerifyOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Returns the options every token is verified with
+    pub fn options(&self) -> &VerifyOptions {
+        &self.options
+    }
+
+    /// Keeps entries for at most `ttl`, even for tokens that expire later or
+    /// never
+    pub fn with_max_ttl(mut self, ttl: Duration) -> Self {
+        self.max_ttl = Some(ttl);
+        self
+    }
+
+    /// Returns the longest time an entry is kept, if limited
+    pub fn max_ttl(&self) -> Option<Duration> {
+        self.max_ttl
+    }
+
+    /// Reads the time that decides when entries expire from `clock`
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// Returns the number of cached entries, including expired ones not yet
+    /// evicted
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// Returns true if nothing is cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops every entry
+    pub fn clear(&self) {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+
+    /// Drops the entries of tokens with `identifier`, or bound to a discharge
+    /// with it, returning how many were dropped
+    pub fn invalidate(&self, identifier: &[u8]) -> usize {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let stale: Vec<Key> = entries
+            .iter()
+            .filter(|(_, entry)| entry.identifiers.iter().any(|i| i == identifier))
+            .map(|(key, _)| *key)
+            .collect();
+        for key in &stale {
+            entries.pop(key);
+        }
+        stale.len()
+    }
+
+    /// Verifies `stroopwafel` like
+    /// [`Stroopwafel::verify_with_options`], calling `verifier` only if no
+    /// live entry exists for the token, its discharges and `fingerprint`
+    pub fn verify(
+        &self,
+        stroopwafel: &Stroopwafel,
+        root_key: &[u8],
+        verifier: &impl CaveatVerifier,
+        discharges: &[Stroopwafel],
+        fingerprint: &[u8],
+    ) -> Result<()> {
+        let key = cache_key(stroopwafel, root_key, discharges, fingerprint);
+        let now = self.clock.now();
+
+        if self.lookup(&key, now) {
+            // The verifier accepted these exact caveats in this context;
+            // everything else is checked again
+            let result = stroopwafel.verify_with_options(
+                root_key,
+                &AcceptAllVerifier,
+                discharges,
+                &self.options,
+            );
+            if result.is_err() {
+                self.remove(&key);
+            }
+            return result;
+        }
+
+        stroopwafel.verify_with_options(root_key, verifier, discharges, &self.options)?;
+
+        let entry = Entry {
+            identifiers: std::iter::once(stroopwafel)
+                .chain(discharges)
+                .map(|token| token.identifier.clone())
+                .collect(),
+            expires_at: self.expiry(stroopwafel, discharges, now),
+        };
+        if entry.is_live(now) {
+            self.entries
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .put(key, entry);
+        }
+        Ok(())
+    }
+
+    /// Returns true if a live entry exists for `key`, dropping it if it has
+    /// expired
+    fn lookup(&self, key: &Key, now: SystemTime) -> bool {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        match entries.get(key) {
+            Some(entry) if entry.is_live(now) => true,
+            Some(_) => {
+                entries.pop(key);
+                false
+            }
+            None => false,
+        }
+    }
+
+    fn remove(&self, key: &Key) {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .pop(key);
+    }
+
+    /// Returns when an entry for a token verified at `now` expires: at its
+    /// earliest expiry caveat, capped by the maximum TTL
+    fn expiry(
+        &self,
+        stroopwafel: &Stroopwafel,
+        discharges: &[Stroopwafel],
+        now: SystemTime,
+    ) -> Option<SystemTime> {
+        let token_expiry = std::iter::once(stroopwafel)
+            .chain(discharges)
+            .flat_map(|token| &token.caveats)
+            .filter(|caveat| caveat.is_first_party())
+            .filter_map(|caveat| ParsedCaveat::parse(&caveat.caveat_id).ok()?.expiry())
+            .min()
+            .map(|expiry| expiry.to_system_time().unwrap_or(SystemTime::UNIX_EPOCH));
+        let ttl_expiry = self.max_ttl.and_then(|ttl| now.checked_add(ttl));
+
+        match (token_expiry, ttl_expiry) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+/// Derives the cache key of a presentation, keyed by the root key so tokens
+/// are never matched across keys
+fn cache_key(
+    stroopwafel: &Stroopwafel,
+    root_key: &[u8],
+    discharges: &[Stroopwafel],
+    fingerprint: &[u8],
+) -> Key {
+    let mut message = Vec::with_capacity(
+        SIGNATURE_SIZE * (discharges.len() + 1) + size_of::<u64>() + fingerprint.len(),
+    );
+    message.extend_from_slice(&stroopwafel.signature);
+    message.extend_from_slice(&(discharges.len() as u64).to_be_bytes());
+    for discharge in discharges {
+        message.extend_from_slice(&discharge.signature);
+    }
+    message.extend_from_slice(fingerprint);
+    hmac_sha3(root_key, &message)
+}
+
+impl fmt::Debug for VerificationCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VerificationCache")
+            .field("len", &self.len())
+            .field("options", &self.options)
+            .field("max_ttl", &self.max_ttl)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StroopwafelError;
+    use crate::clock::ManualClock;
+    use crate::revocation::{MemoryRevocationStore, RevocationStore};
+    use crate::verifier::Verifier;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const ROOT_KEY: &[u8] = b"root_secret";
+
+    /// Accepts every caveat, counting the calls
+    #[derive(Default)]
+    struct CountingVerifier {
+        calls: AtomicUsize,
+    }
+
+    impl CountingVerifier {
+        fn calls(&self) -> usize {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    impl Verifier for CountingVerifier {
+        fn verify_caveat(&self, _predicate: &[u8]) -> Result<()> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    fn token(caveats: &[&str]) -> Stroopwafel {
+        let mut token = Stroopwafel::new(ROOT_KEY, b"identifier", None::<String>);
+        for caveat in caveats {
+            token.add_first_party_caveat(caveat.as_bytes());
+        }
+        token
+    }
+
+    #[test]
+    fn test_hits_skip_the_verifier() {
+        let cache = VerificationCache::new(10);
+        let verifier = CountingVerifier::default();
+        let token = token(&["account = alice"]);
+
+        for _ in 0..3 {
+            assert!(
+                cache
+                    .verify(&token, ROOT_KEY, &verifier, &[], b"ctx")
+                    .is_ok()
+            );
+        }
+        assert_eq!(verifier.calls(), 1);
+
+        // Another context, or another root key, is a miss
+        assert!(
+            cache
+                .verify(&token, ROOT_KEY, &verifier, &[], b"other")
+                .is_ok()
+        );
+        assert_eq!(verifier.calls(), 2);
+        assert_eq!(
+            cache.verify(&token, b"wrong", &verifier, &[], b"ctx"),
+            Err(StroopwafelError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_failures_are_not_cached() {
+        let cache = VerificationCache::new(10);
+        let token = token(&["account = alice"]);
+        let bob = crate::verifier::ContextVerifier::empty().with("account", "bob");
+
+        assert!(cache.verify(&token, ROOT_KEY, &bob, &[], b"ctx").is_err());
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_altered_token_with_cached_signature_fails() {
+        let cache = VerificationCache::new(10);
+        let verifier = CountingVerifier::default();
+        let token = token(&["account = alice", "action = read"]);
+        assert!(
+            cache
+                .verify(&token, ROOT_KEY, &verifier, &[], b"ctx")
+                .is_ok()
+        );
+
+        let mut stripped = token.clone();
+        stripped.caveats.pop();
+        assert_eq!(
+            cache.verify(&stripped, ROOT_KEY, &verifier, &[], b"ctx"),
+            Err(StroopwafelError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_discharges_are_part_of_the_key() {
+        let verification_key = b"verification_secret";
+        let mut primary = token(&[]);
+        primary.add_third_party_caveat(b"auth", verification_key, "https://auth.example.com");
+
+        let discharge = |account: &str| {
+            let mut discharge =
+                Stroopwafel::create_discharge(verification_key, b"auth", None::<String>);
+            discharge.add_first_party_caveat(format!("account = {account}").as_bytes());
+            primary.bind_discharge(&discharge)
+        };
+
+        let cache = VerificationCache::new(10);
+        let verifier = CountingVerifier::default();
+        let alice = [discharge("alice")];
+        let bob = [discharge("bob")];
+        assert!(
+            cache
+                .verify(&primary, ROOT_KEY, &verifier, &alice, b"ctx")
+                .is_ok()
+        );
+        assert!(
+            cache
+                .verify(&primary, ROOT_KEY, &verifier, &bob, b"ctx")
+                .is_ok()
+        );
+        assert_eq!(verifier.calls(), 2);
+        assert_eq!(cache.len(), 2);
+
+        assert_eq!(cache.invalidate(b"auth"), 2);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_entries_expire_with_the_token() {
+        let clock = Arc::new(ManualClock::at_unix(100));
+        let cache = VerificationCache::new(10).with_clock(Arc::clone(&clock));
+        let verifier = CountingVerifier::default();
+        let token = token(&["time < 200"]);
+
+        assert!(
+            cache
+                .verify(&token, ROOT_KEY, &verifier, &[], b"ctx")
+                .is_ok()
+        );
+        assert!(
+            cache
+                .verify(&token, ROOT_KEY, &verifier, &[], b"ctx")
+                .is_ok()
+        );
+        assert_eq!(verifier.calls(), 1);
+
+        clock.advance(Duration::from_secs(100));
+        assert!(
+            cache
+                .verify(&token, ROOT_KEY, &verifier, &[], b"ctx")
+                .is_ok()
+        );
+        assert_eq!(verifier.calls(), 2);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_max_ttl() {
+        let clock = Arc::new(ManualClock::at_unix(100));
+        let cache = VerificationCache::new(10)
+            .with_clock(Arc::clone(&clock))
+            .with_max_ttl(Duration::from_secs(30));
+        let verifier = CountingVerifier::default();
+        let token = token(&["account = alice"]);
+
+        assert!(
+            cache
+                .verify(&token, ROOT_KEY, &verifier, &[], b"ctx")
+                .is_ok()
+        );
+        clock.advance(Duration::from_secs(29));
+        assert!(
+            cache
+                .verify(&token, ROOT_KEY, &verifier, &[], b"ctx")
+                .is_ok()
+        );
+        assert_eq!(verifier.calls(), 1);
+
+        clock.advance(Duration::from_secs(1));
+        assert!(
+            cache
+                .verify(&token, ROOT_KEY, &verifier, &[], b"ctx")
+                .is_ok()
+        );
+        assert_eq!(verifier.calls(), 2);
+    }
+
+    #[test]
+    fn test_revocation_invalidates_entries() {
+        let store = Arc::new(MemoryRevocationStore::new());
+        let cache = VerificationCache::new(10)
+            .with_options(VerifyOptions::new().with_revocation_store(store.clone()));
+        let verifier = CountingVerifier::default();
+        let token = token(&["account = alice"]);
+
+        assert!(
+            cache
+                .verify(&token, ROOT_KEY, &verifier, &[], b"ctx")
+                .is_ok()
+        );
+        store.revoke(b"identifier", None).unwrap();
+        assert_eq!(
+            cache.verify(&token, ROOT_KEY, &verifier, &[], b"ctx"),
+            Err(StroopwafelError::Revoked {
+                identifier: b"identifier".to_vec()
+            })
+        );
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_least_recently_used_entry_is_evicted() {
+        let cache = VerificationCache::new(2);
+        let verifier = CountingVerifier::default();
+        let token = token(&["account = alice"]);
+
+        for fingerprint in [b"a", b"b", b"a", b"c"] {
+            assert!(
+                cache
+                    .verify(&token, ROOT_KEY, &verifier, &[], fingerprint)
+                    .is_ok()
+            );
+        }
+        assert_eq!(cache.len(), 2);
+        assert_eq!(verifier.calls(), 3);
+
+        // "b" was evicted, "a" was kept
+        assert!(cache.verify(&token, ROOT_KEY, &verifier, &[], b"a").is_ok());
+        assert_eq!(verifier.calls(), 3);
+        assert!(cache.verify(&token, ROOT_KEY, &verifier, &[], b"b").is_ok());
+        assert_eq!(verifier.calls(), 4);
+    }
+}
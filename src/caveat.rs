@@ -1,4 +1,11 @@
+use crate::predicate::{MissingKeyPolicy, Operator, Predicate};
+use crate::restriction::TIME_KEY;
+use crate::structured::{StructuredCaveat, Value};
+use crate::timestamp::Timestamp;
+use crate::{Result, StroopwafelError};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
 
 /// A caveat represents a restriction on the authorization granted by a macaroon.
 /// Caveats can be either first-party (verified by the service) or third-party
@@ -22,6 +29,100 @@ pub struct Caveat {
     pub location: Option<String>,
 }
 
+/// The kind of a caveat
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaveatKind {
+    /// Verified locally by a verifier
+    FirstParty,
+    /// Verified through a discharge macaroon
+    ThirdParty,
+}
+
+/// Identifies which macaroon a caveat belongs to during verification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaveatSource {
+    /// The primary stroopwafel being verified
+    Primary,
+    /// A discharge macaroon
+    Discharge {
+        /// Position of the discharge in the slice passed to verification
+        index: usize,
+        /// The discharge identifier (the third-party caveat it discharges)
+        identifier: Vec<u8>,
+    },
+}
+
+/// A first-party caveat together with its position, handed to a
+/// [`CaveatVerifier`](crate::verifier::CaveatVerifier)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CaveatContext<'a> {
+    /// The caveat predicate
+    pub predicate: &'a [u8],
+    /// Position of the caveat within its macaroon
+    pub index: usize,
+    /// The discharge holding the caveat, or `None` for caveats of the primary
+    pub discharge: Option<DischargeContext<'a>>,
+}
+
+/// The discharge macaroon a caveat belongs to
+///
+/// The verification key id is bound into the primary stroopwafel's
+/// signature, and the discharge, identifier included, is signed with the key
+/// it stands for, so these say who issued the discharge. The location is not
+/// covered by any signature: a holder can rewrite it, so it must never decide
+/// which rules a discharge's caveats are checked against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DischargeContext<'a> {
+    /// Position of the discharge in the slice passed to verification
+    pub index: usize,
+    /// The discharge identifier (the third-party caveat it discharges)
+    pub identifier: &'a [u8],
+    /// The verification key id of the third-party caveat the discharge
+    /// satisfies, naming the key the discharge is signed with
+    pub verification_key_id: &'a [u8],
+    /// The location of the third-party caveat the discharge satisfies, i.e.
+    /// where the discharge was meant to be obtained
+    ///
+    /// Unauthenticated; use it as a hint only.
+    pub location: Option<&'a str>,
+}
+
+impl<'a> CaveatContext<'a> {
+    /// Creates the context of a caveat of the primary stroopwafel
+    pub fn primary(predicate: &'a [u8], index: usize) -> Self {
+        Self {
+            predicate,
+            index,
+            discharge: None,
+        }
+    }
+
+    /// Creates the context of a caveat of a discharge macaroon
+    pub fn discharge(predicate: &'a [u8], index: usize, discharge: DischargeContext<'a>) -> Self {
+        Self {
+            predicate,
+            index,
+            discharge: Some(discharge),
+        }
+    }
+
+    /// Returns true if the caveat belongs to a discharge macaroon
+    pub fn is_discharge(&self) -> bool {
+        self.discharge.is_some()
+    }
+
+    /// Returns the macaroon the caveat belongs to
+    pub fn source(&self) -> CaveatSource {
+        match self.discharge {
+            None => CaveatSource::Primary,
+            Some(discharge) => CaveatSource::Discharge {
+                index: discharge.index,
+                identifier: discharge.identifier.to_vec(),
+            },
+        }
+    }
+}
+
 impl Caveat {
     /// Creates a new first-party caveat
     pub fn first_party(caveat_id: impl Into<Vec<u8>>) -> Self {
@@ -54,6 +155,166 @@ impl Caveat {
     pub fn is_third_party(&self) -> bool {
         !self.is_first_party()
     }
+
+    /// Returns the kind of this caveat
+    pub fn kind(&self) -> CaveatKind {
+        if self.is_first_party() {
+            CaveatKind::FirstParty
+        } else {
+            CaveatKind::ThirdParty
+        }
+    }
+
+    /// Returns true if this is a first-party caveat in the structured encoding
+    pub fn is_structured(&self) -> bool {
+        self.is_first_party() && StructuredCaveat::is_structured(&self.caveat_id)
+    }
+
+    /// Borrows the fields verification reads
+    pub(crate) fn parts(&self) -> CaveatParts<'_> {
+        CaveatParts {
+            caveat_id: &self.caveat_id,
+            verification_key_id: self.verification_key_id.as_deref(),
+            location: self.location.as_deref(),
+        }
+    }
+}
+
+/// The fields of a caveat, borrowed from an owned [`Caveat`] or a
+/// [`CaveatRef`](crate::view::CaveatRef)
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CaveatParts<'a> {
+    pub(crate) caveat_id: &'a [u8],
+    pub(crate) verification_key_id: Option<&'a [u8]>,
+    pub(crate) location: Option<&'a str>,
+}
+
+impl<'a> CaveatParts<'a> {
+    /// Returns true if this is a first-party caveat
+    pub(crate) fn is_first_party(&self) -> bool {
+        self.verification_key_id.is_none() && self.location.is_none()
+    }
+
+    /// Returns the bytes the caveat binds into the signature chain: the
+    /// caveat ID for first-party caveats, the verification key ID otherwise
+    pub(crate) fn chain_element(self) -> Option<&'a [u8]> {
+        if self.is_first_party() {
+            Some(self.caveat_id)
+        } else {
+            self.verification_key_id
+        }
+    }
+}
+
+/// A first-party caveat parsed with the grammar understood by
+/// [`ContextVerifier`](crate::verifier::ContextVerifier)
+///
+/// Caveat bytes are either a UTF-8 text predicate or a [`StructuredCaveat`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParsedCaveat {
+    /// A text predicate such as `"account = alice"`
+    Predicate(Predicate),
+    /// A binary structured caveat
+    Structured(StructuredCaveat),
+}
+
+impl ParsedCaveat {
+    /// Parses first-party caveat bytes
+    ///
+    /// # Errors
+    /// Returns `StroopwafelError::InvalidFormat` (or `Decode` for a corrupt
+    /// structured caveat) if the bytes are not a valid predicate.
+    pub fn parse(caveat_id: &[u8]) -> Result<Self> {
+        if StructuredCaveat::is_structured(caveat_id) {
+            return StructuredCaveat::decode(caveat_id).map(ParsedCaveat::Structured);
+        }
+
+        let predicate_str = std::str::from_utf8(caveat_id)
+            .map_err(|e| StroopwafelError::InvalidFormat(e.to_string()))?;
+
+        Predicate::parse(predicate_str).map(ParsedCaveat::Predicate)
+    }
+
+    /// Returns the context key this caveat restricts
+    pub fn key(&self) -> &str {
+        match self {
+            ParsedCaveat::Predicate(predicate) => &predicate.key,
+            ParsedCaveat::Structured(caveat) => &caveat.key,
+        }
+    }
+
+    /// Returns the comparison operator
+    pub fn operator(&self) -> Operator {
+        match self {
+            ParsedCaveat::Predicate(predicate) => predicate.operator,
+            ParsedCaveat::Structured(caveat) => caveat.operator,
+        }
+    }
+
+    /// Evaluate this caveat against a context
+    pub fn evaluate(&self, context: &HashMap<String, String>) -> bool {
+        match self {
+            ParsedCaveat::Predicate(predicate) => predicate.evaluate(context),
+            ParsedCaveat::Structured(caveat) => caveat.evaluate(context),
+        }
+    }
+
+    /// Evaluate this caveat against the context value of its key
+    pub fn evaluate_value(&self, actual: &str) -> bool {
+        match self {
+            ParsedCaveat::Predicate(predicate) => predicate.evaluate_value(actual),
+            ParsedCaveat::Structured(caveat) => caveat.evaluate_value(actual),
+        }
+    }
+
+    /// Returns the instant this caveat expires the token at, for expiry
+    /// caveats (`time <` or `time <=`)
+    pub(crate) fn expiry(&self) -> Option<Timestamp> {
+        if self.key() != TIME_KEY
+            || !matches!(
+                self.operator(),
+                Operator::LessThan | Operator::LessThanOrEqual
+            )
+        {
+            return None;
+        }
+
+        match self {
+            ParsedCaveat::Predicate(predicate) => Timestamp::parse(&predicate.value),
+            ParsedCaveat::Structured(caveat) => match &caveat.value {
+                Value::Integer(seconds) => Some(Timestamp::from_unix_seconds(*seconds)),
+                Value::String(value) => Timestamp::parse_rfc3339(value),
+                Value::Boolean(_) => None,
+            },
+        }
+    }
+
+    /// Evaluate this caveat for a key that has no value in the context
+    ///
+    /// # Errors
+    /// Returns `StroopwafelError::MissingContext` under
+    /// [`MissingKeyPolicy::Error`], unless the operator tests for presence.
+    pub fn evaluate_missing(&self, policy: MissingKeyPolicy) -> Result<bool> {
+        self.operator().evaluate_missing(self.key(), policy)
+    }
+}
+
+impl fmt::Display for ParsedCaveat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParsedCaveat::Predicate(predicate) => write!(f, "{predicate}"),
+            ParsedCaveat::Structured(caveat) => write!(f, "{caveat}"),
+        }
+    }
+}
+
+/// Renders caveat bytes for messages: structured caveats in their text form,
+/// anything else lossily decoded as UTF-8
+pub(crate) fn describe_caveat(caveat_id: &[u8]) -> String {
+    match StructuredCaveat::decode(caveat_id) {
+        Ok(caveat) => caveat.to_string(),
+        Err(_) => String::from_utf8_lossy(caveat_id).into_owned(),
+    }
 }
 
 #[cfg(test)]
@@ -86,4 +347,27 @@ mod tests {
             Some("https://auth.example.com".to_string())
         );
     }
+
+    #[test]
+    fn test_parsed_caveat_text() {
+        let parsed = ParsedCaveat::parse(b"account = alice").unwrap();
+        assert!(matches!(parsed, ParsedCaveat::Predicate(_)));
+        assert_eq!(parsed.key(), "account");
+        assert_eq!(parsed.operator(), Operator::Equal);
+    }
+
+    #[test]
+    fn test_parsed_caveat_structured() {
+        let bytes = StructuredCaveat::new("count", Operator::LessThan, 10).encode();
+        let parsed = ParsedCaveat::parse(&bytes).unwrap();
+        assert!(matches!(parsed, ParsedCaveat::Structured(_)));
+        assert_eq!(parsed.key(), "count");
+        assert_eq!(parsed.operator(), Operator::LessThan);
+    }
+
+    #[test]
+    fn test_parsed_caveat_invalid() {
+        assert!(ParsedCaveat::parse(b"acount alice").is_err());
+        assert!(ParsedCaveat::parse(&[0xff, 0xfe]).is_err());
+    }
 }
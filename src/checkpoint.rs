@@ -0,0 +1,288 @@
+use crate::Stroopwafel;
+use crate::crypto::{SIGNATURE_SIZE, bind_caveat, hmac_sha3, signatures_equal};
+use crate::stroopwafel::Token;
+use lru::LruCache;
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::sync::{Mutex, MutexGuard};
+use subtle::ConstantTimeEq;
+
+type Signature = [u8; SIGNATURE_SIZE];
+
+/// Remembers the chain signatures of verified tokens, so tokens that extend
+/// them are authenticated from the longest known prefix
+///
+/// Presented tokens are usually a base token minted by the service plus a
+/// few caveats added by the client. Once a token's signature has verified,
+/// each of its intermediate chain signatures (identifier plus the first N
+/// caveats) is known to be authentic. Attached to
+/// [`VerifyOptions`](crate::options::VerifyOptions), the cache lets
+/// verification resume the HMAC chain from the longest cached prefix instead
+/// of from the root key. Prefixes are matched on their exact bytes, and only
+/// chains that verified in full are cached.
+///
+/// Only the signature check is shortened: every caveat is still evaluated,
+/// and discharges are checked as usual.
+///
+/// A cache belongs to one root key; verification with any other key ignores
+/// it. It holds at most `capacity` checkpoints and evicts the least recently
+/// used when full, so base tokens in use stay cached while one-off
+/// attenuations age out.
+///
+/// # Example
+/// ```
+/// use stroopwafel::Stroopwafel;
+/// use stroopwafel::checkpoint::CheckpointCache;
+/// use stroopwafel::options::VerifyOptions;
+/// use stroopwafel::verifier::AcceptAllVerifier;
+/// use std::sync::Arc;
+///
+/// let root_key = b"secret";
+/// let mut base = Stroopwafel::new(root_key, b"identifier", None::<String>);
+/// base.add_first_party_caveat(b"account = alice");
+///
+/// let cache = Arc::new(CheckpointCache::new(root_key, 10_000));
+/// assert!(cache.warm(&base));
+///
+/// // A client attenuates the token; only the new caveat is hashed
+/// let mut presented = base.clone();
+/// presented.add_first_party_caveat(b"action = read");
+///
+/// let options = VerifyOptions::new().with_checkpoint_cache(cache);
+/// assert!(presented.verify_with_options(root_key, &AcceptAllVerifier, &[], &options).is_ok());
+/// ```
+pub struct CheckpointCache {
+    root_key: Vec<u8>,
+    checkpoints: Mutex<LruCache<Key, Signature>>,
+}
+
+/// A chain prefix: the identifier alone, or the prefix with the given
+/// signature extended by one caveat
+#[derive(PartialEq, Eq, Hash)]
+enum Key {
+    Root(Vec<u8>),
+    Extension(Signature, Vec<u8>),
+}
+
+impl CheckpointCache {
+    /// Creates an empty cache for tokens minted with `root_key`, holding at
+    /// most `capacity` checkpoints (at least one)
+    pub fn new(root_key: &[u8], capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            root_key: root_key.to_vec(),
+            checkpoints: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    /// Returns the maximum number of checkpoints
+    pub fn capacity(&self) -> usize {
+        self.lock().cap().get()
+    }
+
+    /// Returns the number of cached checkpoints
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns true if nothing is cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops every checkpoint
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Verifies the signature of `stroopwafel` under the cache's root key,
+    /// caching its chain if it is authentic
+    ///
+    /// Call this on freshly minted base tokens to have tokens derived from
+    /// them verify faster from the start.
+    pub fn warm(&self, stroopwafel: &Stroopwafel) -> bool {
+        self.signature_matches(stroopwafel, &self.root_key)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, LruCache<Key, Signature>> {
+        self.checkpoints.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns true if the signature chain of `token` is valid under
+    /// `root_key`, resuming from the longest cached prefix
+    pub(crate) fn signature_matches(&self, token: &impl Token, root_key: &[u8]) -> bool {
+        if !bool::from(self.root_key.as_slice().ct_eq(root_key)) {
+            return token.signature_matches(root_key);
+        }
+
+        let identifier = token.identifier();
+        let elements: Vec<&[u8]> = token.chain_elements().collect();
+
+        let cached = self.longest_prefix(identifier, &elements);
+        let (depth, start) = cached.unwrap_or_else(|| (0, hmac_sha3(root_key, identifier)));
+
+        // chain[n] is the signature after the cached prefix and n more caveats
+        let mut chain = Vec::with_capacity(elements.len() - depth + 1);
+        chain.push(start);
+        for element in &elements[depth..] {
+            chain.push(bind_caveat(&chain[chain.len() - 1], element));
+        }
+
+        if !signatures_equal(&chain[chain.len() - 1], token.signature()) {
+            return false;
+        }
+
+        let mut checkpoints = self.lock();
+        // Shorter prefixes are shared by more tokens, so they go in last and
+        // are evicted last
+        for (n, element) in elements[depth..].iter().enumerate().rev() {
+            checkpoints.put(Key::Extension(chain[n], element.to_vec()), chain[n + 1]);
+        }
+        if cached.is_none() {
+            checkpoints.put(Key::Root(identifier.to_vec()), start);
+        }
+        true
+    }
+
+    /// Finds the longest cached prefix of a chain, returning the number of
+    /// caveats it covers and its signature
+    fn longest_prefix(&self, identifier: &[u8], elements: &[&[u8]]) -> Option<(usize, Signature)> {
+        let mut checkpoints = self.lock();
+        let mut signature = *checkpoints.get(&Key::Root(identifier.to_vec()))?;
+        let mut depth = 0;
+        while let Some(element) = elements.get(depth)
+            && let Some(extension) = checkpoints.get(&Key::Extension(signature, element.to_vec()))
+        {
+            signature = *extension;
+            depth += 1;
+        }
+        Some((depth, signature))
+    }
+}
+
+impl fmt::Debug for CheckpointCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CheckpointCache")
+            .field("capacity", &self.capacity())
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROOT_KEY: &[u8] = b"root_secret";
+
+    fn token(caveats: &[&str]) -> Stroopwafel {
+        let mut token = Stroopwafel::new(ROOT_KEY, b"identifier", None::<String>);
+        for caveat in caveats {
+            token.add_first_party_caveat(caveat.as_bytes());
+        }
+        token
+    }
+
+    #[test]
+    fn test_warm_caches_every_prefix() {
+        let cache = CheckpointCache::new(ROOT_KEY, 100);
+        assert!(cache.is_empty());
+
+        assert!(cache.warm(&token(&["a = 1", "b = 2"])));
+        assert_eq!(cache.len(), 3);
+
+        // Shared prefixes are stored once
+        assert!(cache.warm(&token(&["a = 1", "c = 3"])));
+        assert_eq!(cache.len(), 4);
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_extension_of_cached_prefix() {
+        let cache = CheckpointCache::new(ROOT_KEY, 100);
+        let base = token(&["a = 1"]);
+        assert!(cache.warm(&base));
+
+        let mut extended = base.clone();
+        extended.add_first_party_caveat(b"b = 2");
+        assert!(cache.signature_matches(&extended, ROOT_KEY));
+
+        assert_eq!(
+            cache
+                .longest_prefix(b"identifier", &[b"a = 1", b"b = 2"])
+                .map(|p| p.0),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn test_forgeries_are_rejected_and_not_cached() {
+        let cache = CheckpointCache::new(ROOT_KEY, 100);
+        assert!(cache.warm(&token(&["a = 1"])));
+
+        // Swapping a caveat of a cached prefix
+        let mut forged = token(&["a = 1", "b = 2"]);
+        forged.caveats[0].caveat_id = b"a = 100".to_vec();
+        assert!(!cache.signature_matches(&forged, ROOT_KEY));
+
+        // Extending a cached prefix without the signature
+        let mut extended = token(&["a = 1"]);
+        extended.caveats.push(crate::Caveat::first_party(b"b = 2"));
+        assert!(!cache.signature_matches(&extended, ROOT_KEY));
+
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_other_root_keys_bypass_the_cache() {
+        let cache = CheckpointCache::new(ROOT_KEY, 100);
+        let other = Stroopwafel::new(b"other", b"identifier", None::<String>);
+        assert!(cache.signature_matches(&other, b"other"));
+        assert!(!cache.signature_matches(&other, ROOT_KEY));
+        assert!(cache.is_empty());
+
+        // A token minted under the cached key does not verify under another
+        assert!(cache.warm(&token(&[])));
+        assert!(!cache.signature_matches(&token(&[]), b"other"));
+    }
+
+    #[test]
+    fn test_capacity_is_respected() {
+        let cache = CheckpointCache::new(ROOT_KEY, 2);
+        assert!(cache.warm(&token(&["a = 1", "b = 2", "c = 3"])));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(
+            cache
+                .longest_prefix(b"identifier", &[b"a = 1", b"b = 2"])
+                .map(|p| p.0),
+            Some(1)
+        );
+
+        // Verification still works beyond the cached prefixes
+        assert!(cache.signature_matches(&token(&["a = 1", "b = 2", "c = 3"]), ROOT_KEY));
+        assert!(cache.signature_matches(&token(&["x = 1"]), ROOT_KEY));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_new_prefixes_are_cached_when_full() {
+        let cache = CheckpointCache::new(ROOT_KEY, 4);
+        assert!(cache.warm(&token(&["a = 1"])));
+
+        // Fill the cache with one-off attenuations of the base token
+        for i in 0..10 {
+            assert!(cache.signature_matches(&token(&["a = 1", &format!("x = {i}")]), ROOT_KEY));
+        }
+        assert_eq!(cache.len(), 4);
+
+        assert!(cache.signature_matches(&token(&["a = 1", "b = 2"]), ROOT_KEY));
+        assert_eq!(
+            cache
+                .longest_prefix(b"identifier", &[b"a = 1", b"b = 2"])
+                .map(|p| p.0),
+            Some(2)
+        );
+    }
+}
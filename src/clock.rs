@@ -0,0 +1,121 @@
+use crate::{Result, StroopwafelError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A source of the current time for time-based caveats
+///
+/// Verification and minting code read the time through a clock instead of
+/// calling [`SystemTime::now`] directly, so expiry logic can be tested with a
+/// [`ManualClock`].
+pub trait Clock: Send + Sync {
+    /// Returns the current time
+    fn now(&self) -> SystemTime;
+}
+
+/// The system wall clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock that only moves when told to, for tests
+///
+/// # Example
+/// ```
+/// use stroopwafel::clock::{Clock, ManualClock, unix_seconds};
+/// use std::time::Duration;
+///
+/// let clock = ManualClock::at_unix(1_700_000_000);
+/// clock.advance(Duration::from_secs(60));
+/// assert_eq!(unix_seconds(clock.now()).unwrap(), 1_700_000_060);
+/// ```
+#[derive(Debug)]
+pub struct ManualClock {
+    now: Mutex<SystemTime>,
+}
+
+impl ManualClock {
+    /// Creates a clock stopped at `now`
+    pub fn new(now: SystemTime) -> Self {
+        Self {
+            now: Mutex::new(now),
+        }
+    }
+
+    /// Creates a clock stopped at `secs` Unix seconds
+    pub fn at_unix(secs: u64) -> Self {
+        Self::new(UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    /// Moves the clock to `now`
+    pub fn set(&self, now: SystemTime) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) = now;
+    }
+
+    /// Moves the clock forward by `duration`
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap_or_else(|e| e.into_inner());
+        *now += duration;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now(&self) -> SystemTime {
+        self.as_ref().now()
+    }
+}
+
+/// Converts a system time to whole Unix seconds
+///
+/// # Errors
+/// Returns `StroopwafelError::InvalidTime` if `time` is before the Unix epoch.
+pub fn unix_seconds(time: SystemTime) -> Result<u64> {
+    time.duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .map_err(|_| StroopwafelError::InvalidTime("time is before the Unix epoch".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manual_clock() {
+        let clock = ManualClock::at_unix(100);
+        assert_eq!(unix_seconds(clock.now()).unwrap(), 100);
+
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(unix_seconds(clock.now()).unwrap(), 105);
+
+        clock.set(UNIX_EPOCH + Duration::from_secs(42));
+        assert_eq!(unix_seconds(clock.now()).unwrap(), 42);
+    }
+
+    #[test]
+    fn test_shared_clock() {
+        let clock = Arc::new(ManualClock::at_unix(100));
+        let shared: Box<dyn Clock> = Box::new(Arc::clone(&clock));
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(unix_seconds(shared.now()).unwrap(), 101);
+    }
+
+    #[test]
+    fn test_unix_seconds_before_epoch() {
+        let before = UNIX_EPOCH - Duration::from_secs(1);
+        assert!(matches!(
+            unix_seconds(before),
+            Err(StroopwafelError::InvalidTime(_))
+        ));
+    }
+}
@@ -0,0 +1,186 @@
+use crate::caveat::ParsedCaveat;
+use crate::predicate::Operator;
+use crate::restriction::{EPOCH_KEY_PREFIX, Restriction};
+use crate::structured::Value;
+use crate::{Result, StroopwafelError};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// Per-subject generation counters for mass revocation
+///
+/// Minting stamps the subject's current epoch into the token with
+/// [`stamp`](Self::stamp). Verification with the store attached to
+/// [`VerifyOptions`](crate::options::VerifyOptions) rejects tokens stamped
+/// with an older epoch with `StroopwafelError::StaleEpoch`, so
+/// [`bump`](Self::bump) revokes every token of a subject at once ("log out
+/// everywhere") without tracking their identifiers.
+///
+/// Subjects that were never bumped are at epoch 0.
+///
+/// # Example
+/// ```
+/// use stroopwafel::Stroopwafel;
+/// use stroopwafel::epoch::{EpochStore, MemoryEpochStore};
+/// use stroopwafel::options::VerifyOptions;
+/// use stroopwafel::verifier::AcceptAllVerifier;
+/// use std::sync::Arc;
+///
+/// let store = Arc::new(MemoryEpochStore::new());
+/// let options = VerifyOptions::new().with_epoch_store(store.clone());
+///
+/// let root_key = b"secret";
+/// let mut token = Stroopwafel::new(root_key, b"identifier", None::<String>);
+/// token.add_restriction(&store.stamp("alice").unwrap());
+/// assert!(token.verify_with_options(root_key, &AcceptAllVerifier, &[], &options).is_ok());
+///
+/// store.bump("alice").unwrap();
+/// assert!(token.verify_with_options(root_key, &AcceptAllVerifier, &[], &options).is_err());
+/// ```
+pub trait EpochStore: Send + Sync {
+    /// Returns the current epoch of `subject`
+    fn current(&self, subject: &str) -> Result<u64>;
+
+    /// Advances the epoch of `subject`, returning the new epoch
+    ///
+    /// Tokens stamped with an earlier epoch no longer verify.
+    fn bump(&self, subject: &str) -> Result<u64>;
+
+    /// Returns a restriction stamping a token with the current epoch of
+    /// `subject`, or an error for subjects
+    /// [`Restriction::epoch`](crate::restriction::Restriction::epoch) rejects
+    fn stamp(&self, subject: &str) -> Result<Restriction> {
+        Restriction::epoch(subject, self.current(subject)?)
+    }
+}
+
+impl<S: EpochStore + ?Sized> EpochStore for Arc<S> {
+    fn current(&self, subject: &str) -> Result<u64> {
+        self.as_ref().current(subject)
+    }
+
+    fn bump(&self, subject: &str) -> Result<u64> {
+        self.as_ref().bump(subject)
+    }
+}
+
+/// An epoch store held in memory
+#[derive(Debug, Default)]
+pub struct MemoryEpochStore {
+    epochs: RwLock<HashMap<String, u64>>,
+}
+
+impl MemoryEpochStore {
+    /// Creates a store with every subject at epoch 0
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl EpochStore for MemoryEpochStore {
+    fn current(&self, subject: &str) -> Result<u64> {
+        let epochs = self.epochs.read().unwrap_or_else(|e| e.into_inner());
+        Ok(epochs.get(subject).copied().unwrap_or(0))
+    }
+
+    fn bump(&self, subject: &str) -> Result<u64> {
+        let mut epochs = self.epochs.write().unwrap_or_else(|e| e.into_inner());
+        let epoch = epochs.entry(subject.to_string()).or_insert(0);
+        *epoch = epoch
+            .checked_add(1)
+            .ok_or_else(|| StroopwafelError::Storage(format!("epoch of '{subject}' overflowed")))?;
+        Ok(*epoch)
+    }
+}
+
+/// Extracts the subject and epoch of an epoch caveat (`epoch:<subject> = N`)
+///
+/// Returns `None` for any other caveat, including malformed epoch caveats,
+/// which are left to the verifier.
+pub(crate) fn epoch_caveat(caveat_id: &[u8]) -> Option<(String, u64)> {
+    let caveat = ParsedCaveat::parse(caveat_id).ok()?;
+    let subject = caveat.key().strip_prefix(EPOCH_KEY_PREFIX)?;
+    if subject.is_empty() || caveat.operator() != Operator::Equal {
+        return None;
+    }
+
+    let epoch = match &caveat {
+        ParsedCaveat::Predicate(predicate) => predicate.value.parse().ok()?,
+        ParsedCaveat::Structured(caveat) => match caveat.value {
+            Value::Integer(epoch) => u64::try_from(epoch).ok()?,
+            _ => return None,
+        },
+    };
+    Some((subject.to_string(), epoch))
+}
+
+/// Checks an epoch caveat against the subject's current epoch
+pub(crate) fn check_epoch(store: &dyn EpochStore, subject: String, epoch: u64) -> Result<()> {
+    let current = store.current(&subject)?;
+    if epoch < current {
+        return Err(StroopwafelError::StaleEpoch {
+            subject,
+            epoch,
+            current,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structured::StructuredCaveat;
+
+    #[test]
+    fn test_memory_store_bump() {
+        let store = MemoryEpochStore::new();
+        assert_eq!(store.current("alice").unwrap(), 0);
+        assert_eq!(store.bump("alice").unwrap(), 1);
+        assert_eq!(store.bump("alice").unwrap(), 2);
+        assert_eq!(store.current("alice").unwrap(), 2);
+        assert_eq!(store.current("bob").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_stamp() {
+        let store = MemoryEpochStore::new();
+        store.bump("alice").unwrap();
+        assert_eq!(
+            store.stamp("alice").unwrap(),
+            Restriction::epoch("alice", 1).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_epoch_caveat() {
+        assert_eq!(
+            epoch_caveat(b"epoch:alice = 3"),
+            Some(("alice".to_string(), 3))
+        );
+        let structured = StructuredCaveat::new("epoch:bob", Operator::Equal, 7).encode();
+        assert_eq!(epoch_caveat(&structured), Some(("bob".to_string(), 7)));
+
+        assert_eq!(epoch_caveat(b"account = alice"), None);
+        assert_eq!(epoch_caveat(b"epoch: = 3"), None);
+        assert_eq!(epoch_caveat(b"epoch:alice >= 3"), None);
+        assert_eq!(epoch_caveat(b"epoch:alice = -1"), None);
+        assert_eq!(epoch_caveat(b"epoch:alice = three"), None);
+    }
+
+    #[test]
+    fn test_check_epoch() {
+        let store = MemoryEpochStore::new();
+        store.bump("alice").unwrap();
+
+        assert!(check_epoch(&store, "alice".to_string(), 1).is_ok());
+        assert!(check_epoch(&store, "alice".to_string(), 2).is_ok());
+        assert_eq!(
+            check_epoch(&store, "alice".to_string(), 0),
+            Err(StroopwafelError::StaleEpoch {
+                subject: "alice".to_string(),
+                epoch: 0,
+                current: 1,
+            })
+        );
+    }
+}
@@ -1,24 +1,164 @@
+use crate::caveat::CaveatSource;
+use std::error::Error as StdError;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
 
 /// Errors that can occur when working with stroopwafels
+///
+/// This enum is non-exhaustive: new variants may be added without a major
+/// version bump, so matches should include a wildcard arm.
 #[derive(Debug, Clone, Error, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum StroopwafelError {
     /// The stroopwafel's signature is invalid
     #[error("Invalid stroopwafel signature")]
     InvalidSignature,
 
     /// A caveat condition was violated
+    ///
+    /// Verifiers return this variant. [`Stroopwafel::verify`](crate::Stroopwafel::verify)
+    /// reports violations as [`CaveatViolated`](Self::CaveatViolated) instead,
+    /// adding the position of the caveat.
     #[error("Caveat violation: {0}")]
     CaveatViolation(String),
 
+    /// A caveat of the primary stroopwafel or of a discharge was violated
+    #[error("Caveat {index} ({predicate}) violated: {reason}")]
+    CaveatViolated {
+        /// Position of the caveat within its macaroon
+        index: usize,
+        /// The macaroon the caveat belongs to
+        origin: CaveatSource,
+        /// The caveat predicate, lossily decoded as UTF-8
+        predicate: String,
+        /// The reason given by the verifier
+        reason: String,
+    },
+
+    /// No verifier recognized a caveat, and unrecognized caveats are rejected
+    ///
+    /// See [`UnrecognizedPolicy`](crate::options::UnrecognizedPolicy).
+    #[error("Caveat {index} ({predicate}) not recognized")]
+    UnrecognizedCaveat {
+        /// Position of the caveat within its macaroon
+        index: usize,
+        /// The macaroon the caveat belongs to
+        origin: CaveatSource,
+        /// The caveat predicate, lossily decoded as UTF-8
+        predicate: String,
+    },
+
+    /// A caveat's key has no value in the verification context
+    ///
+    /// See [`MissingKeyPolicy::Error`](crate::predicate::MissingKeyPolicy::Error).
+    #[error("No context value for key '{key}'")]
+    MissingContext {
+        /// The key without a value
+        key: String,
+    },
+
+    /// The token carries no caveats, and the policy forbids unrestricted tokens
+    ///
+    /// See [`TokenPolicy`](crate::policy::TokenPolicy).
+    #[error("Token carries no caveats")]
+    UnrestrictedToken,
+
+    /// The token has no caveat on a key the policy requires
+    ///
+    /// See [`TokenPolicy`](crate::policy::TokenPolicy).
+    #[error("Token has no caveat restricting '{key}'")]
+    MissingRestriction {
+        /// The unrestricted key
+        key: String,
+    },
+
+    /// The token expires later than the policy allows
+    ///
+    /// See [`TokenPolicy`](crate::policy::TokenPolicy).
+    #[error("Token lifetime of {}s exceeds the maximum of {}s", .lifetime.as_secs(), .max_lifetime.as_secs())]
+    LifetimeExceeded {
+        /// Time left until the token's expiry
+        lifetime: Duration,
+        /// The maximum lifetime the policy allows
+        max_lifetime: Duration,
+    },
+
+    /// The token or one of its discharges has been revoked
+    ///
+    /// See [`RevocationStore`](crate::revocation::RevocationStore).
+    #[error("Revoked: {}", String::from_utf8_lossy(.identifier))]
+    Revoked {
+        /// The revoked identifier
+        identifier: Vec<u8>,
+    },
+
+    /// The token was stamped with an epoch older than the subject's current one
+    ///
+    /// See [`EpochStore`](crate::epoch::EpochStore).
+    #[error("Epoch {epoch} of '{subject}' is older than the current epoch {current}")]
+    StaleEpoch {
+        /// The subject the epoch belongs to
+        subject: String,
+        /// The epoch stamped into the token
+        epoch: u64,
+        /// The subject's current epoch
+        current: u64,
+    },
+
+    /// A single-use token was presented again
+    ///
+    /// See [`ReplayStore`](crate::replay::ReplayStore).
+    #[error("Token nonce '{nonce}' was already used")]
+    Replayed {
+        /// The nonce of the token
+        nonce: String,
+    },
+
+    /// The token's usage quota is used up
+    ///
+    /// See [`CounterStore`](crate::quota::CounterStore).
+    #[error("Usage quota of {limit} exhausted")]
+    QuotaExceeded {
+        /// The number of presentations the token allows
+        limit: u64,
+    },
+
+    /// No discharge macaroon was supplied for a third-party caveat
+    #[error("Missing discharge macaroon for caveat: {}", String::from_utf8_lossy(.caveat_id))]
+    MissingDischarge {
+        /// The identifier of the undischarged third-party caveat
+        caveat_id: Vec<u8>,
+    },
+
+    /// A discharge macaroon's signature is invalid or not bound to the primary
+    #[error("Invalid discharge signature: {}", String::from_utf8_lossy(.identifier))]
+    InvalidDischargeSignature {
+        /// The identifier of the discharge macaroon
+        identifier: Vec<u8>,
+    },
+
     /// Failed to deserialize the stroopwafel
     #[error("Deserialization error: {0}")]
     DeserializationError(String),
 
+    /// Failed to decode a stroopwafel or caveat, with the underlying error as source
+    #[error("Decode error: {0}")]
+    Decode(#[source] DecodeError),
+
     /// The stroopwafel format is invalid
     #[error("Invalid format: {0}")]
     InvalidFormat(String),
 
+    /// A time could not be represented, e.g. because it is before the Unix epoch
+    #[error("Invalid time: {0}")]
+    InvalidTime(String),
+
+    /// A store used during verification failed, e.g. an I/O error
+    #[error("Storage error: {0}")]
+    Storage(String),
+
     /// Cryptographic operation failed
     #[error("Cryptographic error: {0}")]
     CryptoError(String),
@@ -27,3 +167,107 @@ pub enum StroopwafelError {
     #[error("Invalid key length")]
     InvalidKeyLength,
 }
+
+/// A decoding failure that keeps the underlying error available through
+/// [`Error::source`](std::error::Error::source)
+#[derive(Debug, Clone)]
+pub struct DecodeError {
+    format: &'static str,
+    inner: Arc<dyn StdError + Send + Sync>,
+}
+
+impl DecodeError {
+    /// Wraps an error raised while decoding `format` (e.g. `"JSON"`)
+    pub fn new(format: &'static str, inner: impl StdError + Send + Sync + 'static) -> Self {
+        Self {
+            format,
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// The name of the format that failed to decode
+    pub fn format(&self) -> &'static str {
+        self.format
+    }
+
+    /// The underlying decoder error
+    pub fn inner(&self) -> &(dyn StdError + Send + Sync + 'static) {
+        self.inner.as_ref()
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid {}", self.format)
+    }
+}
+
+impl StdError for DecodeError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(self.inner.as_ref())
+    }
+}
+
+impl PartialEq for DecodeError {
+    fn eq(&self, other: &Self) -> bool {
+        self.format == other.format && self.inner.to_string() == other.inner.to_string()
+    }
+}
+
+impl Eq for DecodeError {}
+
+impl StroopwafelError {
+    /// Creates a [`Decode`](Self::Decode) error for a failure decoding `format`
+    pub fn decode(format: &'static str, inner: impl StdError + Send + Sync + 'static) -> Self {
+        StroopwafelError::Decode(DecodeError::new(format, inner))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_error_keeps_source() {
+        let inner = serde_json::from_str::<u32>("not json").unwrap_err();
+        let message = inner.to_string();
+        let error = StroopwafelError::decode("JSON", inner);
+
+        let decode = error.source().expect("decode error has a source");
+        assert_eq!(decode.to_string(), "invalid JSON");
+        assert_eq!(decode.source().unwrap().to_string(), message);
+        assert_eq!(error.to_string(), "Decode error: invalid JSON");
+    }
+
+    #[test]
+    fn test_decode_error_equality() {
+        let a = StroopwafelError::decode("hex", hex::FromHexError::OddLength);
+        let b = StroopwafelError::decode("hex", hex::FromHexError::OddLength);
+        let c = StroopwafelError::decode("base64", hex::FromHexError::OddLength);
+
+        assert_eq!(a, b.clone());
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn test_structured_variant_messages() {
+        let error = StroopwafelError::MissingDischarge {
+            caveat_id: b"auth".to_vec(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "Missing discharge macaroon for caveat: auth"
+        );
+
+        let error = StroopwafelError::CaveatViolated {
+            index: 2,
+            origin: CaveatSource::Primary,
+            predicate: "account = alice".to_string(),
+            reason: "account mismatch".to_string(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "Caveat 2 (account = alice) violated: account mismatch"
+        );
+    }
+}
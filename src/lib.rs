@@ -1,13 +1,28 @@
 #![doc = include_str!("../README.md")]
 #![allow(rustdoc::broken_intra_doc_links)]
 
+pub mod batch;
+pub mod cache;
 pub mod caveat;
+pub mod checkpoint;
+pub mod clock;
 pub mod crypto;
+pub mod epoch;
 pub mod error;
+pub mod options;
+pub mod policy;
 pub mod predicate;
+pub mod quota;
+pub mod replay;
+pub mod report;
+pub mod restriction;
+pub mod revocation;
 pub mod serialization;
 pub mod stroopwafel;
+pub mod structured;
+pub mod timestamp;
 pub mod verifier;
+pub mod view;
 
 pub use caveat::Caveat;
 pub use error::StroopwafelError;
@@ -0,0 +1,211 @@
+use crate::checkpoint::CheckpointCache;
+use crate::epoch::EpochStore;
+use crate::policy::TokenPolicy;
+use crate::quota::{CounterStore, QuotaScope};
+use crate::replay::ReplayStore;
+use crate::revocation::RevocationStore;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How [`Stroopwafel::verify_with_options`](crate::Stroopwafel::verify_with_options)
+/// treats caveats that no verifier recognizes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnrecognizedPolicy {
+    /// Fail verification with `StroopwafelError::UnrecognizedCaveat` (default)
+    #[default]
+    Reject,
+    /// Treat unrecognized caveats as satisfied
+    ///
+    /// This weakens every token: a caveat the verifier does not understand no
+    /// longer restricts anything. Use it only while rolling out new caveat
+    /// kinds to verifiers that do not know them yet.
+    Accept,
+}
+
+/// Settings for [`Stroopwafel::verify_with_options`](crate::Stroopwafel::verify_with_options)
+///
+/// The defaults match [`Stroopwafel::verify`](crate::Stroopwafel::verify).
+///
+/// # Example
+/// ```
+/// use stroopwafel::Stroopwafel;
+/// use stroopwafel::options::{UnrecognizedPolicy, VerifyOptions};
+/// use stroopwafel::verifier::ContextVerifier;
+///
+/// let root_key = b"secret";
+/// let mut stroopwafel = Stroopwafel::new(root_key, b"identifier", None::<String>);
+/// stroopwafel.add_first_party_caveat(b"not a predicate");
+///
+/// let verifier = ContextVerifier::empty();
+/// assert!(stroopwafel.verify(root_key, &verifier, &[]).is_err());
+///
+/// let options = VerifyOptions::new().with_unrecognized_policy(UnrecognizedPolicy::Accept);
+/// assert!(stroopwafel.verify_with_options(root_key, &verifier, &[], &options).is_ok());
+/// ```
+#[derive(Clone, Default)]
+pub struct VerifyOptions {
+    unrecognized: UnrecognizedPolicy,
+    token_policy: Option<TokenPolicy>,
+    revocations: Option<Arc<dyn RevocationStore>>,
+    epochs: Option<Arc<dyn EpochStore>>,
+    replays: Option<Arc<dyn ReplayStore>>,
+    clock_skew: Duration,
+    counters: Option<Arc<dyn CounterStore>>,
+    quota_scope: QuotaScope,
+    checkpoints: Option<Arc<CheckpointCache>>,
+}
+
+impl VerifyOptions {
+    /// Creates the default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how caveats that no verifier recognizes are treated
+    pub fn with_unrecognized_policy(mut self, policy: UnrecognizedPolicy) -> Self {
+        self.unrecognized = policy;
+        self
+    }
+
+    /// Returns how caveats that no verifier recognizes are treated
+    pub fn unrecognized_policy(&self) -> UnrecognizedPolicy {
+        self.unrecognized
+    }
+
+    /// Requires presented tokens to satisfy `policy`
+    ///
+    /// The policy is checked after the primary signature and before any
+    /// discharge or caveat.
+    pub fn with_token_policy(mut self, policy: TokenPolicy) -> Self {
+        self.token_policy = Some(policy);
+        self
+    }
+
+    /// Returns the policy presented tokens must satisfy, if any
+    pub fn token_policy(&self) -> Option<&TokenPolicy> {
+        self.token_policy.as_ref()
+    }
+
+    /// Rejects tokens whose identifier, or the identifier of a discharge
+    /// passed in, is revoked in `store`
+    ///
+    /// Revocation is checked after the primary signature and before the
+    /// token policy, the discharges and the caveats.
+    pub fn with_revocation_store(mut self, store: Arc<dyn RevocationStore>) -> Self {
+        self.revocations = Some(store);
+        self
+    }
+
+    /// Returns the store of revoked identifiers, if any
+    pub fn revocation_store(&self) -> Option<&dyn RevocationStore> {
+        self.revocations.as_deref()
+    }
+
+    /// Checks epoch caveats (`epoch:<subject> = N`) against `store`
+    ///
+    /// Epoch caveats are then satisfied by the store rather than the verifier,
+    /// and fail with `StroopwafelError::StaleEpoch` once the subject's epoch
+    /// has moved past the stamped one. Without a store they are handed to the
+    /// verifier like any other caveat.
+    pub fn with_epoch_store(mut self, store: Arc<dyn EpochStore>) -> Self {
+        self.epochs = Some(store);
+        self
+    }
+
+    /// Returns the store of subject epochs, if any
+    pub fn epoch_store(&self) -> Option<&dyn EpochStore> {
+        self.epochs.as_deref()
+    }
+
+    /// Makes tokens with a nonce caveat (`nonce = <value>`) single-use by
+    /// recording their nonces in `store`
+    ///
+    /// Nonce caveats are then satisfied by the store rather than the verifier.
+    /// The nonce is recorded only after every other check has passed, and a
+    /// second presentation fails with `StroopwafelError::Replayed`.
+    pub fn with_replay_store(mut self, store: Arc<dyn ReplayStore>) -> Self {
+        self.replays = Some(store);
+        self
+    }
+
+    /// Returns the store of used nonces, if any
+    pub fn replay_store(&self) -> Option<&dyn ReplayStore> {
+        self.replays.as_deref()
+    }
+
+    /// Keeps nonces for `skew` past the expiry of their token
+    ///
+    /// Set this to the skew given to
+    /// [`ContextVerifier::with_clock_skew`](crate::verifier::ContextVerifier::with_clock_skew):
+    /// the verifier accepts expired tokens for that long, so their nonces must
+    /// be remembered for as long.
+    pub fn with_clock_skew(mut self, skew: Duration) -> Self {
+        self.clock_skew = skew;
+        self
+    }
+
+    /// Returns how long nonces are kept past the expiry of their token
+    pub fn clock_skew(&self) -> Duration {
+        self.clock_skew
+    }
+
+    /// Enforces quota caveats (`uses <= N`) by counting presentations in
+    /// `store`
+    ///
+    /// Quota caveats are then satisfied by the store rather than the verifier.
+    /// The counter is incremented only after every caveat has passed and
+    /// replayed nonces have been refused, and verification fails with
+    /// `StroopwafelError::QuotaExceeded` once the quota is used up.
+    pub fn with_counter_store(mut self, store: Arc<dyn CounterStore>) -> Self {
+        self.counters = Some(store);
+        self
+    }
+
+    /// Returns the store of usage counters, if any
+    pub fn counter_store(&self) -> Option<&dyn CounterStore> {
+        self.counters.as_deref()
+    }
+
+    /// Sets which presentations share a usage counter
+    pub fn with_quota_scope(mut self, scope: QuotaScope) -> Self {
+        self.quota_scope = scope;
+        self
+    }
+
+    /// Returns which presentations share a usage counter
+    pub fn quota_scope(&self) -> QuotaScope {
+        self.quota_scope
+    }
+
+    /// Resumes the primary signature chain from the longest prefix cached in
+    /// `cache`, and caches the chains of tokens that verify
+    ///
+    /// Only the signature check is shortened; caveats and discharges are
+    /// checked in full.
+    pub fn with_checkpoint_cache(mut self, cache: Arc<CheckpointCache>) -> Self {
+        self.checkpoints = Some(cache);
+        self
+    }
+
+    /// Returns the cache of verified chain prefixes, if any
+    pub fn checkpoint_cache(&self) -> Option<&CheckpointCache> {
+        self.checkpoints.as_deref()
+    }
+}
+
+impl fmt::Debug for VerifyOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VerifyOptions")
+            .field("unrecognized", &self.unrecognized)
+            .field("token_policy", &self.token_policy)
+            .field("revocations", &self.revocations.is_some())
+            .field("epochs", &self.epochs.is_some())
+            .field("replays", &self.replays.is_some())
+            .field("clock_skew", &self.clock_skew)
+            .field("counters", &self.counters.is_some())
+            .field("quota_scope", &self.quota_scope)
+            .field("checkpoints", &self.checkpoints.is_some())
+            .finish()
+    }
+}
@@ -0,0 +1,313 @@
+use crate::caveat::ParsedCaveat;
+use crate::clock::{Clock, SystemClock};
+use crate::restriction::TIME_KEY;
+use crate::stroopwafel::Token;
+use crate::timestamp::Timestamp;
+use crate::{Result, Stroopwafel, StroopwafelError};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Restrictions every token must carry, checked when minting and when
+/// verifying
+///
+/// A policy looks only at the first-party caveats of the primary stroopwafel:
+/// it decides whether a token is restricted enough to be issued or accepted,
+/// while the verifier still decides whether each caveat holds.
+///
+/// - [`forbid_unrestricted`](Self::forbid_unrestricted) rejects tokens without
+///   caveats with `StroopwafelError::UnrestrictedToken`
+/// - [`require_key`](Self::require_key) rejects tokens without a caveat on the
+///   key with `StroopwafelError::MissingRestriction`
+/// - [`with_max_lifetime`](Self::with_max_lifetime) rejects tokens whose
+///   expiry (`time <` or `time <=`) is missing or further away than the limit,
+///   with `MissingRestriction` or `StroopwafelError::LifetimeExceeded`
+///
+/// Attach the policy to [`VerifyOptions`](crate::options::VerifyOptions) to
+/// enforce it on presented tokens, and call [`check`](Self::check) before
+/// handing out a minted token.
+///
+/// # Example
+/// ```
+/// use stroopwafel::Stroopwafel;
+/// use stroopwafel::policy::TokenPolicy;
+/// use stroopwafel::restriction::Restriction;
+/// use std::time::Duration;
+///
+/// let policy = TokenPolicy::new()
+///     .forbid_unrestricted()
+///     .require_key("aud")
+///     .with_max_lifetime(Duration::from_secs(3600));
+///
+/// let root_key = b"secret";
+/// let mut token = Stroopwafel::new(root_key, b"identifier", None::<String>);
+/// assert!(policy.check(&token).is_err());
+///
+/// token.add_first_party_caveat(b"aud = storage");
+/// token.add_restriction(&Restriction::expires_in(Duration::from_secs(600)).unwrap());
+/// assert!(policy.check(&token).is_ok());
+/// ```
+#[derive(Clone, Default)]
+pub struct TokenPolicy {
+    forbid_unrestricted: bool,
+    required_keys: Vec<String>,
+    max_lifetime: Option<Duration>,
+    clock: Option<Arc<dyn Clock>>,
+}
+
+impl TokenPolicy {
+    /// Creates a policy that accepts every token
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rejects tokens that carry no caveats at all
+    pub fn forbid_unrestricted(mut self) -> Self {
+        self.forbid_unrestricted = true;
+        self
+    }
+
+    /// Requires a first-party caveat on `key`
+    ///
+    /// Any operator counts: the policy only checks that the key is
+    /// restricted, and the verifier checks the restriction itself.
+    pub fn require_key(mut self, key: impl Into<String>) -> Self {
+        let key = key.into();
+        if !self.required_keys.contains(&key) {
+            self.required_keys.push(key);
+        }
+        self
+    }
+
+    /// Requires an expiry no further than `max_lifetime` from now
+    ///
+    /// The earliest `time <` or `time <=` caveat is the token's expiry.
+    pub fn with_max_lifetime(mut self, max_lifetime: Duration) -> Self {
+        self.max_lifetime = Some(max_lifetime);
+        self
+    }
+
+    /// Reads the current time for the lifetime check from `clock` instead of
+    /// the system clock
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
+    /// Returns the keys every token must restrict
+    pub fn required_keys(&self) -> &[String] {
+        &self.required_keys
+    }
+
+    /// Returns the maximum remaining lifetime of a token, if limited
+    pub fn max_lifetime(&self) -> Option<Duration> {
+        self.max_lifetime
+    }
+
+    /// Checks that `stroopwafel` carries the restrictions this policy demands
+    ///
+    /// The signature is not checked; verification does that first.
+    ///
+    /// # Errors
+    /// Returns `StroopwafelError::UnrestrictedToken`, `MissingRestriction` or
+    /// `LifetimeExceeded` for the first rule the token breaks, in that order,
+    /// and `InvalidTime` if the clock is before the Unix epoch.
+    pub fn check(&self, stroopwafel: &Stroopwafel) -> Result<()> {
+        self.check_token(stroopwafel)
+    }
+
+    /// Checks an owned or borrowed token, like [`check`](Self::check)
+    pub(crate) fn check_token(&self, token: &impl Token) -> Result<()> {
+        if self.forbid_unrestricted && token.caveat_count() == 0 {
+            return Err(StroopwafelError::UnrestrictedToken);
+        }
+
+        // Caveats this policy cannot parse restrict nothing it knows about
+        let caveats: Vec<ParsedCaveat> = token
+            .caveat_parts()
+            .filter(|caveat| caveat.is_first_party())
+            .filter_map(|caveat| ParsedCaveat::parse(caveat.caveat_id).ok())
+            .collect();
+
+        for key in &self.required_keys {
+            if !caveats.iter().any(|caveat| caveat.key() == key) {
+                return Err(StroopwafelError::MissingRestriction { key: key.clone() });
+            }
+        }
+
+        if let Some(max_lifetime) = self.max_lifetime {
+            let expiry = caveats
+                .iter()
+                .filter_map(ParsedCaveat::expiry)
+                .min()
+                .ok_or_else(|| StroopwafelError::MissingRestriction {
+                    key: TIME_KEY.to_string(),
+                })?;
+
+            let now = match &self.clock {
+                Some(clock) => Timestamp::from_system_time(clock.now())?,
+                None => Timestamp::from_system_time(SystemClock.now())?,
+            };
+            let remaining = expiry.unix_nanos().saturating_sub(now.unix_nanos()).max(0);
+            let lifetime = Duration::from_nanos(u64::try_from(remaining).unwrap_or(u64::MAX));
+
+            if lifetime > max_lifetime {
+                return Err(StroopwafelError::LifetimeExceeded {
+                    lifetime,
+                    max_lifetime,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for TokenPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenPolicy")
+            .field("forbid_unrestricted", &self.forbid_unrestricted)
+            .field("required_keys", &self.required_keys)
+            .field("max_lifetime", &self.max_lifetime)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::predicate::Operator;
+    use crate::restriction::Restriction;
+    use crate::structured::StructuredCaveat;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn token(caveats: &[&str]) -> Stroopwafel {
+        let mut token = Stroopwafel::new(b"secret", b"identifier", None::<String>);
+        for caveat in caveats {
+            token.add_first_party_caveat(caveat.as_bytes());
+        }
+        token
+    }
+
+    #[test]
+    fn test_default_policy_accepts_everything() {
+        assert!(TokenPolicy::new().check(&token(&[])).is_ok());
+    }
+
+    #[test]
+    fn test_forbid_unrestricted() {
+        let policy = TokenPolicy::new().forbid_unrestricted();
+        assert_eq!(
+            policy.check(&token(&[])),
+            Err(StroopwafelError::UnrestrictedToken)
+        );
+        assert!(policy.check(&token(&["account = alice"])).is_ok());
+
+        // A third-party caveat restricts the token too
+        let mut third_party = token(&[]);
+        third_party.add_third_party_caveat(b"auth", b"key", "https://auth.example.com");
+        assert!(policy.check(&third_party).is_ok());
+    }
+
+    #[test]
+    fn test_required_keys() {
+        let policy = TokenPolicy::new().require_key("aud").require_key("account");
+
+        assert!(
+            policy
+                .check(&token(&["aud = storage", "account in alice,bob"]))
+                .is_ok()
+        );
+        assert_eq!(
+            policy.check(&token(&["aud = storage"])),
+            Err(StroopwafelError::MissingRestriction {
+                key: "account".to_string()
+            })
+        );
+        // A key mentioned only in an unparseable caveat is not restricted
+        assert_eq!(
+            policy.check(&token(&["aud storage", "account = alice"])),
+            Err(StroopwafelError::MissingRestriction {
+                key: "aud".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_required_key_in_structured_caveat() {
+        let policy = TokenPolicy::new().require_key("aud");
+        let mut token = token(&[]);
+        token.add_structured_caveat(&StructuredCaveat::new("aud", Operator::Equal, "storage"));
+        assert!(policy.check(&token).is_ok());
+    }
+
+    #[test]
+    fn test_max_lifetime() {
+        let policy = TokenPolicy::new()
+            .with_max_lifetime(Duration::from_secs(3600))
+            .with_clock(ManualClock::at_unix(NOW));
+
+        assert!(policy.check(&token(&["time < 1700003600"])).is_ok());
+        assert!(
+            policy
+                .check(&token(&["time <= 2023-11-14T23:13:20Z"]))
+                .is_ok()
+        );
+        assert_eq!(
+            policy.check(&token(&["time < 1700003601"])),
+            Err(StroopwafelError::LifetimeExceeded {
+                lifetime: Duration::from_secs(3601),
+                max_lifetime: Duration::from_secs(3600),
+            })
+        );
+
+        // The earliest expiry counts
+        assert!(
+            policy
+                .check(&token(&["time < 1800000000", "time < 1700000060"]))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_max_lifetime_requires_expiry() {
+        let policy = TokenPolicy::new()
+            .with_max_lifetime(Duration::from_secs(3600))
+            .with_clock(ManualClock::at_unix(NOW));
+
+        // Not-before caveats are not expiries
+        assert_eq!(
+            policy.check(&token(&["time > 1600000000"])),
+            Err(StroopwafelError::MissingRestriction {
+                key: TIME_KEY.to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_max_lifetime_with_restriction() {
+        let clock = Arc::new(ManualClock::at_unix(NOW));
+        let policy = TokenPolicy::new()
+            .with_max_lifetime(Duration::from_secs(60))
+            .with_clock(Arc::clone(&clock));
+
+        let mut token = token(&[]);
+        token.add_restriction(
+            &Restriction::expires_in_with_clock(Duration::from_secs(60), &clock).unwrap(),
+        );
+        assert!(policy.check(&token).is_ok());
+
+        let mut structured = Stroopwafel::new(b"secret", b"identifier", None::<String>);
+        structured.add_structured_caveat(&StructuredCaveat::new(
+            TIME_KEY,
+            Operator::LessThan,
+            NOW as i64 + 120,
+        ));
+        assert!(matches!(
+            policy.check(&structured),
+            Err(StroopwafelError::LifetimeExceeded { .. })
+        ));
+    }
+}
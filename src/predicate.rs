@@ -1,5 +1,10 @@
+use crate::timestamp::Timestamp;
 use crate::{Result, StroopwafelError};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
 
 /// Operators supported in predicates
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -16,10 +21,53 @@ pub enum Operator {
     LessThanOrEqual,
     /// Greater than or equal (>=)
     GreaterThanOrEqual,
+    /// Membership in a comma-separated set (in)
+    In,
+    /// String prefix (^=)
+    StartsWith,
+    /// IP address within a CIDR network (<<=)
+    InSubnet,
+    /// The key has a value (exists); takes no value
+    Exists,
+    /// The key has no value (!exists); takes no value
+    NotExists,
 }
 
+/// How a predicate evaluates when its key has no value in the context
+///
+/// The `exists` and `!exists` operators always test for presence and are not
+/// affected by the policy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MissingKeyPolicy {
+    /// The predicate fails (default)
+    #[default]
+    Fail,
+    /// The missing value is a null that differs from every value: `!=` holds,
+    /// and every other operator fails
+    Null,
+    /// Evaluation fails with `StroopwafelError::MissingContext`
+    Error,
+}
+
+/// All operators, ordered so that longer symbols are tried before their prefixes
+const OPERATORS: [Operator; 11] = [
+    Operator::NotExists,
+    Operator::Exists,
+    Operator::InSubnet,
+    Operator::LessThanOrEqual,
+    Operator::GreaterThanOrEqual,
+    Operator::NotEqual,
+    Operator::StartsWith,
+    Operator::Equal,
+    Operator::LessThan,
+    Operator::GreaterThan,
+    Operator::In,
+];
+
 impl Operator {
     /// Parse an operator from a string
+    ///
+    /// See also the [`FromStr`] implementation, which reports an error instead.
     pub fn parse(s: &str) -> Option<Self> {
         match s.trim() {
             "=" => Some(Operator::Equal),
@@ -28,12 +76,88 @@ impl Operator {
             ">" => Some(Operator::GreaterThan),
             "<=" => Some(Operator::LessThanOrEqual),
             ">=" => Some(Operator::GreaterThanOrEqual),
+            "in" => Some(Operator::In),
+            "^=" => Some(Operator::StartsWith),
+            "<<=" => Some(Operator::InSubnet),
+            "exists" => Some(Operator::Exists),
+            "!exists" => Some(Operator::NotExists),
             _ => None,
         }
     }
 
+    /// Returns the textual form of the operator (e.g., "<=")
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operator::Equal => "=",
+            Operator::NotEqual => "!=",
+            Operator::LessThan => "<",
+            Operator::GreaterThan => ">",
+            Operator::LessThanOrEqual => "<=",
+            Operator::GreaterThanOrEqual => ">=",
+            Operator::In => "in",
+            Operator::StartsWith => "^=",
+            Operator::InSubnet => "<<=",
+            Operator::Exists => "exists",
+            Operator::NotExists => "!exists",
+        }
+    }
+
+    /// Returns true if the operator is a word that must be surrounded by whitespace
+    fn is_word(&self) -> bool {
+        matches!(self, Operator::In | Operator::Exists | Operator::NotExists)
+    }
+
+    /// Returns true for the operators that take no value (`exists`, `!exists`)
+    pub fn is_unary(&self) -> bool {
+        matches!(self, Operator::Exists | Operator::NotExists)
+    }
+
+    /// Returns true for the equality and ordering operators
+    pub(crate) fn is_comparison(&self) -> bool {
+        !matches!(
+            self,
+            Operator::In
+                | Operator::StartsWith
+                | Operator::InSubnet
+                | Operator::Exists
+                | Operator::NotExists
+        )
+    }
+
+    /// Evaluate the operator for a key that has no value in the context
+    ///
+    /// # Errors
+    /// Returns `StroopwafelError::MissingContext` under
+    /// [`MissingKeyPolicy::Error`], unless the operator tests for presence.
+    pub fn evaluate_missing(&self, key: &str, policy: MissingKeyPolicy) -> Result<bool> {
+        match (self, policy) {
+            (Operator::Exists, _) => Ok(false),
+            (Operator::NotExists, _) => Ok(true),
+            (_, MissingKeyPolicy::Fail) => Ok(false),
+            (_, MissingKeyPolicy::Null) => Ok(*self == Operator::NotEqual),
+            (_, MissingKeyPolicy::Error) => Err(StroopwafelError::MissingContext {
+                key: key.to_string(),
+            }),
+        }
+    }
+
     /// Evaluate the operator on two string values
     pub fn evaluate(&self, left: &str, right: &str) -> bool {
+        match self {
+            Operator::In => right.split(',').any(|item| item.trim() == left),
+            Operator::StartsWith => left.starts_with(right),
+            Operator::InSubnet => ip_in_subnet(left, right),
+            Operator::Exists => true,
+            Operator::NotExists => false,
+            _ => self.compare(left, right),
+        }
+    }
+
+    /// Evaluate the operator on any two comparable values
+    ///
+    /// The set, prefix and subnet operators have no meaning here and always
+    /// evaluate to false.
+    pub fn compare<T: PartialOrd + ?Sized>(&self, left: &T, right: &T) -> bool {
         match self {
             Operator::Equal => left == right,
             Operator::NotEqual => left != right,
@@ -41,6 +165,11 @@ impl Operator {
             Operator::GreaterThan => left > right,
             Operator::LessThanOrEqual => left <= right,
             Operator::GreaterThanOrEqual => left >= right,
+            Operator::In
+            | Operator::StartsWith
+            | Operator::InSubnet
+            | Operator::Exists
+            | Operator::NotExists => false,
         }
     }
 
@@ -53,11 +182,142 @@ impl Operator {
             Operator::GreaterThan => left > right,
             Operator::LessThanOrEqual => left <= right,
             Operator::GreaterThanOrEqual => left >= right,
+            Operator::In
+            | Operator::StartsWith
+            | Operator::InSubnet
+            | Operator::Exists
+            | Operator::NotExists => false,
         }
     }
+
+    /// Evaluates the operator on two context values, picking the comparison
+    /// the values support
+    ///
+    /// Ordering comparisons (`<`, `>`, `<=`, `>=`, `=`, `!=`) treat values that
+    /// are both times (Unix seconds or RFC 3339, see
+    /// [`Timestamp`](crate::timestamp::Timestamp)) as instants, compared
+    /// exactly. Other numbers are compared numerically, and anything else as
+    /// strings.
+    pub(crate) fn evaluate_values(&self, actual: &str, expected: &str) -> bool {
+        if self.is_comparison() {
+            if let (Some(actual), Some(expected)) =
+                (Timestamp::parse(actual), Timestamp::parse(expected))
+            {
+                return self.compare(&actual, &expected);
+            }
+
+            if let (Ok(actual), Ok(expected)) = (actual.parse::<f64>(), expected.parse::<f64>()) {
+                return self.evaluate_numeric(actual, expected);
+            }
+        }
+
+        self.evaluate(actual, expected)
+    }
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Operator {
+    type Err = StroopwafelError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Operator::parse(s)
+            .ok_or_else(|| StroopwafelError::InvalidFormat(format!("Unknown operator: '{s}'")))
+    }
+}
+
+impl Serialize for Operator {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Operator {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Checks whether `ip` lies within `network`, given in CIDR notation
+///
+/// A network without a prefix length only matches the exact address.
+fn ip_in_subnet(ip: &str, network: &str) -> bool {
+    let (address, prefix_len) = match network.split_once('/') {
+        Some((address, prefix_len)) => match prefix_len.parse::<u32>() {
+            Ok(prefix_len) => (address, Some(prefix_len)),
+            Err(_) => return false,
+        },
+        None => (network, None),
+    };
+
+    let (Ok(ip), Ok(address)) = (ip.parse::<IpAddr>(), address.parse::<IpAddr>()) else {
+        return false;
+    };
+
+    match (ip, address) {
+        (IpAddr::V4(ip), IpAddr::V4(address)) => {
+            let prefix_len = prefix_len.unwrap_or(32);
+            if prefix_len > 32 {
+                return false;
+            }
+            let mask = u32::MAX.checked_shl(32 - prefix_len).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(address) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(address)) => {
+            let prefix_len = prefix_len.unwrap_or(128);
+            if prefix_len > 128 {
+                return false;
+            }
+            let mask = u128::MAX.checked_shl(128 - prefix_len).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(address) & mask
+        }
+        _ => false,
+    }
+}
+
+/// Finds the leftmost operator in a predicate string
+///
+/// At each position the longest matching symbol wins, so `<=` is never read
+/// as `<`. Word operators such as `in` only match when surrounded by whitespace.
+fn find_operator(s: &str) -> Option<(usize, Operator)> {
+    for (pos, _) in s.char_indices() {
+        let rest = &s[pos..];
+
+        for operator in OPERATORS {
+            let symbol = operator.as_str();
+            if !rest.starts_with(symbol) {
+                continue;
+            }
+
+            // Unary operators end the predicate; other words are followed by
+            // whitespace and a value
+            let after = &rest[symbol.len()..];
+            let followed = if operator.is_unary() {
+                after.trim().is_empty()
+            } else {
+                after.starts_with(char::is_whitespace)
+            };
+            if operator.is_word() && !(s[..pos].ends_with(char::is_whitespace) && followed) {
+                continue;
+            }
+
+            return Some((pos, operator));
+        }
+    }
+
+    None
 }
 
 /// A parsed predicate with key, operator, and value
+///
+/// A predicate displays in the canonical form `"key operator value"` (one space
+/// on each side of the operator), and that form parses back to an equal
+/// predicate. Serde uses the same string form.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Predicate {
     /// The key (e.g., "account", "time", "action")
@@ -76,56 +336,99 @@ impl Predicate {
     /// - "account = alice"
     /// - "time < 2025-12-31T23:59:59Z"
     /// - "count >= 10"
+    /// - "action in read,write"
+    /// - "resource ^= /api/"
+    /// - "ip <<= 10.0.0.0/8"
+    /// - "status !exists"
+    ///
+    /// The leftmost operator splits the key from the value, so values may
+    /// themselves contain operator characters. The unary `exists` and
+    /// `!exists` operators end the predicate and have an empty value.
     pub fn parse(s: &str) -> Result<Self> {
-        // Try to find an operator
-        let operators = ["<=", ">=", "!=", "=", "<", ">"];
-
-        for op_str in &operators {
-            if let Some(pos) = s.find(op_str) {
-                let key = s[..pos].trim().to_string();
-                let value = s[pos + op_str.len()..].trim().to_string();
-
-                if key.is_empty() || value.is_empty() {
-                    return Err(StroopwafelError::InvalidFormat(format!(
-                        "Invalid predicate format: '{s}'"
-                    )));
-                }
-
-                let operator = Operator::parse(op_str).ok_or_else(|| {
-                    StroopwafelError::InvalidFormat(format!("Unknown operator: '{op_str}'"))
-                })?;
-
-                return Ok(Predicate {
-                    key,
-                    operator,
-                    value,
-                });
-            }
+        let (pos, operator) = find_operator(s).ok_or_else(|| {
+            StroopwafelError::InvalidFormat(format!("No operator found in predicate: '{s}'"))
+        })?;
+
+        let key = s[..pos].trim().to_string();
+        let value = s[pos + operator.as_str().len()..].trim().to_string();
+
+        if key.is_empty() || value.is_empty() != operator.is_unary() {
+            return Err(StroopwafelError::InvalidFormat(format!(
+                "Invalid predicate format: '{s}'"
+            )));
         }
 
-        Err(StroopwafelError::InvalidFormat(format!(
-            "No operator found in predicate: '{s}'"
-        )))
+        Ok(Predicate {
+            key,
+            operator,
+            value,
+        })
     }
 
     /// Evaluate this predicate against a context
     ///
     /// The context is a map of key-value pairs representing the current state.
+    /// Time values may be Unix seconds or RFC 3339 on either side and are
+    /// compared as instants; other numbers compare numerically.
     pub fn evaluate(&self, context: &HashMap<String, String>) -> bool {
-        let actual_value = match context.get(&self.key) {
-            Some(v) => v,
-            None => return false, // Key not in context
-        };
-
-        // Try numeric comparison first
-        if let (Ok(actual_num), Ok(expected_num)) =
-            (actual_value.parse::<f64>(), self.value.parse::<f64>())
-        {
-            return self.operator.evaluate_numeric(actual_num, expected_num);
+        self.evaluate_with_policy(context, MissingKeyPolicy::Fail)
+            .unwrap_or(false)
+    }
+
+    /// Evaluate this predicate against a context, applying `policy` when the
+    /// key is missing
+    ///
+    /// # Errors
+    /// Returns `StroopwafelError::MissingContext` for a missing key under
+    /// [`MissingKeyPolicy::Error`].
+    pub fn evaluate_with_policy(
+        &self,
+        context: &HashMap<String, String>,
+        policy: MissingKeyPolicy,
+    ) -> Result<bool> {
+        match context.get(&self.key) {
+            Some(actual_value) => Ok(self.evaluate_value(actual_value)),
+            None => self.operator.evaluate_missing(&self.key, policy),
         }
+    }
 
-        // Fall back to string comparison
-        self.operator.evaluate(actual_value, &self.value)
+    /// Evaluate this predicate against the context value of its key
+    ///
+    /// Use this when context values are not held in a map, e.g. when they are
+    /// computed on demand.
+    pub fn evaluate_value(&self, actual: &str) -> bool {
+        self.operator.evaluate_values(actual, &self.value)
+    }
+}
+
+impl fmt::Display for Predicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.operator.is_unary() {
+            write!(f, "{} {}", self.key, self.operator)
+        } else {
+            write!(f, "{} {} {}", self.key, self.operator, self.value)
+        }
+    }
+}
+
+impl FromStr for Predicate {
+    type Err = StroopwafelError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Predicate::parse(s)
+    }
+}
+
+impl Serialize for Predicate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Predicate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
     }
 }
 
@@ -267,10 +570,305 @@ mod tests {
         assert!(!pred.evaluate(&context));
     }
 
+    #[test]
+    fn test_evaluate_time_mixed_formats() {
+        let mut context = HashMap::new();
+        context.insert("time".to_string(), "1735689599".to_string());
+
+        // 2024-12-31T23:59:59Z
+        assert!(
+            Predicate::parse("time < 2025-01-01T00:00:00Z")
+                .unwrap()
+                .evaluate(&context)
+        );
+        assert!(
+            Predicate::parse("time = 2024-12-31T23:59:59Z")
+                .unwrap()
+                .evaluate(&context)
+        );
+        assert!(
+            Predicate::parse("time >= 2025-01-01T00:59:59+01:00")
+                .unwrap()
+                .evaluate(&context)
+        );
+        assert!(
+            !Predicate::parse("time < 2024-12-31")
+                .unwrap()
+                .evaluate(&context)
+        );
+
+        context.insert("time".to_string(), "2024-12-31T18:59:59-05:00".to_string());
+        assert!(
+            Predicate::parse("time = 1735689599")
+                .unwrap()
+                .evaluate(&context)
+        );
+        assert!(
+            Predicate::parse("time < 1735689600")
+                .unwrap()
+                .evaluate(&context)
+        );
+    }
+
+    #[test]
+    fn test_evaluate_time_sub_second() {
+        let mut context = HashMap::new();
+        context.insert("time".to_string(), "1735689599.999999999".to_string());
+
+        assert!(
+            Predicate::parse("time < 1735689600")
+                .unwrap()
+                .evaluate(&context)
+        );
+        assert!(
+            Predicate::parse("time < 2025-01-01T00:00:00Z")
+                .unwrap()
+                .evaluate(&context)
+        );
+        assert!(
+            !Predicate::parse("time < 2024-12-31T23:59:59.5Z")
+                .unwrap()
+                .evaluate(&context)
+        );
+    }
+
     #[test]
     fn test_operator_precedence() {
         // Make sure <= is matched before <
         let pred = Predicate::parse("x <= 5").unwrap();
         assert_eq!(pred.operator, Operator::LessThanOrEqual);
     }
+
+    #[test]
+    fn test_parse_leftmost_operator() {
+        let pred = Predicate::parse("query = a<b").unwrap();
+        assert_eq!(pred.key, "query");
+        assert_eq!(pred.operator, Operator::Equal);
+        assert_eq!(pred.value, "a<b");
+    }
+
+    #[test]
+    fn test_parse_in() {
+        let pred = Predicate::parse("action in read,write").unwrap();
+        assert_eq!(pred.key, "action");
+        assert_eq!(pred.operator, Operator::In);
+        assert_eq!(pred.value, "read,write");
+    }
+
+    #[test]
+    fn test_parse_in_requires_whitespace() {
+        // "in" inside a key is not an operator
+        let pred = Predicate::parse("login = alice").unwrap();
+        assert_eq!(pred.key, "login");
+        assert_eq!(pred.operator, Operator::Equal);
+
+        assert!(Predicate::parse("login").is_err());
+    }
+
+    #[test]
+    fn test_parse_starts_with_and_subnet() {
+        let pred = Predicate::parse("resource ^= /api/").unwrap();
+        assert_eq!(pred.operator, Operator::StartsWith);
+        assert_eq!(pred.value, "/api/");
+
+        let pred = Predicate::parse("ip <<= 10.0.0.0/8").unwrap();
+        assert_eq!(pred.operator, Operator::InSubnet);
+        assert_eq!(pred.value, "10.0.0.0/8");
+    }
+
+    #[test]
+    fn test_evaluate_in() {
+        let pred = Predicate::parse("action in read, write").unwrap();
+        let mut context = HashMap::new();
+
+        context.insert("action".to_string(), "write".to_string());
+        assert!(pred.evaluate(&context));
+
+        context.insert("action".to_string(), "delete".to_string());
+        assert!(!pred.evaluate(&context));
+    }
+
+    #[test]
+    fn test_evaluate_starts_with() {
+        let pred = Predicate::parse("resource ^= /api/").unwrap();
+        let mut context = HashMap::new();
+
+        context.insert("resource".to_string(), "/api/documents".to_string());
+        assert!(pred.evaluate(&context));
+
+        context.insert("resource".to_string(), "/admin".to_string());
+        assert!(!pred.evaluate(&context));
+    }
+
+    #[test]
+    fn test_evaluate_starts_with_numeric_looking_values() {
+        // Prefix checks must not fall into numeric comparison
+        let pred = Predicate::parse("code ^= 10").unwrap();
+        let mut context = HashMap::new();
+        context.insert("code".to_string(), "1042".to_string());
+
+        assert!(pred.evaluate(&context));
+    }
+
+    #[test]
+    fn test_evaluate_in_subnet() {
+        let pred = Predicate::parse("ip <<= 10.0.0.0/8").unwrap();
+        let mut context = HashMap::new();
+
+        context.insert("ip".to_string(), "10.1.2.3".to_string());
+        assert!(pred.evaluate(&context));
+
+        context.insert("ip".to_string(), "11.0.0.1".to_string());
+        assert!(!pred.evaluate(&context));
+
+        context.insert("ip".to_string(), "not an ip".to_string());
+        assert!(!pred.evaluate(&context));
+
+        context.insert("ip".to_string(), "::1".to_string());
+        assert!(!pred.evaluate(&context));
+    }
+
+    #[test]
+    fn test_evaluate_in_subnet_ipv6() {
+        let pred = Predicate::parse("ip <<= 2001:db8::/32").unwrap();
+        let mut context = HashMap::new();
+
+        context.insert("ip".to_string(), "2001:db8::1".to_string());
+        assert!(pred.evaluate(&context));
+
+        context.insert("ip".to_string(), "2001:db9::1".to_string());
+        assert!(!pred.evaluate(&context));
+    }
+
+    #[test]
+    fn test_evaluate_in_subnet_edge_prefixes() {
+        assert!(ip_in_subnet("192.168.1.1", "0.0.0.0/0"));
+        assert!(ip_in_subnet("192.168.1.1", "192.168.1.1/32"));
+        assert!(ip_in_subnet("192.168.1.1", "192.168.1.1"));
+        assert!(!ip_in_subnet("192.168.1.2", "192.168.1.1"));
+        assert!(!ip_in_subnet("192.168.1.1", "192.168.1.1/33"));
+    }
+
+    #[test]
+    fn test_operator_display_roundtrip() {
+        for operator in OPERATORS {
+            assert_eq!(operator.to_string().parse::<Operator>().unwrap(), operator);
+        }
+        assert!("=>".parse::<Operator>().is_err());
+    }
+
+    #[test]
+    fn test_predicate_display_canonical() {
+        let pred = Predicate::parse("  count>=10 ").unwrap();
+        assert_eq!(pred.to_string(), "count >= 10");
+
+        let pred = Predicate::parse("action in read,write").unwrap();
+        assert_eq!(pred.to_string(), "action in read,write");
+    }
+
+    #[test]
+    fn test_predicate_from_str() {
+        let pred: Predicate = "account = alice".parse().unwrap();
+        assert_eq!(pred, Predicate::parse("account = alice").unwrap());
+        assert!("no operator here".parse::<Predicate>().is_err());
+    }
+
+    #[test]
+    fn test_parse_presence_operators() {
+        let pred = Predicate::parse("status exists").unwrap();
+        assert_eq!(pred.key, "status");
+        assert_eq!(pred.operator, Operator::Exists);
+        assert_eq!(pred.value, "");
+        assert_eq!(pred.to_string(), "status exists");
+
+        let pred = Predicate::parse("  status   !exists ").unwrap();
+        assert_eq!(pred.operator, Operator::NotExists);
+        assert_eq!(pred.to_string(), "status !exists");
+
+        // Presence operators take no value, and other operators need one
+        assert!(Predicate::parse("status exists yes").is_err());
+        assert!(Predicate::parse("status !exists no").is_err());
+        assert!(Predicate::parse("status =").is_err());
+
+        // Words merely containing the operator are not operators
+        let pred = Predicate::parse("path = /exists").unwrap();
+        assert_eq!(pred.operator, Operator::Equal);
+        assert_eq!(pred.value, "/exists");
+    }
+
+    #[test]
+    fn test_evaluate_presence_operators() {
+        let mut context = HashMap::new();
+        context.insert("status".to_string(), "".to_string());
+
+        assert!(
+            Predicate::parse("status exists")
+                .unwrap()
+                .evaluate(&context)
+        );
+        assert!(
+            !Predicate::parse("status !exists")
+                .unwrap()
+                .evaluate(&context)
+        );
+        assert!(!Predicate::parse("other exists").unwrap().evaluate(&context));
+        assert!(
+            Predicate::parse("other !exists")
+                .unwrap()
+                .evaluate(&context)
+        );
+    }
+
+    #[test]
+    fn test_missing_key_policies() {
+        let context = HashMap::new();
+        let not_equal = Predicate::parse("role != admin").unwrap();
+        let equal = Predicate::parse("role = admin").unwrap();
+        let absent = Predicate::parse("role !exists").unwrap();
+
+        assert!(
+            !not_equal
+                .evaluate_with_policy(&context, MissingKeyPolicy::Fail)
+                .unwrap()
+        );
+        assert!(
+            not_equal
+                .evaluate_with_policy(&context, MissingKeyPolicy::Null)
+                .unwrap()
+        );
+        assert!(
+            !equal
+                .evaluate_with_policy(&context, MissingKeyPolicy::Null)
+                .unwrap()
+        );
+
+        assert!(matches!(
+            equal.evaluate_with_policy(&context, MissingKeyPolicy::Error),
+            Err(StroopwafelError::MissingContext { key }) if key == "role"
+        ));
+
+        // Presence operators ignore the policy
+        for policy in [
+            MissingKeyPolicy::Fail,
+            MissingKeyPolicy::Null,
+            MissingKeyPolicy::Error,
+        ] {
+            assert!(absent.evaluate_with_policy(&context, policy).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_serde_json() {
+        let pred = Predicate::parse("ip <<= 10.0.0.0/8").unwrap();
+        let json = serde_json::to_string(&pred).unwrap();
+        assert_eq!(json, "\"ip <<= 10.0.0.0/8\"");
+        assert_eq!(serde_json::from_str::<Predicate>(&json).unwrap(), pred);
+
+        assert_eq!(
+            serde_json::to_string(&Operator::StartsWith).unwrap(),
+            "\"^=\""
+        );
+        assert!(serde_json::from_str::<Operator>("\"~\"").is_err());
+        assert!(serde_json::from_str::<Predicate>("\"garbage\"").is_err());
+    }
 }
@@ -0,0 +1,355 @@
+use crate::caveat::ParsedCaveat;
+use crate::predicate::Operator;
+use crate::restriction::USES_KEY;
+use crate::stroopwafel::Token;
+use crate::structured::Value;
+use crate::{Result, StroopwafelError};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Which presentations share a usage counter
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QuotaScope {
+    /// All tokens minted with the same identifier share one counter (default),
+    /// so attenuating a token does not reset its quota
+    #[default]
+    Identifier,
+    /// Each attenuation counts separately, keyed by the token signature
+    ///
+    /// A holder can hand out attenuated tokens with their own, tighter quota,
+    /// but can also mint a fresh allowance by adding any caveat.
+    Signature,
+}
+
+/// Usage counters for quota caveats (`uses <= N`)
+///
+/// Attach a store to [`VerifyOptions`](crate::options::VerifyOptions) to have
+/// verification enforce quota caveats (see
+/// [`Restriction::max_uses`](crate::restriction::Restriction::max_uses)).
+/// Quota caveats are then satisfied by the store rather than the verifier.
+/// Once every caveat has passed, verification increments the token's counter
+/// and fails with `StroopwafelError::QuotaExceeded` once the tightest quota of
+/// the token (discharges included) is used up. Replayed nonces are refused
+/// before the counter is incremented, so a replay does not count, unless it
+/// races the presentation that records the nonce.
+pub trait CounterStore: Send + Sync {
+    /// Increments `counter` unless it has reached `limit`
+    ///
+    /// Returns the new count, or `None` without changing anything if the
+    /// counter is at or above `limit`. Checking and incrementing must be
+    /// atomic, so that concurrent presentations cannot exceed the limit.
+    fn increment(&self, counter: &[u8], limit: u64) -> Result<Option<u64>>;
+
+    /// Returns the current value of `counter`, 0 if it was never incremented
+    fn count(&self, counter: &[u8]) -> Result<u64>;
+}
+
+impl<S: CounterStore + ?Sized> CounterStore for Arc<S> {
+    fn increment(&self, counter: &[u8], limit: u64) -> Result<Option<u64>> {
+        self.as_ref().increment(counter, limit)
+    }
+
+    fn count(&self, counter: &[u8]) -> Result<u64> {
+        self.as_ref().count(counter)
+    }
+}
+
+/// A counter store held in memory
+///
+/// # Example
+/// ```
+/// use stroopwafel::Stroopwafel;
+/// use stroopwafel::options::VerifyOptions;
+/// use stroopwafel::quota::MemoryCounterStore;
+/// use stroopwafel::restriction::Restriction;
+/// use stroopwafel::verifier::AcceptAllVerifier;
+/// use std::sync::Arc;
+///
+/// let root_key = b"secret";
+/// let mut token = Stroopwafel::new(root_key, b"partner-7", None::<String>);
+/// token.add_restriction(&Restriction::max_uses(2));
+///
+/// let options = VerifyOptions::new().with_counter_store(Arc::new(MemoryCounterStore::new()));
+/// assert!(token.verify_with_options(root_key, &AcceptAllVerifier, &[], &options).is_ok());
+/// assert!(token.verify_with_options(root_key, &AcceptAllVerifier, &[], &options).is_ok());
+/// assert!(token.verify_with_options(root_key, &AcceptAllVerifier, &[], &options).is_err());
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryCounterStore {
+    counters: Mutex<HashMap<Vec<u8>, u64>>,
+}
+
+impl MemoryCounterStore {
+    /// Creates a store with every counter at 0
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl CounterStore for MemoryCounterStore {
+    fn increment(&self, counter: &[u8], limit: u64) -> Result<Option<u64>> {
+        let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        let count = counters.entry(counter.to_vec()).or_insert(0);
+        if *count >= limit {
+            return Ok(None);
+        }
+        *count += 1;
+        Ok(Some(*count))
+    }
+
+    fn count(&self, counter: &[u8]) -> Result<u64> {
+        let counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(counters.get(counter).copied().unwrap_or(0))
+    }
+}
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteCounterStore;
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use super::CounterStore;
+    use crate::{Result, StroopwafelError};
+    use rusqlite::{Connection, OptionalExtension, params};
+    use std::path::Path;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    /// A counter store persisted in a local SQLite database
+    ///
+    /// Counters live in the `stroopwafel_counters` table, which is created on
+    /// open. Each increment is a single conditional upsert, so processes
+    /// sharing the database file cannot exceed a quota between them.
+    ///
+    /// Requires the `sqlite` feature.
+    #[derive(Debug)]
+    pub struct SqliteCounterStore {
+        connection: Mutex<Connection>,
+    }
+
+    impl SqliteCounterStore {
+        /// Opens (or creates) the database at `path`
+        ///
+        /// # Errors
+        /// Returns `StroopwafelError::Storage` if the database cannot be
+        /// opened or initialized.
+        pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+            Self::init(Connection::open(path).map_err(storage_error)?)
+        }
+
+        /// Opens a private in-memory database, e.g. for tests
+        ///
+        /// # Errors
+        /// Returns `StroopwafelError::Storage` if the database cannot be
+        /// initialized.
+        pub fn open_in_memory() -> Result<Self> {
+            Self::init(Connection::open_in_memory().map_err(storage_error)?)
+        }
+
+        fn init(connection: Connection) -> Result<Self> {
+            connection
+                .busy_timeout(Duration::from_secs(5))
+                .map_err(storage_error)?;
+            connection
+                .execute(
+                    "CREATE TABLE IF NOT EXISTS stroopwafel_counters (
+                        counter BLOB PRIMARY KEY,
+                        count INTEGER NOT NULL
+                    )",
+                    [],
+                )
+                .map_err(storage_error)?;
+
+            Ok(Self {
+                connection: Mutex::new(connection),
+            })
+        }
+    }
+
+    impl CounterStore for SqliteCounterStore {
+        fn increment(&self, counter: &[u8], limit: u64) -> Result<Option<u64>> {
+            if limit == 0 {
+                return Ok(None);
+            }
+
+            // SQLite integers are signed; no quota comes close to i64::MAX
+            let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+            let connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+            let count: Option<i64> = connection
+                .query_row(
+                    "INSERT INTO stroopwafel_counters (counter, count) VALUES (?1, 1)
+                     ON CONFLICT (counter) DO UPDATE SET count = count + 1 WHERE count < ?2
+                     RETURNING count",
+                    params![counter, limit],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(storage_error)?;
+
+            Ok(count.map(|count| count as u64))
+        }
+
+        fn count(&self, counter: &[u8]) -> Result<u64> {
+            let connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+            let count: Option<i64> = connection
+                .query_row(
+                    "SELECT count FROM stroopwafel_counters WHERE counter = ?1",
+                    params![counter],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(storage_error)?;
+
+            Ok(count.map_or(0, |count| count as u64))
+        }
+    }
+
+    fn storage_error(e: rusqlite::Error) -> StroopwafelError {
+        StroopwafelError::Storage(e.to_string())
+    }
+}
+
+/// Extracts the limit of a quota caveat (`uses <= N` or `uses < N`)
+///
+/// Returns `None` for any other caveat, including malformed quota caveats,
+/// which are left to the verifier.
+pub(crate) fn quota_caveat(caveat_id: &[u8]) -> Option<u64> {
+    let caveat = ParsedCaveat::parse(caveat_id).ok()?;
+    if caveat.key() != USES_KEY {
+        return None;
+    }
+
+    let value = match &caveat {
+        ParsedCaveat::Predicate(predicate) => predicate.value.parse().ok()?,
+        ParsedCaveat::Structured(caveat) => match caveat.value {
+            Value::Integer(value) => u64::try_from(value).ok()?,
+            _ => return None,
+        },
+    };
+    match caveat.operator() {
+        Operator::LessThanOrEqual => Some(value),
+        Operator::LessThan => Some(value.saturating_sub(1)),
+        _ => None,
+    }
+}
+
+/// Counts a presentation of `token` against `limit`, the tightest quota
+/// among its caveats, which passed every other check
+///
+/// # Errors
+/// Returns `StroopwafelError::QuotaExceeded` if the quota is used up.
+pub(crate) fn charge_quota(
+    store: &dyn CounterStore,
+    scope: QuotaScope,
+    token: &impl Token,
+    limit: u64,
+) -> Result<()> {
+    let counter: &[u8] = match scope {
+        QuotaScope::Identifier => token.identifier(),
+        QuotaScope::Signature => token.signature(),
+    };
+    match store.increment(counter, limit)? {
+        Some(_) => Ok(()),
+        None => Err(StroopwafelError::QuotaExceeded { limit }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Stroopwafel;
+    use crate::structured::StructuredCaveat;
+
+    #[test]
+    fn test_memory_store_increment() {
+        let store = MemoryCounterStore::new();
+        assert_eq!(store.increment(b"a", 2).unwrap(), Some(1));
+        assert_eq!(store.increment(b"a", 2).unwrap(), Some(2));
+        assert_eq!(store.increment(b"a", 2).unwrap(), None);
+        assert_eq!(store.count(b"a").unwrap(), 2);
+
+        // Raising the limit allows more
+        assert_eq!(store.increment(b"a", 3).unwrap(), Some(3));
+        assert_eq!(store.count(b"b").unwrap(), 0);
+        assert_eq!(store.increment(b"b", 0).unwrap(), None);
+    }
+
+    #[test]
+    fn test_memory_store_concurrent_increments() {
+        let store = Arc::new(MemoryCounterStore::new());
+        let accepted: usize = (0..16)
+            .map(|_| {
+                let store = Arc::clone(&store);
+                std::thread::spawn(move || store.increment(b"a", 5).unwrap().is_some())
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| usize::from(handle.join().unwrap()))
+            .sum();
+        assert_eq!(accepted, 5);
+    }
+
+    #[test]
+    fn test_quota_caveat() {
+        assert_eq!(quota_caveat(b"uses <= 1000"), Some(1000));
+        assert_eq!(quota_caveat(b"uses < 1000"), Some(999));
+        assert_eq!(quota_caveat(b"uses < 0"), Some(0));
+        let structured = StructuredCaveat::new(USES_KEY, Operator::LessThanOrEqual, 5).encode();
+        assert_eq!(quota_caveat(&structured), Some(5));
+
+        assert_eq!(quota_caveat(b"uses >= 10"), None);
+        assert_eq!(quota_caveat(b"uses <= lots"), None);
+        assert_eq!(quota_caveat(b"uses <= -1"), None);
+        assert_eq!(quota_caveat(b"account = alice"), None);
+    }
+
+    #[test]
+    fn test_charge_quota_scopes() {
+        let store = MemoryCounterStore::new();
+        let mut token = Stroopwafel::new(b"secret", b"partner", None::<String>);
+        token.add_first_party_caveat(b"uses <= 1");
+        let mut attenuated = token.clone();
+        attenuated.add_first_party_caveat(b"action = read");
+
+        for scope in [QuotaScope::Identifier, QuotaScope::Signature] {
+            assert!(charge_quota(&store, scope, &token, 1).is_ok());
+        }
+
+        // Identifier scope shares the counter with attenuated tokens
+        assert_eq!(
+            charge_quota(&store, QuotaScope::Identifier, &attenuated, 1),
+            Err(StroopwafelError::QuotaExceeded { limit: 1 })
+        );
+        assert!(charge_quota(&store, QuotaScope::Signature, &attenuated, 1).is_ok());
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_store_increment() {
+        let store = SqliteCounterStore::open_in_memory().unwrap();
+        assert_eq!(store.count(b"a").unwrap(), 0);
+        assert_eq!(store.increment(b"a", 2).unwrap(), Some(1));
+        assert_eq!(store.increment(b"a", 2).unwrap(), Some(2));
+        assert_eq!(store.increment(b"a", 2).unwrap(), None);
+        assert_eq!(store.count(b"a").unwrap(), 2);
+        assert_eq!(store.increment(b"b", 0).unwrap(), None);
+        assert_eq!(store.increment(b"b", u64::MAX).unwrap(), Some(1));
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_store_persists() {
+        let path = std::env::temp_dir().join(format!(
+            "stroopwafel-counters-{}.sqlite",
+            std::process::id()
+        ));
+        {
+            let store = SqliteCounterStore::open(&path).unwrap();
+            store.increment(b"a", 10).unwrap();
+        }
+        let store = SqliteCounterStore::open(&path).unwrap();
+        assert_eq!(store.increment(b"a", 10).unwrap(), Some(2));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,430 @@
+use crate::caveat::{CaveatContext, ParsedCaveat};
+use crate::clock::{Clock, SystemClock};
+use crate::predicate::Operator;
+use crate::restriction::NONCE_KEY;
+use crate::structured::Value;
+use crate::timestamp::Timestamp;
+use crate::{Result, StroopwafelError};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// Remembers the nonces of single-use tokens that have been presented
+///
+/// Attach a store to [`VerifyOptions`](crate::options::VerifyOptions) to make
+/// tokens with a nonce caveat (`nonce = <value>`, see
+/// [`Restriction::nonce`](crate::restriction::Restriction::nonce)) single-use.
+/// Nonce caveats are then satisfied by the store rather than the verifier.
+/// Once every other check has passed, verification records the nonce, and a
+/// second presentation fails with `StroopwafelError::Replayed`. Tokens that
+/// fail verification do not use up their nonce.
+///
+/// A nonce must be remembered while the token can still verify. Holders can
+/// append caveats but not remove them, so only the caveats before the nonce,
+/// in the same stroopwafel, are trusted to bound its lifetime: the nonce is
+/// recorded until the earliest expiry (`time <` or `time <=`) among them plus
+/// the [clock skew](crate::options::VerifyOptions::with_clock_skew), or
+/// forever if there is none. Add the expiry before the nonce.
+pub trait ReplayStore: Send + Sync {
+    /// Records `nonce` as used until `expires_at` (forever if `None`)
+    ///
+    /// Returns false, without changing anything, if the nonce is already
+    /// recorded and has not expired. Checking and recording must be atomic, so
+    /// that concurrent presentations of one token cannot both succeed.
+    fn record(&self, nonce: &str, expires_at: Option<SystemTime>) -> Result<bool>;
+
+    /// Returns true if `nonce` is recorded and has not expired, without
+    /// changing anything
+    fn is_recorded(&self, nonce: &str) -> Result<bool>;
+}
+
+impl<S: ReplayStore + ?Sized> ReplayStore for Arc<S> {
+    fn record(&self, nonce: &str, expires_at: Option<SystemTime>) -> Result<bool> {
+        self.as_ref().record(nonce, expires_at)
+    }
+
+    fn is_recorded(&self, nonce: &str) -> Result<bool> {
+        self.as_ref().is_recorded(nonce)
+    }
+}
+
+/// Stores at or above this size are swept for expired nonces
+const MIN_SWEEP_SIZE: usize = 1024;
+
+/// A replay store held in memory
+///
+/// Nonces are evicted once their token has expired: lazily, when a store
+/// that has grown since the last sweep records a nonce, or on demand with
+/// [`evict_expired`](Self::evict_expired).
+///
+/// # Example
+/// ```
+/// use stroopwafel::Stroopwafel;
+/// use stroopwafel::options::VerifyOptions;
+/// use stroopwafel::replay::MemoryReplayStore;
+/// use stroopwafel::restriction::Restriction;
+/// use stroopwafel::verifier::AcceptAllVerifier;
+/// use stroopwafel::StroopwafelError;
+/// use std::sync::Arc;
+///
+/// let root_key = b"secret";
+/// let mut token = Stroopwafel::new(root_key, b"password-reset", None::<String>);
+/// token.add_restriction(&Restriction::nonce("5f0c2a9e")?);
+///
+/// let options = VerifyOptions::new().with_replay_store(Arc::new(MemoryReplayStore::new()));
+/// assert!(token.verify_with_options(root_key, &AcceptAllVerifier, &[], &options).is_ok());
+/// assert!(matches!(
+///     token.verify_with_options(root_key, &AcceptAllVerifier, &[], &options),
+///     Err(StroopwafelError::Replayed { .. })
+/// ));
+/// # Ok::<(), StroopwafelError>(())
+/// ```
+pub struct MemoryReplayStore {
+    state: Mutex<State>,
+    clock: Box<dyn Clock>,
+}
+
+struct State {
+    nonces: HashMap<String, Option<SystemTime>>,
+    sweep_at: usize,
+}
+
+impl MemoryReplayStore {
+    /// Creates an empty store reading the time from the system clock
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(State {
+                nonces: HashMap::new(),
+                sweep_at: MIN_SWEEP_SIZE,
+            }),
+            clock: Box::new(SystemClock),
+        }
+    }
+
+    /// Reads the time that decides when nonces expire from `clock`
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// Returns the number of remembered nonces, including expired ones not
+    /// yet evicted
+    pub fn len(&self) -> usize {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .nonces
+            .len()
+    }
+
+    /// Returns true if no nonce is remembered
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops the nonces of expired tokens, returning how many were dropped
+    pub fn evict_expired(&self) -> usize {
+        let now = self.clock.now();
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.evict(now)
+    }
+}
+
+impl State {
+    fn evict(&mut self, now: SystemTime) -> usize {
+        let before = self.nonces.len();
+        self.nonces
+            .retain(|_, expires_at| !is_expired(*expires_at, now));
+        self.sweep_at = (self.nonces.len() * 2).max(MIN_SWEEP_SIZE);
+        before - self.nonces.len()
+    }
+}
+
+fn is_expired(expires_at: Option<SystemTime>, now: SystemTime) -> bool {
+    expires_at.is_some_and(|expires_at| expires_at <= now)
+}
+
+impl Default for MemoryReplayStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for MemoryReplayStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryReplayStore")
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl ReplayStore for MemoryReplayStore {
+    fn record(&self, nonce: &str, expires_at: Option<SystemTime>) -> Result<bool> {
+        let now = self.clock.now();
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        if state.nonces.len() >= state.sweep_at {
+            state.evict(now);
+        }
+
+        if let Some(recorded) = state.nonces.get(nonce)
+            && !is_expired(*recorded, now)
+        {
+            return Ok(false);
+        }
+
+        state.nonces.insert(nonce.to_string(), expires_at);
+        Ok(true)
+    }
+
+    fn is_recorded(&self, nonce: &str) -> Result<bool> {
+        let now = self.clock.now();
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(state
+            .nonces
+            .get(nonce)
+            .is_some_and(|recorded| !is_expired(*recorded, now)))
+    }
+}
+
+/// Extracts the nonce of a nonce caveat (`nonce = <value>`)
+///
+/// Returns `None` for any other caveat, including malformed nonce caveats,
+/// which are left to the verifier.
+pub(crate) fn nonce_caveat(caveat: ParsedCaveat) -> Option<String> {
+    if caveat.key() != NONCE_KEY || caveat.operator() != Operator::Equal {
+        return None;
+    }
+
+    let nonce = match caveat {
+        ParsedCaveat::Predicate(predicate) => predicate.value,
+        ParsedCaveat::Structured(caveat) => match caveat.value {
+            Value::String(nonce) => nonce,
+            Value::Integer(nonce) => nonce.to_string(),
+            Value::Boolean(_) => return None,
+        },
+    };
+    (!nonce.is_empty()).then_some(nonce)
+}
+
+/// The nonces of a presentation, gathered while its caveats are checked
+#[derive(Debug, Default)]
+pub(crate) struct Nonces {
+    /// Each nonce with the expiry it is kept until
+    nonces: Vec<(String, Option<Timestamp>)>,
+    /// The earliest expiry among the primary caveats seen so far
+    primary: Option<Timestamp>,
+    /// The discharge being read and the earliest expiry among its caveats
+    /// seen so far
+    discharge: Option<(usize, Option<Timestamp>)>,
+}
+
+impl Nonces {
+    /// Notes a caveat of the presentation, returning true if it is a nonce
+    /// caveat
+    pub(crate) fn observe(&mut self, caveat: &CaveatContext<'_>) -> bool {
+        let Ok(parsed) = ParsedCaveat::parse(caveat.predicate) else {
+            return false;
+        };
+
+        // Discharge caveats are handed over one discharge at a time
+        let earliest = match caveat.discharge {
+            None => &mut self.primary,
+            Some(discharge) => {
+                let (index, earliest) = self.discharge.get_or_insert((discharge.index, None));
+                if *index != discharge.index {
+                    *index = discharge.index;
+                    *earliest = None;
+                }
+                earliest
+            }
+        };
+
+        let expiry = parsed.expiry();
+        if let Some(nonce) = nonce_caveat(parsed) {
+            self.nonces.push((nonce, *earliest));
+            return true;
+        }
+        if let Some(expiry) = expiry {
+            *earliest = Some(earliest.map_or(expiry, |earliest| earliest.min(expiry)));
+        }
+        false
+    }
+
+    /// Refuses a presentation whose nonces were already used, without
+    /// recording anything
+    ///
+    /// # Errors
+    /// Returns `StroopwafelError::Replayed` for the first nonce that was
+    /// already used.
+    pub(crate) fn check(&self, store: &dyn ReplayStore) -> Result<()> {
+        for (nonce, _) in &self.nonces {
+            if store.is_recorded(nonce)? {
+                return Err(StroopwafelError::Replayed {
+                    nonce: nonce.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Records the nonces of a presentation that passed every other check
+    ///
+    /// # Errors
+    /// Returns `StroopwafelError::Replayed` for the first nonce that was
+    /// already used.
+    pub(crate) fn record(self, store: &dyn ReplayStore, clock_skew: Duration) -> Result<()> {
+        // Verifiers allowing for skew accept the token until that long past
+        // its expiry
+        let skew = i128::try_from(clock_skew.as_nanos()).unwrap_or(i128::MAX);
+
+        for (nonce, expiry) in self.nonces {
+            let expires_at =
+                expiry.and_then(|expiry| expiry.saturating_add_nanos(skew).to_system_time());
+            if !store.record(&nonce, expires_at)? {
+                return Err(StroopwafelError::Replayed { nonce });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::structured::StructuredCaveat;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn test_record_once() {
+        let store = MemoryReplayStore::new();
+        assert!(!store.is_recorded("a").unwrap());
+        assert!(store.record("a", None).unwrap());
+        assert!(store.is_recorded("a").unwrap());
+        assert!(!store.record("a", None).unwrap());
+        assert!(store.record("b", None).unwrap());
+        assert_eq!(store.len(), 2);
+    }
+
+    #[test]
+    fn test_expired_nonces_are_forgotten() {
+        let clock = Arc::new(ManualClock::at_unix(100));
+        let store = MemoryReplayStore::new().with_clock(Arc::clone(&clock));
+
+        assert!(store.record("a", Some(at(160))).unwrap());
+        assert!(store.record("b", None).unwrap());
+        assert!(!store.record("a", Some(at(160))).unwrap());
+
+        clock.advance(Duration::from_secs(60));
+        assert!(!store.is_recorded("a").unwrap());
+        assert_eq!(store.evict_expired(), 1);
+        assert!(store.record("a", Some(at(220))).unwrap());
+        assert!(!store.record("b", None).unwrap());
+    }
+
+    #[test]
+    fn test_record_sweeps_large_stores() {
+        let clock = Arc::new(ManualClock::at_unix(100));
+        let store = MemoryReplayStore::new().with_clock(Arc::clone(&clock));
+        for i in 0..MIN_SWEEP_SIZE {
+            store.record(&i.to_string(), Some(at(150))).unwrap();
+        }
+
+        clock.advance(Duration::from_secs(60));
+        store.record("fresh", None).unwrap();
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn test_concurrent_presentations() {
+        let store = Arc::new(MemoryReplayStore::new());
+        let accepted: usize = (0..8)
+            .map(|_| {
+                let store = Arc::clone(&store);
+                std::thread::spawn(move || store.record("once", None).unwrap())
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| usize::from(handle.join().unwrap()))
+            .sum();
+        assert_eq!(accepted, 1);
+    }
+
+    #[test]
+    fn test_nonce_caveat() {
+        let nonce = |caveat_id: &[u8]| nonce_caveat(ParsedCaveat::parse(caveat_id).unwrap());
+        assert_eq!(nonce(b"nonce = 5f0c"), Some("5f0c".to_string()));
+        let structured = StructuredCaveat::new(NONCE_KEY, Operator::Equal, 42).encode();
+        assert_eq!(nonce(&structured), Some("42".to_string()));
+
+        assert_eq!(nonce(b"nonce != 5f0c"), None);
+        assert_eq!(nonce(b"account = alice"), None);
+    }
+
+    #[test]
+    fn test_nonces_recorded_until_expiry() {
+        let clock = Arc::new(ManualClock::at_unix(100));
+        let store = MemoryReplayStore::new().with_clock(Arc::clone(&clock));
+        let nonces = || {
+            let mut nonces = Nonces::default();
+            assert!(!nonces.observe(&CaveatContext::primary(b"time < 300", 0)));
+            assert!(!nonces.observe(&CaveatContext::primary(b"time < 200", 1)));
+            assert!(nonces.observe(&CaveatContext::primary(b"nonce = abc", 2)));
+            nonces
+        };
+
+        assert!(nonces().record(&store, Duration::ZERO).is_ok());
+        assert_eq!(
+            nonces().record(&store, Duration::ZERO),
+            Err(StroopwafelError::Replayed {
+                nonce: "abc".to_string()
+            })
+        );
+
+        // The nonce is kept until the earliest expiry
+        clock.set(at(200));
+        assert!(nonces().record(&store, Duration::ZERO).is_ok());
+
+        // ... plus the clock skew verifiers allow
+        let skew = Duration::from_secs(30);
+        assert!(nonces().record(&store, skew).is_ok());
+        clock.set(at(229));
+        assert!(nonces().record(&store, Duration::ZERO).is_err());
+        clock.set(at(230));
+        assert!(nonces().record(&store, Duration::ZERO).is_ok());
+    }
+
+    #[test]
+    fn test_later_expiries_do_not_shorten_retention() {
+        let clock = Arc::new(ManualClock::at_unix(100));
+        let store = MemoryReplayStore::new().with_clock(Arc::clone(&clock));
+        let auth = crate::caveat::DischargeContext {
+            index: 0,
+            identifier: b"login",
+            verification_key_id: b"auth_key",
+            location: None,
+        };
+
+        let mut nonces = Nonces::default();
+        nonces.observe(&CaveatContext::primary(b"time < 200", 0));
+        nonces.observe(&CaveatContext::primary(b"nonce = abc", 1));
+        // An expiry a holder appended after the nonce
+        nonces.observe(&CaveatContext::primary(b"time < 101", 2));
+        // An expiry in a discharge
+        nonces.observe(&CaveatContext::discharge(b"time < 101", 0, auth));
+        nonces.observe(&CaveatContext::discharge(b"nonce = def", 1, auth));
+        nonces.record(&store, Duration::ZERO).unwrap();
+
+        clock.set(at(150));
+        assert!(!store.record("abc", None).unwrap());
+        assert!(store.record("def", None).unwrap());
+        clock.set(at(200));
+        assert!(store.record("abc", None).unwrap());
+    }
+}
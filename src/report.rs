@@ -0,0 +1,616 @@
+use crate::caveat::{
+    Caveat, CaveatContext, CaveatKind, CaveatSource, DischargeContext, ParsedCaveat,
+};
+use crate::epoch::{check_epoch, epoch_caveat};
+use crate::options::{UnrecognizedPolicy, VerifyOptions};
+use crate::quota::{QuotaScope, quota_caveat};
+use crate::replay::nonce_caveat;
+use crate::stroopwafel::Token;
+use crate::verifier::{CaveatOutcome, CaveatVerifier};
+use crate::{Result, Stroopwafel, StroopwafelError};
+
+/// The outcome of checking a single caveat
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaveatStatus {
+    /// The caveat is satisfied
+    Passed,
+    /// The caveat is violated, with the reason
+    Failed(String),
+    /// The caveat could not be evaluated (e.g. it could not be parsed), with the reason
+    Unknown(String),
+}
+
+impl CaveatStatus {
+    /// Returns true if the caveat is satisfied
+    pub fn is_passed(&self) -> bool {
+        matches!(self, CaveatStatus::Passed)
+    }
+
+    /// Classifies a verifier outcome into a status
+    fn from_outcome(outcome: CaveatOutcome) -> Self {
+        match outcome {
+            CaveatOutcome::Satisfied => CaveatStatus::Passed,
+            CaveatOutcome::Violated(reason)
+            | CaveatOutcome::Error(StroopwafelError::CaveatViolation(reason)) => {
+                CaveatStatus::Failed(reason)
+            }
+            CaveatOutcome::Unrecognized => {
+                CaveatStatus::Unknown("Caveat not recognized by the verifier".to_string())
+            }
+            CaveatOutcome::Error(e) => CaveatStatus::Unknown(e.to_string()),
+        }
+    }
+
+    /// Classifies the result of a check made without the verifier into a
+    /// status
+    fn from_result(result: Result<()>) -> Self {
+        match result {
+            Ok(()) => CaveatStatus::Passed,
+            Err(
+                e @ (StroopwafelError::StaleEpoch { .. }
+                | StroopwafelError::Replayed { .. }
+                | StroopwafelError::QuotaExceeded { .. }),
+            ) => CaveatStatus::Failed(e.to_string()),
+            Err(e) => CaveatStatus::Unknown(e.to_string()),
+        }
+    }
+}
+
+/// The result of checking one caveat of a stroopwafel or discharge
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaveatReport {
+    /// Position of the caveat within its macaroon
+    pub index: usize,
+    /// The macaroon the caveat belongs to
+    pub source: CaveatSource,
+    /// Whether the caveat is first- or third-party
+    pub kind: CaveatKind,
+    /// The caveat identifier (the predicate for first-party caveats)
+    pub caveat_id: Vec<u8>,
+    /// The outcome of the check
+    pub status: CaveatStatus,
+}
+
+/// A detailed account of a verification, covering every caveat
+///
+/// Unlike [`Stroopwafel::verify`], which stops at the first problem, a report
+/// checks every caveat of the primary stroopwafel and of each discharge it
+/// uses, so all problems can be shown at once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerificationReport {
+    /// Whether the primary stroopwafel's signature is valid
+    pub signature_valid: bool,
+    /// Failures of the checks on the token as a whole (revocation, then the
+    /// token policy)
+    pub token_failures: Vec<StroopwafelError>,
+    /// One entry per caveat, primary caveats first in order, each third-party
+    /// caveat followed by the caveats of its discharge
+    pub caveats: Vec<CaveatReport>,
+}
+
+impl VerificationReport {
+    /// Returns the overall decision: true only if the signature is valid, no
+    /// check on the token as a whole failed and every caveat passed
+    ///
+    /// This agrees with [`Stroopwafel::verify_with_options`] returning
+    /// `Ok(())` under the options the report was made with (the default
+    /// options, like [`Stroopwafel::verify`], for
+    /// [`verify_report`](Stroopwafel::verify_report)). A report uses nothing
+    /// up, though: a nonce or quota it reports as unused may be used by the
+    /// time the token is presented.
+    pub fn is_authorized(&self) -> bool {
+        self.signature_valid
+            && self.token_failures.is_empty()
+            && self.caveats.iter().all(|c| c.status.is_passed())
+    }
+
+    /// Returns the caveats that did not pass
+    pub fn failures(&self) -> impl Iterator<Item = &CaveatReport> {
+        self.caveats.iter().filter(|c| !c.status.is_passed())
+    }
+}
+
+impl Stroopwafel {
+    /// Verifies this stroopwafel with the default options and reports on
+    /// every caveat instead of stopping at the first failure
+    ///
+    /// Caveats are evaluated even when the signature is invalid, to help
+    /// diagnose the token, but such a report is never authorized.
+    ///
+    /// # Arguments
+    /// * `root_key` - The secret root key used to mint this stroopwafel
+    /// * `verifier` - A verifier that checks caveat predicates
+    /// * `discharges` - Optional discharge macaroons for third-party caveats
+    ///
+    /// # Example
+    /// ```
+    /// use stroopwafel::Stroopwafel;
+    /// use stroopwafel::verifier::ContextVerifier;
+    ///
+    /// let root_key = b"secret";
+    /// let mut token = Stroopwafel::new(root_key, b"identifier", None::<String>);
+    /// token.add_first_party_caveat(b"account = alice");
+    /// token.add_first_party_caveat(b"action = write");
+    ///
+    /// let verifier = ContextVerifier::empty()
+    ///     .with("account", "bob")
+    ///     .with("action", "read");
+    ///
+    /// let report = token.verify_report(root_key, &verifier, &[]);
+    /// assert!(report.signature_valid);
+    /// assert!(!report.is_authorized());
+    /// assert_eq!(report.failures().count(), 2);
+    /// ```
+    pub fn verify_report(
+        &self,
+        root_key: &[u8],
+        verifier: &impl CaveatVerifier,
+        discharges: &[Stroopwafel],
+    ) -> VerificationReport {
+        self.verify_report_with_options(root_key, verifier, discharges, &VerifyOptions::default())
+    }
+
+    /// Verifies this stroopwafel like
+    /// [`verify_with_options`](Self::verify_with_options) and reports on every
+    /// check instead of stopping at the first failure
+    ///
+    /// Revocation and the token policy are reported as
+    /// [`token_failures`](VerificationReport::token_failures), and caveats the
+    /// verifier does not recognize pass under [`UnrecognizedPolicy::Accept`].
+    /// Epoch, nonce and quota caveats are checked against the stores of
+    /// `options` without changing them: no nonce is recorded and no quota is
+    /// charged.
+    ///
+    /// # Example
+    /// ```
+    /// use stroopwafel::Stroopwafel;
+    /// use stroopwafel::options::VerifyOptions;
+    /// use stroopwafel::revocation::{MemoryRevocationStore, RevocationStore};
+    /// use stroopwafel::verifier::AcceptAllVerifier;
+    /// use std::sync::Arc;
+    ///
+    /// let root_key = b"secret";
+    /// let token = Stroopwafel::new(root_key, b"identifier", None::<String>);
+    ///
+    /// let revocations = Arc::new(MemoryRevocationStore::new());
+    /// revocations.revoke(b"identifier", None)?;
+    /// let options = VerifyOptions::new().with_revocation_store(revocations);
+    ///
+    /// let report = token.verify_report_with_options(root_key, &AcceptAllVerifier, &[], &options);
+    /// assert_eq!(report.token_failures.len(), 1);
+    /// assert!(!report.is_authorized());
+    /// # Ok::<(), stroopwafel::StroopwafelError>(())
+    /// ```
+    pub fn verify_report_with_options(
+        &self,
+        root_key: &[u8],
+        verifier: &impl CaveatVerifier,
+        discharges: &[Stroopwafel],
+        options: &VerifyOptions,
+    ) -> VerificationReport {
+        let signature_valid = self.signature_matches(root_key);
+        let token_failures = self.token_failures(discharges, options);
+        let mut caveats = Vec::with_capacity(self.caveats.len());
+
+        for (index, caveat) in self.caveats.iter().enumerate() {
+            // Reserve this caveat's slot ahead of any discharge caveats it pulls in
+            let position = caveats.len();
+            caveats.push(CaveatReport {
+                index,
+                source: CaveatSource::Primary,
+                kind: caveat.kind(),
+                caveat_id: caveat.caveat_id.clone(),
+                status: CaveatStatus::Passed,
+            });
+
+            caveats[position].status = if caveat.is_first_party() {
+                self.caveat_status(
+                    &CaveatContext::primary(&caveat.caveat_id, index),
+                    verifier,
+                    options,
+                )
+            } else {
+                self.report_third_party_caveat(caveat, discharges, verifier, options, &mut caveats)
+            };
+        }
+
+        VerificationReport {
+            signature_valid,
+            token_failures,
+            caveats,
+        }
+    }
+
+    /// Applies the checks of `options` that concern the token as a whole,
+    /// returning every failure
+    fn token_failures(
+        &self,
+        discharges: &[Stroopwafel],
+        options: &VerifyOptions,
+    ) -> Vec<StroopwafelError> {
+        let mut failures = Vec::new();
+
+        if let Some(store) = options.revocation_store() {
+            for identifier in std::iter::once(self)
+                .chain(discharges)
+                .map(|token| token.identifier.as_slice())
+            {
+                match store.is_revoked(identifier) {
+                    Ok(false) => {}
+                    Ok(true) => failures.push(StroopwafelError::Revoked {
+                        identifier: identifier.to_vec(),
+                    }),
+                    Err(e) => failures.push(e),
+                }
+            }
+        }
+
+        if let Some(policy) = options.token_policy()
+            && let Err(e) = policy.check_token(self)
+        {
+            failures.push(e);
+        }
+
+        failures
+    }
+
+    /// Checks one first-party caveat, with the stores of `options` for the
+    /// caveats they handle and with `verifier` for the rest
+    fn caveat_status(
+        &self,
+        context: &CaveatContext<'_>,
+        verifier: &impl CaveatVerifier,
+        options: &VerifyOptions,
+    ) -> CaveatStatus {
+        if let Some(result) = self.check_builtin(context.predicate, options) {
+            return CaveatStatus::from_result(result);
+        }
+
+        match verifier.check(context) {
+            CaveatOutcome::Unrecognized
+                if options.unrecognized_policy() == UnrecognizedPolicy::Accept =>
+            {
+                CaveatStatus::Passed
+            }
+            outcome => CaveatStatus::from_outcome(outcome),
+        }
+    }
+
+    /// Checks an epoch, nonce or quota caveat against the stores of
+    /// `options` without changing them, returning `None` for caveats left to
+    /// the verifier
+    fn check_builtin(&self, predicate: &[u8], options: &VerifyOptions) -> Option<Result<()>> {
+        if let Some(store) = options.epoch_store()
+            && let Some((subject, epoch)) = epoch_caveat(predicate)
+        {
+            return Some(check_epoch(store, subject, epoch));
+        }
+
+        if let Some(store) = options.replay_store()
+            && let Some(nonce) = ParsedCaveat::parse(predicate).ok().and_then(nonce_caveat)
+        {
+            return Some(match store.is_recorded(&nonce) {
+                Ok(true) => Err(StroopwafelError::Replayed { nonce }),
+                Ok(false) => Ok(()),
+                Err(e) => Err(e),
+            });
+        }
+
+        if let Some(store) = options.counter_store()
+            && let Some(limit) = quota_caveat(predicate)
+        {
+            let counter = match options.quota_scope() {
+                QuotaScope::Identifier => self.identifier.as_slice(),
+                QuotaScope::Signature => self.signature.as_slice(),
+            };
+            return Some(match store.count(counter) {
+                Ok(count) if count >= limit => Err(StroopwafelError::QuotaExceeded { limit }),
+                Ok(_) => Ok(()),
+                Err(e) => Err(e),
+            });
+        }
+
+        None
+    }
+
+    /// Checks a third-party caveat's discharge, appending reports for the
+    /// discharge's own caveats, and returns the status of the caveat itself
+    fn report_third_party_caveat(
+        &self,
+        caveat: &Caveat,
+        discharges: &[Stroopwafel],
+        verifier: &impl CaveatVerifier,
+        options: &VerifyOptions,
+        reports: &mut Vec<CaveatReport>,
+    ) -> CaveatStatus {
+        let Some(verification_key) = caveat.verification_key_id.as_ref() else {
+            return CaveatStatus::Unknown(
+                "Third-party caveat missing verification key".to_string(),
+            );
+        };
+
+        let Some((discharge_index, discharge)) = discharges
+            .iter()
+            .enumerate()
+            .find(|(_, d)| d.identifier == caveat.caveat_id)
+        else {
+            return CaveatStatus::Failed("Missing discharge macaroon".to_string());
+        };
+
+        let context = DischargeContext {
+            index: discharge_index,
+            identifier: &discharge.identifier,
+            verification_key_id: verification_key,
+            location: caveat.location.as_deref(),
+        };
+        let source = CaveatSource::Discharge {
+            index: discharge_index,
+            identifier: discharge.identifier.clone(),
+        };
+
+        for (index, discharge_caveat) in discharge.caveats.iter().enumerate() {
+            if discharge_caveat.is_first_party() {
+                reports.push(CaveatReport {
+                    index,
+                    source: source.clone(),
+                    kind: CaveatKind::FirstParty,
+                    caveat_id: discharge_caveat.caveat_id.clone(),
+                    status: self.caveat_status(
+                        &CaveatContext::discharge(&discharge_caveat.caveat_id, index, context),
+                        verifier,
+                        options,
+                    ),
+                });
+            }
+        }
+
+        if discharge.discharge_signature_matches(verification_key, &self.signature) {
+            CaveatStatus::Passed
+        } else {
+            CaveatStatus::Failed("Invalid discharge signature".to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verifier::{AcceptAllVerifier, ContextVerifier};
+
+    #[test]
+    fn test_report_all_passed() {
+        let root_key = b"secret";
+        let mut token = Stroopwafel::new(root_key, b"identifier", None::<String>);
+        token.add_first_party_caveat(b"account = alice");
+        token.add_first_party_caveat(b"action = read");
+
+        let verifier = ContextVerifier::empty()
+            .with("account", "alice")
+            .with("action", "read");
+        let report = token.verify_report(root_key, &verifier, &[]);
+
+        assert!(report.signature_valid);
+        assert!(report.is_authorized());
+        assert_eq!(report.caveats.len(), 2);
+        assert_eq!(report.failures().count(), 0);
+    }
+
+    #[test]
+    fn test_report_lists_every_failure() {
+        let root_key = b"secret";
+        let mut token = Stroopwafel::new(root_key, b"identifier", None::<String>);
+        token.add_first_party_caveat(b"account = alice");
+        token.add_first_party_caveat(b"action = read");
+        token.add_first_party_caveat(b"not a predicate");
+        token.add_first_party_caveat(b"level >= 3");
+
+        let verifier = ContextVerifier::empty()
+            .with("account", "bob")
+            .with("action", "read")
+            .with("level", "1");
+        let report = token.verify_report(root_key, &verifier, &[]);
+
+        assert!(report.signature_valid);
+        assert!(!report.is_authorized());
+
+        let statuses: Vec<_> = report.caveats.iter().map(|c| &c.status).collect();
+        assert!(matches!(statuses[0], CaveatStatus::Failed(_)));
+        assert!(matches!(statuses[1], CaveatStatus::Passed));
+        assert!(matches!(statuses[2], CaveatStatus::Unknown(_)));
+        assert!(matches!(statuses[3], CaveatStatus::Failed(_)));
+
+        let failed: Vec<_> = report.failures().map(|c| c.index).collect();
+        assert_eq!(failed, vec![0, 2, 3]);
+    }
+
+    #[test]
+    fn test_report_invalid_signature_still_evaluates_caveats() {
+        let root_key = b"secret";
+        let mut token = Stroopwafel::new(root_key, b"identifier", None::<String>);
+        token.add_first_party_caveat(b"account = alice");
+
+        let report = token.verify_report(b"wrong key", &AcceptAllVerifier, &[]);
+
+        assert!(!report.signature_valid);
+        assert!(report.caveats[0].status.is_passed());
+        assert!(!report.is_authorized());
+    }
+
+    #[test]
+    fn test_report_discharge_caveats() {
+        let root_key = b"secret";
+        let mut token = Stroopwafel::new(root_key, b"identifier", None::<String>);
+        token.add_first_party_caveat(b"account = alice");
+        token.add_third_party_caveat(b"auth", b"vk", "https://auth.example.com");
+        token.add_first_party_caveat(b"action = read");
+
+        let mut discharge = Stroopwafel::create_discharge(b"vk", b"auth", None::<String>);
+        discharge.add_first_party_caveat(b"level >= 5");
+        let unrelated = Stroopwafel::create_discharge(b"other", b"other", None::<String>);
+        let discharges = [unrelated, token.bind_discharge(&discharge)];
+
+        let verifier = ContextVerifier::empty()
+            .with("account", "alice")
+            .with("action", "read")
+            .with("level", "1");
+        let report = token.verify_report(root_key, &verifier, &discharges);
+
+        assert_eq!(report.caveats.len(), 4);
+
+        assert_eq!(report.caveats[1].kind, CaveatKind::ThirdParty);
+        assert_eq!(report.caveats[1].source, CaveatSource::Primary);
+        assert!(report.caveats[1].status.is_passed());
+
+        // The discharge caveat follows its third-party caveat
+        let discharge_caveat = &report.caveats[2];
+        assert_eq!(discharge_caveat.index, 0);
+        assert_eq!(
+            discharge_caveat.source,
+            CaveatSource::Discharge {
+                index: 1,
+                identifier: b"auth".to_vec(),
+            }
+        );
+        assert!(matches!(discharge_caveat.status, CaveatStatus::Failed(_)));
+
+        assert_eq!(report.caveats[3].index, 2);
+        assert_eq!(report.caveats[3].source, CaveatSource::Primary);
+        assert!(!report.is_authorized());
+    }
+
+    #[test]
+    fn test_report_missing_and_unbound_discharges() {
+        let root_key = b"secret";
+        let mut token = Stroopwafel::new(root_key, b"identifier", None::<String>);
+        token.add_third_party_caveat(b"auth", b"vk", "https://auth.example.com");
+        token.add_third_party_caveat(b"kyc", b"vk2", "https://kyc.example.com");
+
+        // Only an unbound discharge for the first caveat
+        let discharge = Stroopwafel::create_discharge(b"vk", b"auth", None::<String>);
+        let report = token.verify_report(root_key, &AcceptAllVerifier, &[discharge]);
+
+        assert_eq!(
+            report.caveats[0].status,
+            CaveatStatus::Failed("Invalid discharge signature".to_string())
+        );
+        assert_eq!(
+            report.caveats[1].status,
+            CaveatStatus::Failed("Missing discharge macaroon".to_string())
+        );
+        assert!(!report.is_authorized());
+    }
+
+    #[test]
+    fn test_report_token_failures() {
+        use crate::policy::TokenPolicy;
+        use crate::revocation::{MemoryRevocationStore, RevocationStore};
+        use std::sync::Arc;
+
+        let root_key = b"secret";
+        let mut token = Stroopwafel::new(root_key, b"identifier", None::<String>);
+        token.add_third_party_caveat(b"auth", b"vk", "https://auth.example.com");
+        let discharge = Stroopwafel::create_discharge(b"vk", b"auth", None::<String>);
+        let discharges = [token.bind_discharge(&discharge)];
+
+        let revocations = Arc::new(MemoryRevocationStore::new());
+        revocations.revoke(b"auth", None).unwrap();
+        let options = VerifyOptions::new()
+            .with_revocation_store(revocations)
+            .with_token_policy(TokenPolicy::new().require_key("account"));
+
+        let report =
+            token.verify_report_with_options(root_key, &AcceptAllVerifier, &discharges, &options);
+        assert!(report.signature_valid);
+        assert_eq!(report.failures().count(), 0);
+        assert_eq!(
+            report.token_failures,
+            vec![
+                StroopwafelError::Revoked {
+                    identifier: b"auth".to_vec()
+                },
+                StroopwafelError::MissingRestriction {
+                    key: "account".to_string()
+                },
+            ]
+        );
+        assert!(!report.is_authorized());
+        assert!(
+            token
+                .verify_with_options(root_key, &AcceptAllVerifier, &discharges, &options)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_report_with_options_uses_nothing_up() {
+        use crate::epoch::{EpochStore, MemoryEpochStore};
+        use crate::options::UnrecognizedPolicy;
+        use crate::quota::MemoryCounterStore;
+        use crate::replay::MemoryReplayStore;
+        use crate::restriction::Restriction;
+        use std::sync::Arc;
+
+        let root_key = b"secret";
+        let epochs = Arc::new(MemoryEpochStore::new());
+        let mut token = Stroopwafel::new(root_key, b"identifier", None::<String>);
+        token.add_restriction(&epochs.stamp("alice").unwrap());
+        token.add_restriction(&Restriction::nonce("d41d8cd9").unwrap());
+        token.add_restriction(&Restriction::max_uses(1));
+        token.add_first_party_caveat(b"not a predicate");
+
+        let options = VerifyOptions::new()
+            .with_epoch_store(epochs.clone())
+            .with_replay_store(Arc::new(MemoryReplayStore::new()))
+            .with_counter_store(Arc::new(MemoryCounterStore::new()))
+            .with_unrecognized_policy(UnrecognizedPolicy::Accept);
+        let verifier = ContextVerifier::empty();
+        let statuses = || {
+            token
+                .verify_report_with_options(root_key, &verifier, &[], &options)
+                .caveats
+                .into_iter()
+                .map(|c| c.status.is_passed())
+                .collect::<Vec<_>>()
+        };
+
+        // Reporting twice records no nonce and charges no quota
+        assert_eq!(statuses(), vec![true; 4]);
+        assert_eq!(statuses(), vec![true; 4]);
+        assert!(
+            token
+                .verify_with_options(root_key, &verifier, &[], &options)
+                .is_ok()
+        );
+
+        // Now the nonce and the quota are used up
+        assert_eq!(statuses(), vec![true, false, false, true]);
+
+        epochs.bump("alice").unwrap();
+        let report = token.verify_report_with_options(root_key, &verifier, &[], &options);
+        assert!(matches!(report.caveats[0].status, CaveatStatus::Failed(_)));
+        assert!(!report.is_authorized());
+
+        // Without the options these are ordinary caveats for the verifier
+        let report = token.verify_report(root_key, &verifier, &[]);
+        assert!(report.caveats.iter().all(|c| !c.status.is_passed()));
+    }
+
+    #[test]
+    fn test_report_agrees_with_verify() {
+        let root_key = b"secret";
+        let mut token = Stroopwafel::new(root_key, b"identifier", None::<String>);
+        token.add_first_party_caveat(b"account = alice");
+        token.add_third_party_caveat(b"auth", b"vk", "https://auth.example.com");
+
+        let discharge = Stroopwafel::create_discharge(b"vk", b"auth", None::<String>);
+        let discharges = [token.bind_discharge(&discharge)];
+
+        for account in ["alice", "bob"] {
+            let verifier = ContextVerifier::empty().with("account", account);
+            let report = token.verify_report(root_key, &verifier, &discharges);
+            let result = token.verify(root_key, &verifier, &discharges);
+
+            assert_eq!(report.is_authorized(), result.is_ok());
+        }
+    }
+}
@@ -0,0 +1,481 @@
+use crate::clock::{Clock, SystemClock, unix_seconds};
+use crate::predicate::{Operator, Predicate};
+use crate::{Result, StroopwafelError};
+use std::net::IpAddr;
+use std::time::{Duration, SystemTime};
+
+/// Context key used by time-based restrictions (Unix seconds)
+pub const TIME_KEY: &str = "time";
+
+/// Context key used by [`Restriction::account`]
+pub const ACCOUNT_KEY: &str = "account";
+
+/// Context key used by [`Restriction::actions`]
+pub const ACTION_KEY: &str = "action";
+
+/// Context key used by [`Restriction::resource_prefix`]
+pub const RESOURCE_KEY: &str = "resource";
+
+/// Context key used by [`Restriction::ip_range`]
+pub const IP_KEY: &str = "ip";
+
+/// Context key used by [`Restriction::max_uses`]
+pub const USES_KEY: &str = "uses";
+
+/// Context key used by [`Restriction::nonce`]
+pub const NONCE_KEY: &str = "nonce";
+
+/// Prefix of the context keys used by [`Restriction::epoch`]; the subject
+/// follows it
+pub const EPOCH_KEY_PREFIX: &str = "epoch:";
+
+/// A typed first-party caveat for a common restriction
+///
+/// Restrictions are built from typed values and always render to a predicate
+/// that [`ContextVerifier`](crate::verifier::ContextVerifier) understands, so
+/// minting code never has to format predicate strings by hand.
+///
+/// # Example
+/// ```
+/// use stroopwafel::Stroopwafel;
+/// use stroopwafel::restriction::Restriction;
+/// use stroopwafel::verifier::ContextVerifier;
+/// use std::time::Duration;
+///
+/// let root_key = b"secret";
+/// let mut token = Stroopwafel::new(root_key, b"identifier", None::<String>);
+/// token.add_restriction(&Restriction::account("alice")?);
+/// token.add_restriction(&Restriction::actions(["read", "list"])?);
+/// token.add_restriction(&Restriction::expires_in(Duration::from_secs(3600))?);
+///
+/// let verifier = ContextVerifier::with_current_time()
+///     .with("account", "alice")
+///     .with("action", "read");
+/// assert!(token.verify(root_key, &verifier, &[]).is_ok());
+/// # Ok::<(), stroopwafel::StroopwafelError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Restriction {
+    predicate: Predicate,
+}
+
+impl Restriction {
+    /// Restricts the token to be used strictly before `time`
+    ///
+    /// # Errors
+    /// Returns `StroopwafelError::InvalidTime` if `time` is before the Unix epoch.
+    pub fn expires_at(time: SystemTime) -> Result<Self> {
+        Ok(Self::new(
+            TIME_KEY,
+            Operator::LessThan,
+            unix_seconds(time)?.to_string(),
+        ))
+    }
+
+    /// Restricts the token to be used within `duration` from now
+    ///
+    /// # Errors
+    /// Returns `StroopwafelError::InvalidTime` if the expiry cannot be represented.
+    pub fn expires_in(duration: Duration) -> Result<Self> {
+        Self::expires_in_with_clock(duration, &SystemClock)
+    }
+
+    /// Restricts the token to be used within `duration` from the time read
+    /// from `clock`
+    ///
+    /// # Errors
+    /// Returns `StroopwafelError::InvalidTime` if the expiry cannot be represented.
+    pub fn expires_in_with_clock(duration: Duration, clock: &impl Clock) -> Result<Self> {
+        let expiry = clock
+            .now()
+            .checked_add(duration)
+            .ok_or_else(|| StroopwafelError::InvalidTime("expiry is out of range".to_string()))?;
+
+        Self::expires_at(expiry)
+    }
+
+    /// Restricts the token to be used at or after `time`
+    ///
+    /// # Errors
+    /// Returns `StroopwafelError::InvalidTime` if `time` is before the Unix epoch.
+    pub fn not_before(time: SystemTime) -> Result<Self> {
+        Ok(Self::new(
+            TIME_KEY,
+            Operator::GreaterThanOrEqual,
+            unix_seconds(time)?.to_string(),
+        ))
+    }
+
+    /// Restricts the token to a single account
+    ///
+    /// # Errors
+    /// Returns `StroopwafelError::InvalidFormat` if the account is empty or
+    /// would not parse back from the rendered predicate (e.g. it has leading
+    /// or trailing whitespace).
+    pub fn account(account: impl Into<String>) -> Result<Self> {
+        Self::parsing(ACCOUNT_KEY, Operator::Equal, account.into())
+    }
+
+    /// Restricts the token to a set of actions
+    ///
+    /// # Errors
+    /// Returns `StroopwafelError::InvalidFormat` if the set is empty, or if a
+    /// name is empty, contains a comma (which separates set members) or has
+    /// leading or trailing whitespace.
+    pub fn actions<I, S>(actions: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let actions = actions
+            .into_iter()
+            .map(|action| {
+                let action = action.as_ref();
+                if action.is_empty() || action.contains(',') || action.trim() != action {
+                    return Err(StroopwafelError::InvalidFormat(format!(
+                        "Invalid action name: '{action}'"
+                    )));
+                }
+                Ok(action.to_string())
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if actions.is_empty() {
+            return Err(StroopwafelError::InvalidFormat(
+                "Action set is empty".to_string(),
+            ));
+        }
+
+        Ok(Self::new(ACTION_KEY, Operator::In, actions.join(",")))
+    }
+
+    /// Restricts the token to resources starting with `prefix`
+    ///
+    /// # Errors
+    /// Returns `StroopwafelError::InvalidFormat` if the prefix is empty or
+    /// would not parse back from the rendered predicate.
+    pub fn resource_prefix(prefix: impl Into<String>) -> Result<Self> {
+        Self::parsing(RESOURCE_KEY, Operator::StartsWith, prefix.into())
+    }
+
+    /// Restricts the token to client addresses within `network/prefix_len`
+    ///
+    /// A prefix length longer than the address family allows is clamped to
+    /// the full address width, matching only `network` itself.
+    pub fn ip_range(network: IpAddr, prefix_len: u8) -> Self {
+        let max_len = match network {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+
+        Self::new(
+            IP_KEY,
+            Operator::InSubnet,
+            format!("{network}/{}", prefix_len.min(max_len)),
+        )
+    }
+
+    /// Stamps the token with `epoch`, the generation of `subject` (e.g. a user
+    /// ID) at minting time
+    ///
+    /// Renders to `epoch:<subject> = <epoch>`. Verification with an
+    /// [`EpochStore`](crate::epoch::EpochStore) rejects the token once the
+    /// subject's epoch moves past it.
+    ///
+    /// # Errors
+    /// Returns `StroopwafelError::InvalidFormat` if the subject is empty,
+    /// contains whitespace or would not parse back from the rendered predicate
+    /// (e.g. it contains an operator).
+    pub fn epoch(subject: impl AsRef<str>, epoch: u64) -> Result<Self> {
+        let subject = subject.as_ref();
+        if subject.is_empty() || subject.contains(char::is_whitespace) {
+            return Err(StroopwafelError::InvalidFormat(format!(
+                "Invalid epoch subject: '{subject}'"
+            )));
+        }
+
+        Self::parsing(
+            &format!("{EPOCH_KEY_PREFIX}{subject}"),
+            Operator::Equal,
+            epoch.to_string(),
+        )
+    }
+
+    /// Limits the token to `uses` presentations
+    ///
+    /// Renders to `uses <= <uses>`. Verification with a
+    /// [`CounterStore`](crate::quota::CounterStore) counts presentations and
+    /// rejects the token once the quota is used up.
+    pub fn max_uses(uses: u64) -> Self {
+        Self::new(USES_KEY, Operator::LessThanOrEqual, uses.to_string())
+    }
+
+    /// Makes the token single-use, identified by `nonce`
+    ///
+    /// Renders to `nonce = <nonce>`. Verification with a
+    /// [`ReplayStore`](crate::replay::ReplayStore) records the nonce and
+    /// rejects any later presentation. Nonces must be unique per token (e.g.
+    /// random). Add the token's expiry first: the nonce is only remembered
+    /// until the expiries before it.
+    ///
+    /// # Errors
+    /// Returns `StroopwafelError::InvalidFormat` if the nonce is empty or
+    /// contains whitespace.
+    pub fn nonce(nonce: impl Into<String>) -> Result<Self> {
+        let nonce = nonce.into();
+        if nonce.is_empty() || nonce.contains(char::is_whitespace) {
+            return Err(StroopwafelError::InvalidFormat(format!(
+                "Invalid nonce: '{nonce}'"
+            )));
+        }
+
+        Self::parsing(NONCE_KEY, Operator::Equal, nonce)
+    }
+
+    /// Returns the predicate this restriction renders to
+    pub fn predicate(&self) -> &Predicate {
+        &self.predicate
+    }
+
+    /// Returns the canonical caveat bytes for this restriction
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.predicate.to_string().into_bytes()
+    }
+
+    /// Builds a restriction from caller-supplied text, which must render to
+    /// a predicate that parses back to the same key, operator and value
+    fn parsing(key: &str, operator: Operator, value: String) -> Result<Self> {
+        let restriction = Self::new(key, operator, value);
+        match Predicate::parse(&restriction.predicate.to_string()) {
+            Ok(parsed) if parsed == restriction.predicate => Ok(restriction),
+            _ => Err(StroopwafelError::InvalidFormat(format!(
+                "Invalid restriction: '{}'",
+                restriction.predicate
+            ))),
+        }
+    }
+
+    fn new(key: &str, operator: Operator, value: String) -> Self {
+        Self {
+            predicate: Predicate {
+                key: key.to_string(),
+                operator,
+                value,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Stroopwafel;
+    use crate::clock::ManualClock;
+    use crate::verifier::{ContextVerifier, Verifier};
+    use std::net::{Ipv4Addr, Ipv6Addr};
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn test_epoch_bytes() {
+        assert_eq!(
+            Restriction::epoch("alice", 3).unwrap().to_bytes(),
+            b"epoch:alice = 3"
+        );
+    }
+
+    #[test]
+    fn test_max_uses_bytes() {
+        assert_eq!(Restriction::max_uses(1000).to_bytes(), b"uses <= 1000");
+    }
+
+    #[test]
+    fn test_nonce_bytes() {
+        assert_eq!(
+            Restriction::nonce("8f3a").unwrap().to_bytes(),
+            b"nonce = 8f3a"
+        );
+    }
+
+    #[test]
+    fn test_expires_at_bytes() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let restriction = Restriction::expires_at(time).unwrap();
+        assert_eq!(restriction.to_bytes(), b"time < 1700000000");
+    }
+
+    #[test]
+    fn test_not_before_bytes() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let restriction = Restriction::not_before(time).unwrap();
+        assert_eq!(restriction.to_bytes(), b"time >= 1700000000");
+    }
+
+    #[test]
+    fn test_expires_in_is_in_the_future() {
+        let restriction = Restriction::expires_in(Duration::from_secs(60)).unwrap();
+        let now = unix_seconds(SystemTime::now()).unwrap();
+        let expiry: u64 = restriction.predicate().value.parse().unwrap();
+
+        assert!(expiry >= now + 59 && expiry <= now + 61);
+    }
+
+    #[test]
+    fn test_expires_in_with_clock() {
+        let clock = ManualClock::at_unix(1_700_000_000);
+        let restriction =
+            Restriction::expires_in_with_clock(Duration::from_secs(60), &clock).unwrap();
+        assert_eq!(restriction.to_bytes(), b"time < 1700000060");
+    }
+
+    #[test]
+    fn test_times_before_epoch_are_errors() {
+        let before = UNIX_EPOCH - Duration::from_secs(1);
+        assert!(matches!(
+            Restriction::expires_at(before),
+            Err(StroopwafelError::InvalidTime(_))
+        ));
+        assert!(matches!(
+            Restriction::not_before(before),
+            Err(StroopwafelError::InvalidTime(_))
+        ));
+
+        let clock = ManualClock::new(before);
+        assert!(Restriction::expires_in_with_clock(Duration::ZERO, &clock).is_err());
+    }
+
+    #[test]
+    fn test_account_bytes() {
+        assert_eq!(
+            Restriction::account("alice").unwrap().to_bytes(),
+            b"account = alice"
+        );
+    }
+
+    #[test]
+    fn test_actions_bytes() {
+        let restriction = Restriction::actions(["read", "write"]).unwrap();
+        assert_eq!(restriction.to_bytes(), b"action in read,write");
+    }
+
+    #[test]
+    fn test_invalid_actions_are_errors() {
+        for actions in [
+            &["read,delete"][..],
+            &["read", " write "],
+            &["read", ""],
+            &[],
+        ] {
+            assert!(matches!(
+                Restriction::actions(actions),
+                Err(StroopwafelError::InvalidFormat(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_invalid_values_are_errors() {
+        let results = [
+            Restriction::account(""),
+            Restriction::account(" alice"),
+            Restriction::resource_prefix(""),
+            Restriction::epoch("", 3),
+            Restriction::epoch("a b", 3),
+            Restriction::epoch("a = b", 3),
+            Restriction::nonce(""),
+            Restriction::nonce("a b"),
+        ];
+        for result in results {
+            assert!(matches!(result, Err(StroopwafelError::InvalidFormat(_))));
+        }
+    }
+
+    #[test]
+    fn test_resource_prefix_bytes() {
+        assert_eq!(
+            Restriction::resource_prefix("/api/").unwrap().to_bytes(),
+            b"resource ^= /api/"
+        );
+    }
+
+    #[test]
+    fn test_ip_range_bytes() {
+        let v4 = Restriction::ip_range(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 0)), 8);
+        assert_eq!(v4.to_bytes(), b"ip <<= 10.0.0.0/8");
+
+        let clamped = Restriction::ip_range(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 99);
+        assert_eq!(clamped.to_bytes(), b"ip <<= 10.0.0.1/32");
+
+        let v6 = Restriction::ip_range(IpAddr::V6(Ipv6Addr::LOCALHOST), 128);
+        assert_eq!(v6.to_bytes(), b"ip <<= ::1/128");
+    }
+
+    #[test]
+    fn test_bytes_reparse_to_same_predicate() {
+        let restrictions = [
+            Restriction::expires_in(Duration::from_secs(60)).unwrap(),
+            Restriction::account("alice").unwrap(),
+            Restriction::actions(["read", "write"]).unwrap(),
+            Restriction::resource_prefix("/api/v1/").unwrap(),
+            Restriction::ip_range(IpAddr::V4(Ipv4Addr::new(192, 168, 0, 0)), 16),
+        ];
+
+        for restriction in restrictions {
+            let bytes = restriction.to_bytes();
+            let reparsed = Predicate::parse(std::str::from_utf8(&bytes).unwrap()).unwrap();
+            assert_eq!(&reparsed, restriction.predicate());
+        }
+    }
+
+    #[test]
+    fn test_restrictions_verify() {
+        let root_key = b"secret";
+        let mut token = Stroopwafel::new(root_key, b"identifier", None::<String>);
+        token.add_restriction(&Restriction::account("alice").unwrap());
+        token.add_restriction(&Restriction::actions(["read", "list"]).unwrap());
+        token.add_restriction(&Restriction::resource_prefix("/documents/").unwrap());
+        token.add_restriction(&Restriction::ip_range(
+            IpAddr::V4(Ipv4Addr::new(10, 0, 0, 0)),
+            8,
+        ));
+        token.add_restriction(&Restriction::expires_in(Duration::from_secs(3600)).unwrap());
+        token.add_restriction(
+            &Restriction::not_before(SystemTime::now() - Duration::from_secs(60)).unwrap(),
+        );
+
+        let verifier = ContextVerifier::with_current_time()
+            .with("account", "alice")
+            .with("action", "list")
+            .with("resource", "/documents/report.pdf")
+            .with("ip", "10.20.30.40");
+        assert!(token.verify(root_key, &verifier, &[]).is_ok());
+
+        let verifier = ContextVerifier::with_current_time()
+            .with("account", "alice")
+            .with("action", "delete")
+            .with("resource", "/documents/report.pdf")
+            .with("ip", "10.20.30.40");
+        assert!(token.verify(root_key, &verifier, &[]).is_err());
+    }
+
+    #[test]
+    fn test_expired_restriction_fails() {
+        let restriction =
+            Restriction::expires_at(SystemTime::now() - Duration::from_secs(10)).unwrap();
+        let verifier = ContextVerifier::with_current_time();
+
+        assert!(verifier.verify_caveat(&restriction.to_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_expiry_with_manual_clock() {
+        let clock = std::sync::Arc::new(ManualClock::at_unix(1_700_000_000));
+        let restriction =
+            Restriction::expires_in_with_clock(Duration::from_secs(60), &clock).unwrap();
+        let verifier = ContextVerifier::empty().with_clock(std::sync::Arc::clone(&clock));
+
+        assert!(verifier.verify_caveat(&restriction.to_bytes()).is_ok());
+
+        clock.advance(Duration::from_secs(60));
+        assert!(verifier.verify_caveat(&restriction.to_bytes()).is_err());
+    }
+}
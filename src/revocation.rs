@@ -0,0 +1,467 @@
+use crate::clock::unix_seconds;
+use crate::{Result, StroopwafelError};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A list of revoked token identifiers, consulted during verification
+///
+/// Revoking an identifier kills every token minted with it, and every
+/// discharge issued for the third-party caveat it names, without rotating the
+/// root key. Attach a store to
+/// [`VerifyOptions`](crate::options::VerifyOptions) to have verification fail
+/// with `StroopwafelError::Revoked` for the primary identifier and the
+/// identifier of each discharge passed in.
+///
+/// A revocation may carry an expiry, normally the expiry of the tokens it
+/// kills: once those tokens are dead anyway, [`prune`](Self::prune) drops the
+/// entry. Entries stay revoked until they are pruned.
+pub trait RevocationStore: Send + Sync {
+    /// Revokes `identifier`, keeping the entry at least until `expires_at`
+    /// (forever if `None`)
+    ///
+    /// Revoking an identifier again keeps the later expiry.
+    fn revoke(&self, identifier: &[u8], expires_at: Option<SystemTime>) -> Result<()>;
+
+    /// Returns true if `identifier` is revoked
+    fn is_revoked(&self, identifier: &[u8]) -> Result<bool>;
+
+    /// Drops revocations that expired at or before `now`, returning how many
+    /// were dropped
+    fn prune(&self, now: SystemTime) -> Result<usize>;
+}
+
+impl<S: RevocationStore + ?Sized> RevocationStore for Arc<S> {
+    fn revoke(&self, identifier: &[u8], expires_at: Option<SystemTime>) -> Result<()> {
+        self.as_ref().revoke(identifier, expires_at)
+    }
+
+    fn is_revoked(&self, identifier: &[u8]) -> Result<bool> {
+        self.as_ref().is_revoked(identifier)
+    }
+
+    fn prune(&self, now: SystemTime) -> Result<usize> {
+        self.as_ref().prune(now)
+    }
+}
+
+/// Expiries of revoked identifiers; `None` never expires
+type Entries = HashMap<Vec<u8>, Option<SystemTime>>;
+
+/// Records a revocation, keeping the later of two expiries
+fn insert(entries: &mut Entries, identifier: &[u8], expires_at: Option<SystemTime>) {
+    entries
+        .entry(identifier.to_vec())
+        .and_modify(|existing| {
+            *existing = match (*existing, expires_at) {
+                (Some(a), Some(b)) => Some(a.max(b)),
+                _ => None,
+            }
+        })
+        .or_insert(expires_at);
+}
+
+/// Drops expired entries, returning how many were dropped
+fn prune_entries(entries: &mut Entries, now: SystemTime) -> usize {
+    let before = entries.len();
+    entries.retain(|_, expires_at| expires_at.is_none_or(|expires_at| expires_at > now));
+    before - entries.len()
+}
+
+/// A revocation store held in memory
+///
+/// # Example
+/// ```
+/// use stroopwafel::Stroopwafel;
+/// use stroopwafel::options::VerifyOptions;
+/// use stroopwafel::revocation::{MemoryRevocationStore, RevocationStore};
+/// use stroopwafel::verifier::AcceptAllVerifier;
+/// use std::sync::Arc;
+///
+/// let root_key = b"secret";
+/// let token = Stroopwafel::new(root_key, b"token-42", None::<String>);
+///
+/// let store = Arc::new(MemoryRevocationStore::new());
+/// let options = VerifyOptions::new().with_revocation_store(store.clone());
+/// assert!(token.verify_with_options(root_key, &AcceptAllVerifier, &[], &options).is_ok());
+///
+/// store.revoke(b"token-42", None).unwrap();
+/// assert!(token.verify_with_options(root_key, &AcceptAllVerifier, &[], &options).is_err());
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryRevocationStore {
+    entries: RwLock<Entries>,
+}
+
+impl MemoryRevocationStore {
+    /// Creates an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of revoked identifiers
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// Returns true if nothing is revoked
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl RevocationStore for MemoryRevocationStore {
+    fn revoke(&self, identifier: &[u8], expires_at: Option<SystemTime>) -> Result<()> {
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        insert(&mut entries, identifier, expires_at);
+        Ok(())
+    }
+
+    fn is_revoked(&self, identifier: &[u8]) -> Result<bool> {
+        let entries = self.entries.read().unwrap_or_else(|e| e.into_inner());
+        Ok(entries.contains_key(identifier))
+    }
+
+    fn prune(&self, now: SystemTime) -> Result<usize> {
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        Ok(prune_entries(&mut entries, now))
+    }
+}
+
+/// A revocation store persisted to an append-only file
+///
+/// Each revocation appends one line, `<identifier hex> <expiry>`, where the
+/// expiry is in Unix seconds or `-` for none, and is synced to disk before
+/// [`revoke`](RevocationStore::revoke) returns. The whole list is loaded into
+/// memory on [`open`](Self::open), so lookups do not touch the file.
+/// [`prune`](RevocationStore::prune) compacts the file by writing the
+/// remaining entries to a temporary file and renaming it over the log.
+///
+/// Expiries are stored with whole-second precision, rounded up. A line
+/// without its trailing newline, left by a crash during `revoke`, was never
+/// acknowledged; [`open`](Self::open) drops it and truncates the log back to
+/// the last complete line.
+#[derive(Debug)]
+pub struct FileRevocationStore {
+    path: PathBuf,
+    entries: RwLock<Entries>,
+    log: Mutex<File>,
+}
+
+impl FileRevocationStore {
+    /// Opens the revocation log at `path`, creating it if needed
+    ///
+    /// # Errors
+    /// Returns `StroopwafelError::Storage` if the file cannot be read,
+    /// created or truncated, and `InvalidFormat` if a complete line is
+    /// malformed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut log = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&path)
+            .map_err(storage_error)?;
+
+        let mut contents = Vec::new();
+        log.read_to_end(&mut contents).map_err(storage_error)?;
+
+        // Drop a torn final line so the next revocation starts on a line of
+        // its own
+        let complete = contents
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |i| i + 1);
+        if complete < contents.len() {
+            log.set_len(complete as u64)
+                .and_then(|()| log.sync_data())
+                .map_err(storage_error)?;
+        }
+
+        let contents = std::str::from_utf8(&contents[..complete]).map_err(|_| {
+            StroopwafelError::InvalidFormat("Revocation log is not valid UTF-8".to_string())
+        })?;
+
+        let mut entries = Entries::new();
+        for line in contents.lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (identifier, expires_at) = parse_line(line)?;
+            insert(&mut entries, &identifier, expires_at);
+        }
+
+        Ok(Self {
+            path,
+            entries: RwLock::new(entries),
+            log: Mutex::new(log),
+        })
+    }
+
+    /// Returns the path of the revocation log
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl RevocationStore for FileRevocationStore {
+    fn revoke(&self, identifier: &[u8], expires_at: Option<SystemTime>) -> Result<()> {
+        let expires_at = expires_at.map(round_up).transpose()?;
+        let line = format_line(identifier, expires_at)?;
+
+        // Hold the log while updating memory so a concurrent prune cannot
+        // drop the entry between the two
+        let mut log = self.log.lock().unwrap_or_else(|e| e.into_inner());
+        log.write_all(line.as_bytes())
+            .and_then(|()| log.sync_data())
+            .map_err(storage_error)?;
+
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        insert(&mut entries, identifier, expires_at);
+        Ok(())
+    }
+
+    fn is_revoked(&self, identifier: &[u8]) -> Result<bool> {
+        let entries = self.entries.read().unwrap_or_else(|e| e.into_inner());
+        Ok(entries.contains_key(identifier))
+    }
+
+    fn prune(&self, now: SystemTime) -> Result<usize> {
+        let mut log = self.log.lock().unwrap_or_else(|e| e.into_inner());
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+
+        let mut remaining = entries.clone();
+        let pruned = prune_entries(&mut remaining, now);
+        if pruned == 0 {
+            return Ok(0);
+        }
+
+        let mut contents = String::new();
+        for (identifier, expires_at) in &remaining {
+            contents.push_str(&format_line(identifier, *expires_at)?);
+        }
+
+        let temp_path = self.path.with_extension("compact");
+        let mut temp = File::create(&temp_path).map_err(storage_error)?;
+        temp.write_all(contents.as_bytes())
+            .and_then(|()| temp.sync_all())
+            .map_err(storage_error)?;
+        fs::rename(&temp_path, &self.path).map_err(storage_error)?;
+
+        *log = OpenOptions::new()
+            .append(true)
+            .open(&self.path)
+            .map_err(storage_error)?;
+        *entries = remaining;
+        Ok(pruned)
+    }
+}
+
+/// Formats one log line
+fn format_line(identifier: &[u8], expires_at: Option<SystemTime>) -> Result<String> {
+    let expires_at = match expires_at {
+        Some(time) => unix_seconds(round_up(time)?)?.to_string(),
+        None => "-".to_string(),
+    };
+    Ok(format!("{} {expires_at}\n", hex::encode(identifier)))
+}
+
+/// Parses one log line
+fn parse_line(line: &str) -> Result<(Vec<u8>, Option<SystemTime>)> {
+    let invalid = || StroopwafelError::InvalidFormat(format!("Invalid revocation entry: {line}"));
+
+    let (identifier, expires_at) = line.trim().split_once(' ').ok_or_else(invalid)?;
+    let identifier = hex::decode(identifier).map_err(|_| invalid())?;
+    let expires_at = match expires_at {
+        "-" => None,
+        seconds => {
+            let seconds = seconds.parse::<u64>().map_err(|_| invalid())?;
+            Some(
+                UNIX_EPOCH
+                    .checked_add(Duration::from_secs(seconds))
+                    .ok_or_else(invalid)?,
+            )
+        }
+    };
+    Ok((identifier, expires_at))
+}
+
+/// Rounds a time up to whole Unix seconds, so a stored expiry never ends
+/// before the requested one
+fn round_up(time: SystemTime) -> Result<SystemTime> {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(since) if since.subsec_nanos() > 0 => since
+            .as_secs()
+            .checked_add(1)
+            .and_then(|seconds| UNIX_EPOCH.checked_add(Duration::from_secs(seconds)))
+            .ok_or_else(|| StroopwafelError::InvalidTime("expiry is out of range".to_string())),
+        _ => Ok(time),
+    }
+}
+
+fn storage_error(e: std::io::Error) -> StroopwafelError {
+    StroopwafelError::Storage(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A log path unique to this test run
+    fn temp_path(name: &str) -> PathBuf {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        std::env::temp_dir().join(format!("stroopwafel-{name}-{}-{n}.log", std::process::id()))
+    }
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn test_memory_store() {
+        let store = MemoryRevocationStore::new();
+        assert!(store.is_empty());
+        assert!(!store.is_revoked(b"token").unwrap());
+
+        store.revoke(b"token", Some(at(100))).unwrap();
+        assert!(store.is_revoked(b"token").unwrap());
+        assert!(!store.is_revoked(b"other").unwrap());
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn test_memory_store_prune() {
+        let store = MemoryRevocationStore::new();
+        store.revoke(b"short", Some(at(100))).unwrap();
+        store.revoke(b"long", Some(at(200))).unwrap();
+        store.revoke(b"forever", None).unwrap();
+
+        // Entries stay revoked until pruned
+        assert!(store.is_revoked(b"short").unwrap());
+        assert_eq!(store.prune(at(100)).unwrap(), 1);
+        assert!(!store.is_revoked(b"short").unwrap());
+        assert!(store.is_revoked(b"long").unwrap());
+
+        assert_eq!(store.prune(at(u32::MAX as u64)).unwrap(), 1);
+        assert!(store.is_revoked(b"forever").unwrap());
+    }
+
+    #[test]
+    fn test_revoke_again_keeps_later_expiry() {
+        let store = MemoryRevocationStore::new();
+        store.revoke(b"token", Some(at(200))).unwrap();
+        store.revoke(b"token", Some(at(100))).unwrap();
+        assert_eq!(store.prune(at(150)).unwrap(), 0);
+
+        store.revoke(b"token", None).unwrap();
+        assert_eq!(store.prune(at(300)).unwrap(), 0);
+        assert!(store.is_revoked(b"token").unwrap());
+    }
+
+    #[test]
+    fn test_file_store_persists() {
+        let path = temp_path("persist");
+        {
+            let store = FileRevocationStore::open(&path).unwrap();
+            store.revoke(b"token", None).unwrap();
+            store.revoke(&[0xff, 0x00], Some(at(100))).unwrap();
+        }
+
+        let store = FileRevocationStore::open(&path).unwrap();
+        assert!(store.is_revoked(b"token").unwrap());
+        assert!(store.is_revoked(&[0xff, 0x00]).unwrap());
+        assert!(!store.is_revoked(b"other").unwrap());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_file_store_prune_compacts() {
+        let path = temp_path("prune");
+        let store = FileRevocationStore::open(&path).unwrap();
+        store.revoke(b"old", Some(at(100))).unwrap();
+        store.revoke(b"new", Some(at(200))).unwrap();
+
+        assert_eq!(store.prune(at(150)).unwrap(), 1);
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 1);
+
+        // The log stays appendable after compaction
+        store.revoke(b"later", None).unwrap();
+        drop(store);
+
+        let store = FileRevocationStore::open(&path).unwrap();
+        assert!(!store.is_revoked(b"old").unwrap());
+        assert!(store.is_revoked(b"new").unwrap());
+        assert!(store.is_revoked(b"later").unwrap());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_file_store_rounds_expiry_up() {
+        let path = temp_path("round");
+        let store = FileRevocationStore::open(&path).unwrap();
+        store
+            .revoke(b"token", Some(at(100) + Duration::from_millis(1)))
+            .unwrap();
+        assert_eq!(store.prune(at(100)).unwrap(), 0);
+        assert_eq!(store.prune(at(101)).unwrap(), 1);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_file_store_rejects_corrupt_log() {
+        let path = temp_path("corrupt");
+        fs::write(&path, "not-hex 12\n").unwrap();
+        assert!(matches!(
+            FileRevocationStore::open(&path),
+            Err(StroopwafelError::InvalidFormat(_))
+        ));
+
+        // An expiry past the end of time is malformed, not a panic
+        fs::write(&path, format!("{} {}\n", hex::encode(b"token"), u64::MAX)).unwrap();
+        assert!(matches!(
+            FileRevocationStore::open(&path),
+            Err(StroopwafelError::InvalidFormat(_))
+        ));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_file_store_drops_torn_line() {
+        let path = temp_path("torn");
+        {
+            let store = FileRevocationStore::open(&path).unwrap();
+            store.revoke(b"token", None).unwrap();
+        }
+
+        // A crash mid-append leaves part of a line behind
+        let mut log = OpenOptions::new().append(true).open(&path).unwrap();
+        log.write_all(b"6f746865").unwrap();
+        drop(log);
+
+        let store = FileRevocationStore::open(&path).unwrap();
+        assert!(store.is_revoked(b"token").unwrap());
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            format_line(b"token", None).unwrap()
+        );
+
+        store.revoke(b"other", Some(at(100))).unwrap();
+        drop(store);
+
+        let store = FileRevocationStore::open(&path).unwrap();
+        assert!(store.is_revoked(b"token").unwrap());
+        assert!(store.is_revoked(b"other").unwrap());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
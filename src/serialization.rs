@@ -52,8 +52,7 @@ impl Stroopwafel {
     /// assert_eq!(original, deserialized);
     /// ```
     pub fn from_json(json: &str) -> Result<Self> {
-        serde_json::from_str(json)
-            .map_err(|e| StroopwafelError::DeserializationError(e.to_string()))
+        serde_json::from_str(json).map_err(|e| StroopwafelError::decode("JSON", e))
     }
 
     /// Serializes this stroopwafel to MessagePack binary format
@@ -89,8 +88,7 @@ impl Stroopwafel {
     /// assert_eq!(original, deserialized);
     /// ```
     pub fn from_msgpack(data: &[u8]) -> Result<Self> {
-        rmp_serde::from_slice(data)
-            .map_err(|e| StroopwafelError::DeserializationError(e.to_string()))
+        rmp_serde::from_slice(data).map_err(|e| StroopwafelError::decode("MessagePack", e))
     }
 
     /// Serializes this stroopwafel to a base64-encoded string (MessagePack encoding)
@@ -128,7 +126,7 @@ impl Stroopwafel {
     pub fn from_base64(b64: &str) -> Result<Self> {
         let bytes = URL_SAFE_NO_PAD
             .decode(b64.as_bytes())
-            .map_err(|e| StroopwafelError::DeserializationError(e.to_string()))?;
+            .map_err(|e| StroopwafelError::decode("base64", e))?;
 
         Self::from_msgpack(&bytes)
     }
@@ -163,8 +161,7 @@ impl Stroopwafel {
     /// assert_eq!(original, deserialized);
     /// ```
     pub fn from_hex(hex_str: &str) -> Result<Self> {
-        let msgpack = hex::decode(hex_str)
-            .map_err(|e| StroopwafelError::DeserializationError(e.to_string()))?;
+        let msgpack = hex::decode(hex_str).map_err(|e| StroopwafelError::decode("hex", e))?;
         Self::from_msgpack(&msgpack)
     }
 }
@@ -308,6 +305,25 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_decode_errors_keep_source() {
+        use std::error::Error;
+
+        for (result, format) in [
+            (Stroopwafel::from_json("not valid json"), "JSON"),
+            (Stroopwafel::from_base64("!!!"), "base64"),
+            (Stroopwafel::from_hex("zzz"), "hex"),
+            (Stroopwafel::from_msgpack(&[0xff]), "MessagePack"),
+        ] {
+            let err = result.unwrap_err();
+            let StroopwafelError::Decode(ref decode) = err else {
+                panic!("expected a decode error, got {err:?}");
+            };
+            assert_eq!(decode.format(), format);
+            assert!(err.source().and_then(|s| s.source()).is_some());
+        }
+    }
+
     #[test]
     fn test_invalid_msgpack() {
         let result = Stroopwafel::from_msgpack(&[0xff, 0xff, 0xff]); // Invalid MessagePack
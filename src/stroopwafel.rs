@@ -1,6 +1,14 @@
-use crate::caveat::Caveat;
+use crate::caveat::{
+    Caveat, CaveatContext, CaveatParts, DischargeContext, ParsedCaveat, describe_caveat,
+};
 use crate::crypto::{SIGNATURE_SIZE, bind_caveat, hmac_sha3, signatures_equal};
-use crate::verifier::Verifier;
+use crate::epoch::{check_epoch, epoch_caveat};
+use crate::options::{UnrecognizedPolicy, VerifyOptions};
+use crate::quota::{charge_quota, quota_caveat};
+use crate::replay::Nonces;
+use crate::restriction::Restriction;
+use crate::structured::StructuredCaveat;
+use crate::verifier::{AsyncVerifier, CaveatOutcome, CaveatVerifier};
 use crate::{Result, StroopwafelError};
 use serde::{Deserialize, Serialize};
 
@@ -113,6 +121,77 @@ impl Stroopwafel {
         self.caveats.push(Caveat::first_party(caveat_id));
     }
 
+    /// Adds a first-party caveat after checking that it is a valid predicate
+    ///
+    /// The caveat is parsed with the same grammar that
+    /// [`ContextVerifier`](crate::verifier::ContextVerifier) uses, either as a
+    /// text predicate or as a structured caveat. Malformed caveats are rejected
+    /// before the signature is extended, leaving this stroopwafel unchanged.
+    ///
+    /// Use [`add_first_party_caveat`](Self::add_first_party_caveat) for opaque
+    /// caveats checked by custom verifiers.
+    ///
+    /// # Errors
+    /// Returns `StroopwafelError::InvalidFormat` if the caveat cannot be parsed.
+    ///
+    /// # Example
+    /// ```
+    /// use stroopwafel::Stroopwafel;
+    ///
+    /// let root_key = b"secret";
+    /// let mut stroopwafel = Stroopwafel::new(root_key, b"identifier", None::<String>);
+    /// assert!(stroopwafel.try_add_first_party_caveat(b"account = alice").is_ok());
+    /// assert!(stroopwafel.try_add_first_party_caveat(b"acount alice").is_err());
+    /// assert_eq!(stroopwafel.caveat_count(), 1);
+    /// ```
+    pub fn try_add_first_party_caveat(&mut self, predicate: impl Into<Vec<u8>>) -> Result<()> {
+        let caveat_id = predicate.into();
+        ParsedCaveat::parse(&caveat_id)?;
+
+        self.add_first_party_caveat(caveat_id);
+        Ok(())
+    }
+
+    /// Adds a typed restriction as a first-party caveat
+    ///
+    /// The restriction is rendered to its canonical predicate bytes and added
+    /// with [`add_first_party_caveat`](Self::add_first_party_caveat).
+    ///
+    /// # Example
+    /// ```
+    /// use stroopwafel::Stroopwafel;
+    /// use stroopwafel::restriction::Restriction;
+    ///
+    /// let root_key = b"secret";
+    /// let mut stroopwafel = Stroopwafel::new(root_key, b"identifier", None::<String>);
+    /// stroopwafel.add_restriction(&Restriction::account("alice")?);
+    /// assert_eq!(stroopwafel.caveats[0].caveat_id, b"account = alice");
+    /// # Ok::<(), stroopwafel::StroopwafelError>(())
+    /// ```
+    pub fn add_restriction(&mut self, restriction: &Restriction) {
+        self.add_first_party_caveat(restriction.to_bytes());
+    }
+
+    /// Adds a structured first-party caveat to this stroopwafel
+    ///
+    /// The caveat is encoded into its binary form (see [`StructuredCaveat::encode`])
+    /// and bound to the signature chain exactly like a string predicate.
+    ///
+    /// # Example
+    /// ```
+    /// use stroopwafel::Stroopwafel;
+    /// use stroopwafel::predicate::Operator;
+    /// use stroopwafel::structured::StructuredCaveat;
+    ///
+    /// let root_key = b"secret";
+    /// let mut stroopwafel = Stroopwafel::new(root_key, b"identifier", None::<String>);
+    /// stroopwafel.add_structured_caveat(&StructuredCaveat::new("count", Operator::LessThan, 100));
+    /// assert!(stroopwafel.caveats[0].is_structured());
+    /// ```
+    pub fn add_structured_caveat(&mut self, caveat: &StructuredCaveat) {
+        self.add_first_party_caveat(caveat.encode());
+    }
+
     /// Adds a third-party caveat to this stroopwafel
     ///
     /// Third-party caveats require verification by an external party.
@@ -397,116 +476,412 @@ impl Stroopwafel {
     pub fn verify(
         &self,
         root_key: &[u8],
-        verifier: &impl Verifier,
+        verifier: &impl CaveatVerifier,
         discharges: &[Stroopwafel],
     ) -> Result<()> {
-        // Step 1: Rebuild the signature chain
-        let mut computed_signature = hmac_sha3(root_key, &self.identifier);
+        self.verify_with_options(root_key, verifier, discharges, &VerifyOptions::default())
+    }
 
-        for caveat in &self.caveats {
-            if caveat.is_first_party() {
-                // For first-party caveats, bind the caveat_id
-                computed_signature = bind_caveat(&computed_signature, &caveat.caveat_id);
-            } else {
-                // For third-party caveats, bind the verification_key_id
-                if let Some(ref vk_id) = caveat.verification_key_id {
-                    computed_signature = bind_caveat(&computed_signature, vk_id);
+    /// Verifies this stroopwafel like [`verify`](Self::verify), with explicit options
+    ///
+    /// Each caveat is checked with [`CaveatVerifier::check`]; caveats the
+    /// verifier does not recognize are handled according to
+    /// [`VerifyOptions::unrecognized_policy`].
+    pub fn verify_with_options(
+        &self,
+        root_key: &[u8],
+        verifier: &impl CaveatVerifier,
+        discharges: &[Stroopwafel],
+        options: &VerifyOptions,
+    ) -> Result<()> {
+        self.verify_token(root_key, verifier, discharges, options)
+    }
+
+    /// Verifies this stroopwafel with an asynchronous verifier
+    ///
+    /// Behaves exactly like [`verify`](Self::verify), but awaits the verifier
+    /// for each caveat, so checks that need I/O (e.g. a database lookup) do
+    /// not block. Signatures and discharges are checked before the verifier is
+    /// called for any caveat, so unlike `verify`, an invalid discharge is
+    /// reported even if an earlier caveat is violated. Synchronous verifiers
+    /// can be used through [`SyncAdapter`](crate::verifier::SyncAdapter).
+    ///
+    /// # Example
+    /// ```
+    /// use stroopwafel::Stroopwafel;
+    /// use stroopwafel::verifier::{ContextVerifier, SyncAdapter};
+    ///
+    /// # async fn check() -> stroopwafel::Result<()> {
+    /// let root_key = b"secret";
+    /// let mut stroopwafel = Stroopwafel::new(root_key, b"identifier", None::<String>);
+    /// stroopwafel.add_first_party_caveat(b"account = alice");
+    ///
+    /// let verifier = SyncAdapter::new(ContextVerifier::empty().with("account", "alice"));
+    /// stroopwafel.verify_async(root_key, &verifier, &[]).await
+    /// # }
+    /// ```
+    pub async fn verify_async(
+        &self,
+        root_key: &[u8],
+        verifier: &impl AsyncVerifier,
+        discharges: &[Stroopwafel],
+    ) -> Result<()> {
+        self.verify_async_with_options(root_key, verifier, discharges, &VerifyOptions::default())
+            .await
+    }
+
+    /// Verifies this stroopwafel like [`verify_async`](Self::verify_async),
+    /// with explicit options
+    pub async fn verify_async_with_options(
+        &self,
+        root_key: &[u8],
+        verifier: &impl AsyncVerifier,
+        discharges: &[Stroopwafel],
+        options: &VerifyOptions,
+    ) -> Result<()> {
+        self.authenticate(root_key, options)?;
+        self.admit(discharges, options)?;
+
+        // Verify every discharge before the first await
+        let mut pending = Vec::with_capacity(self.caveat_count());
+        self.for_each_caveat(discharges, |caveat| {
+            pending.push(caveat);
+            Ok(())
+        })?;
+
+        let mut usage = Usage::default();
+        for caveat in &pending {
+            if settle_builtin(caveat, options, &mut usage)? {
+                continue;
+            }
+            settle(caveat, verifier.check(caveat).await, options)?;
+        }
+
+        self.consume(usage, options)
+    }
+}
+
+impl Token for Stroopwafel {
+    fn identifier(&self) -> &[u8] {
+        &self.identifier
+    }
+
+    fn signature(&self) -> &[u8; SIGNATURE_SIZE] {
+        &self.signature
+    }
+
+    fn caveat_count(&self) -> usize {
+        self.caveats.len()
+    }
+
+    fn caveat_parts(&self) -> impl Iterator<Item = CaveatParts<'_>> {
+        self.caveats.iter().map(Caveat::parts)
+    }
+}
+
+/// The parts of a token that verification reads
+///
+/// Implemented by [`Stroopwafel`] and the borrowed
+/// [`StroopwafelRef`](crate::view::StroopwafelRef), so both are verified by
+/// the same code.
+pub(crate) trait Token: Sized {
+    /// Returns the public identifier
+    fn identifier(&self) -> &[u8];
+
+    /// Returns the chained signature
+    fn signature(&self) -> &[u8; SIGNATURE_SIZE];
+
+    /// Returns the number of caveats
+    fn caveat_count(&self) -> usize;
+
+    /// Returns the caveats, in order
+    fn caveat_parts(&self) -> impl Iterator<Item = CaveatParts<'_>>;
+
+    /// Verifies the token: its signature chain, then everything else
+    fn verify_token(
+        &self,
+        root_key: &[u8],
+        verifier: &impl CaveatVerifier,
+        discharges: &[Self],
+        options: &VerifyOptions,
+    ) -> Result<()> {
+        // Step 1: Verify the signature chain of the token
+        self.authenticate(root_key, options)?;
+
+        self.verify_signed(verifier, discharges, options)
+    }
+
+    /// Verifies everything but the primary signature chain, which the caller
+    /// has already checked
+    fn verify_signed(
+        &self,
+        verifier: &impl CaveatVerifier,
+        discharges: &[Self],
+        options: &VerifyOptions,
+    ) -> Result<()> {
+        // Step 2: Check revocation and the token policy
+        self.admit(discharges, options)?;
+
+        // Step 3: Check the caveats in order, verifying each discharge when
+        // its third-party caveat is reached
+        let mut usage = Usage::default();
+        self.for_each_caveat(discharges, |caveat| {
+            if settle_builtin(&caveat, options, &mut usage)? {
+                return Ok(());
+            }
+            settle(&caveat, verifier.check(&caveat), options)
+        })?;
+
+        // Step 4: Use up single-use tokens and quotas, now that everything
+        // else passed
+        self.consume(usage, options)
+    }
+
+    /// Applies the checks of `options` that concern the token as a whole:
+    /// revocation, then the token policy
+    fn admit(&self, discharges: &[Self], options: &VerifyOptions) -> Result<()> {
+        if let Some(store) = options.revocation_store() {
+            for identifier in std::iter::once(self)
+                .chain(discharges)
+                .map(Token::identifier)
+            {
+                if store.is_revoked(identifier)? {
+                    return Err(StroopwafelError::Revoked {
+                        identifier: identifier.to_vec(),
+                    });
                 }
             }
         }
 
-        // Step 2: Verify the signature matches (constant-time comparison)
-        if !signatures_equal(&computed_signature, &self.signature) {
+        if let Some(policy) = options.token_policy() {
+            policy.check_token(self)?;
+        }
+
+        Ok(())
+    }
+
+    /// Records the presentation of a token that passed every check: its
+    /// usage quota, then its nonces
+    ///
+    /// Replays are refused before the quota is charged, and the quota is
+    /// charged before nonces are recorded, so neither is used up by a
+    /// presentation the other refuses. Only a replay racing the presentation
+    /// that records the nonce can still be charged.
+    fn consume(&self, usage: Usage, options: &VerifyOptions) -> Result<()> {
+        if let Some(store) = options.replay_store() {
+            usage.nonces.check(store)?;
+        }
+
+        if let Some(store) = options.counter_store()
+            && let Some(limit) = usage.quota
+        {
+            charge_quota(store, options.quota_scope(), self, limit)?;
+        }
+
+        if let Some(store) = options.replay_store() {
+            usage.nonces.record(store, options.clock_skew())?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks the primary signature chain, resuming from the checkpoint
+    /// cache of `options` if one is configured
+    fn authenticate(&self, root_key: &[u8], options: &VerifyOptions) -> Result<()> {
+        let valid = match options.checkpoint_cache() {
+            Some(cache) => cache.signature_matches(self, root_key),
+            None => self.signature_matches(root_key),
+        };
+        if !valid {
             return Err(StroopwafelError::InvalidSignature);
         }
+        Ok(())
+    }
 
-        // Step 3: Verify each caveat
-        for caveat in &self.caveats {
+    /// Hands this token's first-party caveats to `check`, in order, with the
+    /// first-party caveats of each discharge in place of the third-party
+    /// caveat it satisfies
+    ///
+    /// Each discharge is verified when its third-party caveat is reached, so
+    /// an earlier caveat's error wins over a later discharge's. The primary
+    /// signature must already have been checked.
+    fn for_each_caveat<'a>(
+        &'a self,
+        discharges: &'a [Self],
+        mut check: impl FnMut(CaveatContext<'a>) -> Result<()>,
+    ) -> Result<()> {
+        for (index, caveat) in self.caveat_parts().enumerate() {
             if caveat.is_first_party() {
-                // Verify first-party caveat with the verifier
-                verifier.verify_caveat(&caveat.caveat_id)?;
-            } else {
-                // Verify third-party caveat with discharge macaroon
-                self.verify_third_party_caveat(caveat, discharges, verifier)?;
+                check(CaveatContext::primary(caveat.caveat_id, index))?;
+                continue;
+            }
+
+            let (discharge, discharge_context) = self.resolve_discharge(caveat, discharges)?;
+
+            // Only the discharge's first-party caveats are checked
+            for (index, caveat) in discharge.caveat_parts().enumerate() {
+                if caveat.is_first_party() {
+                    check(CaveatContext::discharge(
+                        caveat.caveat_id,
+                        index,
+                        discharge_context,
+                    ))?;
+                }
             }
         }
 
         Ok(())
     }
 
-    /// Verifies a third-party caveat using discharge macaroons
-    fn verify_third_party_caveat(
+    /// Finds the discharge for a third-party caveat and verifies its binding,
+    /// returning it with the context its caveats are checked in
+    ///
+    /// The discharge signature should be: HMAC(original_discharge_sig, primary.signature)
+    fn resolve_discharge<'a>(
         &self,
-        caveat: &Caveat,
-        discharges: &[Stroopwafel],
-        verifier: &impl Verifier,
-    ) -> Result<()> {
-        // Find the discharge macaroon for this caveat
-        let discharge = discharges
+        caveat: CaveatParts<'a>,
+        discharges: &'a [Self],
+    ) -> Result<(&'a Self, DischargeContext<'a>)> {
+        let (discharge_index, discharge) = discharges
             .iter()
-            .find(|d| d.identifier == caveat.caveat_id)
-            .ok_or_else(|| {
-                StroopwafelError::CaveatViolation(format!(
-                    "Missing discharge macaroon for caveat: {}",
-                    String::from_utf8_lossy(&caveat.caveat_id)
-                ))
+            .enumerate()
+            .find(|(_, d)| d.identifier() == caveat.caveat_id)
+            .ok_or_else(|| StroopwafelError::MissingDischarge {
+                caveat_id: caveat.caveat_id.to_vec(),
             })?;
 
-        // Verify the discharge macaroon's binding
-        // The discharge signature should be: HMAC(original_discharge_sig, primary.signature)
-        // We need to verify the discharge was properly bound
-        let verification_key = caveat.verification_key_id.as_ref().ok_or_else(|| {
+        let verification_key = caveat.verification_key_id.ok_or_else(|| {
             StroopwafelError::InvalidFormat(
                 "Third-party caveat missing verification key".to_string(),
             )
         })?;
 
-        // Verify the discharge macaroon itself
-        discharge.verify_discharge(verification_key, &self.signature, verifier)?;
+        if !discharge.discharge_signature_matches(verification_key, self.signature()) {
+            return Err(StroopwafelError::InvalidDischargeSignature {
+                identifier: discharge.identifier().to_vec(),
+            });
+        }
 
-        Ok(())
+        let context = DischargeContext {
+            index: discharge_index,
+            identifier: discharge.identifier(),
+            verification_key_id: verification_key,
+            location: caveat.location,
+        };
+        Ok((discharge, context))
+    }
+
+    /// Rebuilds the signature chain over this token's caveats from `key`
+    fn chain_signature(&self, key: &[u8]) -> [u8; SIGNATURE_SIZE] {
+        self.chain_elements()
+            .fold(hmac_sha3(key, self.identifier()), |signature, element| {
+                bind_caveat(&signature, element)
+            })
+    }
+
+    /// Returns the bytes each caveat binds into the signature chain, in order
+    fn chain_elements(&self) -> impl Iterator<Item = &[u8]> {
+        self.caveat_parts().filter_map(CaveatParts::chain_element)
+    }
+
+    /// Returns true if this token's signature chain is valid under `root_key`
+    fn signature_matches(&self, root_key: &[u8]) -> bool {
+        // Constant-time comparison
+        signatures_equal(&self.chain_signature(root_key), self.signature())
     }
 
-    /// Verifies a discharge macaroon
-    fn verify_discharge(
+    /// Returns true if this discharge's chain is valid under `verification_key`
+    /// and it is bound to the primary token's signature
+    fn discharge_signature_matches(
         &self,
         verification_key: &[u8],
         primary_signature: &[u8],
-        verifier: &impl Verifier,
-    ) -> Result<()> {
-        // Step 1: Rebuild the discharge's signature chain
-        let mut computed_signature = hmac_sha3(verification_key, &self.identifier);
+    ) -> bool {
+        let expected_signature =
+            hmac_sha3(&self.chain_signature(verification_key), primary_signature);
 
-        for caveat in &self.caveats {
-            if caveat.is_first_party() {
-                computed_signature = bind_caveat(&computed_signature, &caveat.caveat_id);
-            } else if let Some(ref vk_id) = caveat.verification_key_id {
-                computed_signature = bind_caveat(&computed_signature, vk_id);
-            }
-        }
+        // Constant-time comparison
+        signatures_equal(&expected_signature, self.signature())
+    }
+}
 
-        // Step 2: Bind with primary signature
-        let expected_signature = hmac_sha3(&computed_signature, primary_signature);
+/// What a presentation uses up once every check has passed, gathered while
+/// its caveats are checked
+#[derive(Debug, Default)]
+pub(crate) struct Usage {
+    nonces: Nonces,
+    /// The tightest quota
+    quota: Option<u64>,
+}
 
-        // Step 3: Verify the bound signature matches (constant-time comparison)
-        if !signatures_equal(&expected_signature, &self.signature) {
-            return Err(StroopwafelError::InvalidSignature);
-        }
+/// Checks the caveats `options` handle without a verifier, returning false
+/// for caveats left to the verifier
+///
+/// Epoch caveats are checked against the epoch store, if one is set. Nonce
+/// and quota caveats are satisfied here if a replay or counter store is set,
+/// and noted in `usage` to be recorded once all caveats have passed.
+fn settle_builtin(
+    context: &CaveatContext<'_>,
+    options: &VerifyOptions,
+    usage: &mut Usage,
+) -> Result<bool> {
+    if let Some(store) = options.epoch_store()
+        && let Some((subject, epoch)) = epoch_caveat(context.predicate)
+    {
+        check_epoch(store, subject, epoch)?;
+        return Ok(true);
+    }
 
-        // Step 4: Verify all first-party caveats in the discharge
-        for caveat in &self.caveats {
-            if caveat.is_first_party() {
-                verifier.verify_caveat(&caveat.caveat_id)?;
-            }
-        }
+    if options.replay_store().is_some() && usage.nonces.observe(context) {
+        return Ok(true);
+    }
 
-        Ok(())
+    if options.counter_store().is_some()
+        && let Some(limit) = quota_caveat(context.predicate)
+    {
+        usage.quota = Some(usage.quota.map_or(limit, |quota| quota.min(limit)));
+        return Ok(true);
+    }
+
+    Ok(false)
+}
+
+/// Turns a verifier's outcome for a caveat into a result, attaching the
+/// caveat's position to violations
+fn settle(
+    context: &CaveatContext<'_>,
+    outcome: CaveatOutcome,
+    options: &VerifyOptions,
+) -> Result<()> {
+    match outcome {
+        CaveatOutcome::Satisfied => Ok(()),
+        CaveatOutcome::Violated(reason)
+        | CaveatOutcome::Error(StroopwafelError::CaveatViolation(reason)) => {
+            Err(StroopwafelError::CaveatViolated {
+                index: context.index,
+                origin: context.source(),
+                predicate: describe_caveat(context.predicate),
+                reason,
+            })
+        }
+        CaveatOutcome::Error(e) => Err(e),
+        CaveatOutcome::Unrecognized => match options.unrecognized_policy() {
+            UnrecognizedPolicy::Accept => Ok(()),
+            UnrecognizedPolicy::Reject => Err(StroopwafelError::UnrecognizedCaveat {
+                index: context.index,
+                origin: context.source(),
+                predicate: describe_caveat(context.predicate),
+            }),
+        },
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::caveat::CaveatSource;
+    use crate::policy::TokenPolicy;
+    use crate::revocation::{MemoryRevocationStore, RevocationStore};
     use crate::verifier::{AcceptAllVerifier, ContextVerifier, FnVerifier, RejectAllVerifier};
 
     #[test]
@@ -556,6 +931,55 @@ mod tests {
         assert_ne!(stroopwafel.signature, original_signature);
     }
 
+    #[test]
+    fn test_try_add_first_party_caveat_valid() {
+        let root_key = b"secret";
+        let mut stroopwafel = Stroopwafel::new(root_key, b"identifier", None::<String>);
+        let mut expected = stroopwafel.clone();
+
+        stroopwafel
+            .try_add_first_party_caveat(b"account = alice")
+            .unwrap();
+        expected.add_first_party_caveat(b"account = alice");
+
+        assert_eq!(stroopwafel, expected);
+    }
+
+    #[test]
+    fn test_try_add_first_party_caveat_structured() {
+        use crate::predicate::Operator;
+
+        let root_key = b"secret";
+        let mut stroopwafel = Stroopwafel::new(root_key, b"identifier", None::<String>);
+        let caveat = StructuredCaveat::new("count", Operator::LessThan, 10);
+
+        assert!(
+            stroopwafel
+                .try_add_first_party_caveat(caveat.encode())
+                .is_ok()
+        );
+
+        let mut corrupt = caveat.encode();
+        corrupt.truncate(3);
+        assert!(stroopwafel.try_add_first_party_caveat(corrupt).is_err());
+        assert_eq!(stroopwafel.caveat_count(), 1);
+    }
+
+    #[test]
+    fn test_try_add_first_party_caveat_rejects_malformed() {
+        let root_key = b"secret";
+        let mut stroopwafel = Stroopwafel::new(root_key, b"identifier", None::<String>);
+        let original = stroopwafel.clone();
+
+        for malformed in [&b"acount alice"[..], b"= alice", b"account =", b"\xff\xfe"] {
+            let result = stroopwafel.try_add_first_party_caveat(malformed);
+            assert!(matches!(result, Err(StroopwafelError::InvalidFormat(_))));
+        }
+
+        // Signature and caveats are untouched
+        assert_eq!(stroopwafel, original);
+    }
+
     #[test]
     fn test_add_multiple_first_party_caveats() {
         let root_key = b"secret";
@@ -698,7 +1122,11 @@ mod tests {
         assert!(result.is_err());
         assert!(matches!(
             result.unwrap_err(),
-            StroopwafelError::CaveatViolation(_)
+            StroopwafelError::CaveatViolated {
+                index: 0,
+                origin: CaveatSource::Primary,
+                ..
+            }
         ));
     }
 
@@ -743,10 +1171,15 @@ mod tests {
 
         let result = stroopwafel.verify(root_key, &verifier, &[]);
         assert!(result.is_err());
-        assert!(matches!(
+        assert_eq!(
             result.unwrap_err(),
-            StroopwafelError::CaveatViolation(_)
-        ));
+            StroopwafelError::CaveatViolated {
+                index: 1,
+                origin: CaveatSource::Primary,
+                predicate: "action = write".to_string(),
+                reason: "Caveat not allowed: action = write".to_string(),
+            }
+        );
     }
 
     #[test]
@@ -870,10 +1303,44 @@ mod tests {
         let result = primary.verify(root_key, &verifier, &[]);
 
         assert!(result.is_err());
-        assert!(matches!(
+        assert_eq!(
             result.unwrap_err(),
-            StroopwafelError::CaveatViolation(_)
+            StroopwafelError::MissingDischarge {
+                caveat_id: b"auth_check".to_vec()
+            }
+        );
+    }
+
+    #[test]
+    fn test_verify_reports_errors_in_caveat_order() {
+        let root_key = b"root_secret";
+        let verification_key = b"verification_secret";
+
+        let mut primary = Stroopwafel::new(root_key, b"primary_id", None::<String>);
+        primary.add_first_party_caveat(b"account = alice");
+        primary.add_third_party_caveat(b"auth_check", verification_key, "https://auth.example.com");
+        primary.add_first_party_caveat(b"action = read");
+
+        // A violated caveat before the third-party caveat wins over its
+        // missing discharge
+        let verifier = ContextVerifier::empty()
+            .with("account", "bob")
+            .with("action", "read");
+        assert!(matches!(
+            primary.verify(root_key, &verifier, &[]),
+            Err(StroopwafelError::CaveatViolated { index: 0, .. })
         ));
+
+        // The missing discharge wins over a violated caveat after it
+        let verifier = ContextVerifier::empty()
+            .with("account", "alice")
+            .with("action", "write");
+        assert_eq!(
+            primary.verify(root_key, &verifier, &[]),
+            Err(StroopwafelError::MissingDischarge {
+                caveat_id: b"auth_check".to_vec()
+            })
+        );
     }
 
     #[test]
@@ -898,6 +1365,27 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_verify_fails_with_unbound_discharge() {
+        let root_key = b"root_secret";
+        let verification_key = b"verification_secret";
+
+        let mut primary = Stroopwafel::new(root_key, b"primary_id", None::<String>);
+        primary.add_third_party_caveat(b"auth_check", verification_key, "https://auth.example.com");
+
+        // Discharge is never bound to the primary
+        let discharge =
+            Stroopwafel::create_discharge(verification_key, b"auth_check", None::<String>);
+
+        let result = primary.verify(root_key, &AcceptAllVerifier, &[discharge]);
+        assert_eq!(
+            result.unwrap_err(),
+            StroopwafelError::InvalidDischargeSignature {
+                identifier: b"auth_check".to_vec()
+            }
+        );
+    }
+
     #[test]
     fn test_verify_with_discharge_containing_caveats() {
         let root_key = b"root_secret";
@@ -954,7 +1442,11 @@ mod tests {
         assert!(result.is_err());
         assert!(matches!(
             result.unwrap_err(),
-            StroopwafelError::CaveatViolation(_)
+            StroopwafelError::CaveatViolated {
+                index: 0,
+                origin: CaveatSource::Discharge { index: 0, .. },
+                ..
+            }
         ));
     }
 
@@ -985,4 +1477,776 @@ mod tests {
                 .is_ok()
         );
     }
+
+    #[test]
+    fn test_verify_rejects_unrecognized_caveats_by_default() {
+        let root_key = b"secret";
+        let mut stroopwafel = Stroopwafel::new(root_key, b"identifier", None::<String>);
+        stroopwafel.add_first_party_caveat(b"account = alice");
+        stroopwafel.add_first_party_caveat(b"not a predicate");
+
+        let verifier = ContextVerifier::empty().with("account", "alice");
+        assert_eq!(
+            stroopwafel.verify(root_key, &verifier, &[]),
+            Err(StroopwafelError::UnrecognizedCaveat {
+                index: 1,
+                origin: CaveatSource::Primary,
+                predicate: "not a predicate".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_verify_with_options_accepts_unrecognized_caveats() {
+        let root_key = b"secret";
+        let mut stroopwafel = Stroopwafel::new(root_key, b"identifier", None::<String>);
+        stroopwafel.add_first_party_caveat(b"account = alice");
+        stroopwafel.add_first_party_caveat(b"not a predicate");
+
+        let options = VerifyOptions::new().with_unrecognized_policy(UnrecognizedPolicy::Accept);
+        let verifier = ContextVerifier::empty().with("account", "alice");
+        assert!(
+            stroopwafel
+                .verify_with_options(root_key, &verifier, &[], &options)
+                .is_ok()
+        );
+
+        // Violations are still reported
+        let verifier = ContextVerifier::empty().with("account", "bob");
+        assert!(matches!(
+            stroopwafel.verify_with_options(root_key, &verifier, &[], &options),
+            Err(StroopwafelError::CaveatViolated { index: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_verify_with_token_policy() {
+        let root_key = b"secret";
+        let options = VerifyOptions::new()
+            .with_token_policy(TokenPolicy::new().forbid_unrestricted().require_key("aud"));
+
+        let unrestricted = Stroopwafel::new(root_key, b"identifier", None::<String>);
+        assert_eq!(
+            unrestricted.verify_with_options(root_key, &AcceptAllVerifier, &[], &options),
+            Err(StroopwafelError::UnrestrictedToken)
+        );
+        // Plain verification keeps accepting unrestricted tokens
+        assert!(
+            unrestricted
+                .verify(root_key, &AcceptAllVerifier, &[])
+                .is_ok()
+        );
+
+        let mut token = unrestricted.clone();
+        token.add_first_party_caveat(b"account = alice");
+        assert_eq!(
+            token.verify_with_options(root_key, &AcceptAllVerifier, &[], &options),
+            Err(StroopwafelError::MissingRestriction {
+                key: "aud".to_string()
+            })
+        );
+
+        token.add_first_party_caveat(b"aud = storage");
+        assert!(
+            token
+                .verify_with_options(root_key, &AcceptAllVerifier, &[], &options)
+                .is_ok()
+        );
+
+        // Signatures are checked before the policy
+        assert_eq!(
+            unrestricted.verify_with_options(b"wrong", &AcceptAllVerifier, &[], &options),
+            Err(StroopwafelError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_verify_with_revocation_store() {
+        use crate::verifier::SyncAdapter;
+        use std::sync::Arc;
+
+        let root_key = b"root_secret";
+        let verification_key = b"verification_secret";
+
+        let mut primary = Stroopwafel::new(root_key, b"primary_id", None::<String>);
+        primary.add_third_party_caveat(b"auth_check", verification_key, "https://auth.example.com");
+        let discharge =
+            Stroopwafel::create_discharge(verification_key, b"auth_check", None::<String>);
+        let discharges = [primary.bind_discharge(&discharge)];
+
+        let store = Arc::new(MemoryRevocationStore::new());
+        let options = VerifyOptions::new().with_revocation_store(store.clone());
+        assert!(
+            primary
+                .verify_with_options(root_key, &AcceptAllVerifier, &discharges, &options)
+                .is_ok()
+        );
+
+        store.revoke(b"auth_check", None).unwrap();
+        assert_eq!(
+            primary.verify_with_options(root_key, &AcceptAllVerifier, &discharges, &options),
+            Err(StroopwafelError::Revoked {
+                identifier: b"auth_check".to_vec()
+            })
+        );
+
+        store.revoke(b"primary_id", None).unwrap();
+        assert_eq!(
+            primary.verify_with_options(root_key, &AcceptAllVerifier, &discharges, &options),
+            Err(StroopwafelError::Revoked {
+                identifier: b"primary_id".to_vec()
+            })
+        );
+        assert_eq!(
+            block_on(primary.verify_async_with_options(
+                root_key,
+                &SyncAdapter::new(AcceptAllVerifier),
+                &discharges,
+                &options
+            )),
+            Err(StroopwafelError::Revoked {
+                identifier: b"primary_id".to_vec()
+            })
+        );
+
+        // Forged tokens still fail on the signature
+        assert_eq!(
+            primary.verify_with_options(b"wrong", &AcceptAllVerifier, &discharges, &options),
+            Err(StroopwafelError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_verify_with_epoch_store() {
+        use crate::epoch::{EpochStore, MemoryEpochStore};
+        use std::sync::Arc;
+
+        let root_key = b"secret";
+        let store = Arc::new(MemoryEpochStore::new());
+        store.bump("alice").unwrap();
+
+        let mut token = Stroopwafel::new(root_key, b"identifier", None::<String>);
+        token.add_restriction(&store.stamp("alice").unwrap());
+        token.add_first_party_caveat(b"account = alice");
+
+        // Without a store the verifier sees the epoch caveat
+        let verifier = ContextVerifier::empty().with("account", "alice");
+        assert!(token.verify(root_key, &verifier, &[]).is_err());
+
+        let options = VerifyOptions::new().with_epoch_store(store.clone());
+        assert!(
+            token
+                .verify_with_options(root_key, &verifier, &[], &options)
+                .is_ok()
+        );
+
+        // Log out everywhere
+        store.bump("alice").unwrap();
+        assert_eq!(
+            token.verify_with_options(root_key, &verifier, &[], &options),
+            Err(StroopwafelError::StaleEpoch {
+                subject: "alice".to_string(),
+                epoch: 1,
+                current: 2,
+            })
+        );
+
+        // Other subjects are unaffected
+        let mut other = Stroopwafel::new(root_key, b"identifier", None::<String>);
+        other.add_restriction(&store.stamp("bob").unwrap());
+        assert!(
+            other
+                .verify_with_options(root_key, &verifier, &[], &options)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_verify_with_replay_store() {
+        use crate::replay::MemoryReplayStore;
+        use std::sync::Arc;
+
+        let root_key = b"secret";
+        let mut token = Stroopwafel::new(root_key, b"webhook", None::<String>);
+        token.add_restriction(&Restriction::nonce("d41d8cd9").unwrap());
+        token.add_first_party_caveat(b"account = alice");
+
+        let options = VerifyOptions::new().with_replay_store(Arc::new(MemoryReplayStore::new()));
+
+        // A failed verification does not use up the nonce
+        let wrong = ContextVerifier::empty().with("account", "bob");
+        assert!(
+            token
+                .verify_with_options(root_key, &wrong, &[], &options)
+                .is_err()
+        );
+
+        let verifier = ContextVerifier::empty().with("account", "alice");
+        assert!(
+            token
+                .verify_with_options(root_key, &verifier, &[], &options)
+                .is_ok()
+        );
+        assert_eq!(
+            token.verify_with_options(root_key, &verifier, &[], &options),
+            Err(StroopwafelError::Replayed {
+                nonce: "d41d8cd9".to_string()
+            })
+        );
+
+        // Without a replay store the verifier sees the nonce caveat
+        assert!(token.verify(root_key, &verifier, &[]).is_err());
+    }
+
+    #[test]
+    fn test_quota_refusal_keeps_nonce() {
+        use crate::quota::MemoryCounterStore;
+        use crate::replay::MemoryReplayStore;
+        use std::sync::Arc;
+
+        let root_key = b"secret";
+        let mut token = Stroopwafel::new(root_key, b"partner", None::<String>);
+        token.add_restriction(&Restriction::max_uses(1));
+        let mut single_use = token.clone();
+        single_use.add_restriction(&Restriction::nonce("d41d8cd9").unwrap());
+
+        let replays = Arc::new(MemoryReplayStore::new());
+        let options = VerifyOptions::new()
+            .with_replay_store(replays.clone())
+            .with_counter_store(Arc::new(MemoryCounterStore::new()));
+
+        // Both share the quota of their identifier, which runs out first
+        let verifier = AcceptAllVerifier;
+        assert!(
+            token
+                .verify_with_options(root_key, &verifier, &[], &options)
+                .is_ok()
+        );
+        assert_eq!(
+            single_use.verify_with_options(root_key, &verifier, &[], &options),
+            Err(StroopwafelError::QuotaExceeded { limit: 1 })
+        );
+        assert!(replays.is_empty());
+
+        // With quota to spare the nonce is still unused
+        let options = options.with_counter_store(Arc::new(MemoryCounterStore::new()));
+        assert!(
+            single_use
+                .verify_with_options(root_key, &verifier, &[], &options)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_replay_is_not_charged() {
+        use crate::quota::{CounterStore, MemoryCounterStore};
+        use crate::replay::MemoryReplayStore;
+        use std::sync::Arc;
+
+        let root_key = b"secret";
+        let mut token = Stroopwafel::new(root_key, b"partner", None::<String>);
+        token.add_restriction(&Restriction::max_uses(2));
+        token.add_restriction(&Restriction::nonce("d41d8cd9").unwrap());
+
+        let counters = Arc::new(MemoryCounterStore::new());
+        let options = VerifyOptions::new()
+            .with_replay_store(Arc::new(MemoryReplayStore::new()))
+            .with_counter_store(counters.clone());
+
+        let verifier = AcceptAllVerifier;
+        assert!(
+            token
+                .verify_with_options(root_key, &verifier, &[], &options)
+                .is_ok()
+        );
+        for _ in 0..2 {
+            assert_eq!(
+                token.verify_with_options(root_key, &verifier, &[], &options),
+                Err(StroopwafelError::Replayed {
+                    nonce: "d41d8cd9".to_string()
+                })
+            );
+        }
+        assert_eq!(counters.count(b"partner").unwrap(), 1);
+    }
+
+    #[test]
+    fn test_holder_expiry_does_not_shorten_nonce_retention() {
+        use crate::clock::ManualClock;
+        use crate::replay::MemoryReplayStore;
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        let clock = Arc::new(ManualClock::at_unix(1_000));
+        let root_key = b"secret";
+        let mut token = Stroopwafel::new(root_key, b"reset", None::<String>);
+        token.add_first_party_caveat(b"time < 2000");
+        token.add_restriction(&Restriction::nonce("d41d8cd9").unwrap());
+
+        // The holder presents a copy that expires a second from now
+        let mut short = token.clone();
+        short.add_first_party_caveat(b"time < 1001");
+
+        let verifier = ContextVerifier::empty()
+            .with_clock(Arc::clone(&clock))
+            .with_clock_skew(Duration::from_secs(30));
+        let options = VerifyOptions::new()
+            .with_replay_store(Arc::new(
+                MemoryReplayStore::new().with_clock(Arc::clone(&clock)),
+            ))
+            .with_clock_skew(Duration::from_secs(30));
+        assert!(
+            short
+                .verify_with_options(root_key, &verifier, &[], &options)
+                .is_ok()
+        );
+
+        // The original is still refused after the short copy has expired,
+        // and while the verifier's skew lets it through past its own expiry
+        for now in [1_100, 2_010] {
+            clock.set(std::time::UNIX_EPOCH + Duration::from_secs(now));
+            assert_eq!(
+                token.verify_with_options(root_key, &verifier, &[], &options),
+                Err(StroopwafelError::Replayed {
+                    nonce: "d41d8cd9".to_string()
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_verify_with_counter_store() {
+        use crate::quota::{CounterStore, MemoryCounterStore};
+        use std::sync::Arc;
+
+        let root_key = b"secret";
+        let mut token = Stroopwafel::new(root_key, b"partner", None::<String>);
+        token.add_restriction(&Restriction::max_uses(2));
+        token.add_first_party_caveat(b"account = alice");
+        // Attenuation can only tighten the quota
+        let mut attenuated = token.clone();
+        attenuated.add_restriction(&Restriction::max_uses(10));
+
+        let store = Arc::new(MemoryCounterStore::new());
+        let options = VerifyOptions::new().with_counter_store(store.clone());
+
+        // Failed verifications are not counted
+        let wrong = ContextVerifier::empty().with("account", "bob");
+        assert!(
+            token
+                .verify_with_options(root_key, &wrong, &[], &options)
+                .is_err()
+        );
+        assert_eq!(store.count(b"partner").unwrap(), 0);
+
+        let verifier = ContextVerifier::empty().with("account", "alice");
+        assert!(
+            token
+                .verify_with_options(root_key, &verifier, &[], &options)
+                .is_ok()
+        );
+        assert!(
+            attenuated
+                .verify_with_options(root_key, &verifier, &[], &options)
+                .is_ok()
+        );
+        assert_eq!(
+            attenuated.verify_with_options(root_key, &verifier, &[], &options),
+            Err(StroopwafelError::QuotaExceeded { limit: 2 })
+        );
+        assert_eq!(store.count(b"partner").unwrap(), 2);
+    }
+
+    #[test]
+    fn test_verify_with_checkpoint_cache() {
+        use crate::checkpoint::CheckpointCache;
+        use crate::verifier::SyncAdapter;
+        use std::sync::Arc;
+
+        let root_key = b"secret";
+        let mut base = Stroopwafel::new(root_key, b"identifier", None::<String>);
+        base.add_first_party_caveat(b"account = alice");
+
+        let cache = Arc::new(CheckpointCache::new(root_key, 100));
+        assert!(cache.warm(&base));
+        let options = VerifyOptions::new().with_checkpoint_cache(cache.clone());
+
+        let mut presented = base.clone();
+        presented.add_first_party_caveat(b"action = read");
+        let verifier = ContextVerifier::empty()
+            .with("account", "alice")
+            .with("action", "read");
+        assert!(
+            presented
+                .verify_with_options(root_key, &verifier, &[], &options)
+                .is_ok()
+        );
+        assert_eq!(cache.len(), 3);
+
+        // Caveats of the cached prefix are still evaluated
+        let bob = ContextVerifier::empty()
+            .with("account", "bob")
+            .with("action", "read");
+        assert!(matches!(
+            presented.verify_with_options(root_key, &bob, &[], &options),
+            Err(StroopwafelError::CaveatViolated { index: 0, .. })
+        ));
+
+        // A forged suffix on a cached prefix is still rejected
+        let mut forged = base.clone();
+        forged.add_first_party_caveat(b"action = read");
+        forged.caveats[1].caveat_id = b"action = write".to_vec();
+        assert_eq!(
+            forged.verify_with_options(root_key, &verifier, &[], &options),
+            Err(StroopwafelError::InvalidSignature)
+        );
+        assert_eq!(
+            block_on(forged.verify_async_with_options(
+                root_key,
+                &SyncAdapter::new(AcceptAllVerifier),
+                &[],
+                &options
+            )),
+            Err(StroopwafelError::InvalidSignature)
+        );
+        assert_eq!(cache.len(), 3);
+    }
+
+    #[test]
+    fn test_verify_unrecognized_discharge_caveat() {
+        let root_key = b"root_secret";
+        let verification_key = b"verification_secret";
+
+        let mut primary = Stroopwafel::new(root_key, b"primary_id", None::<String>);
+        primary.add_third_party_caveat(b"auth_check", verification_key, "https://auth.example.com");
+
+        let mut discharge =
+            Stroopwafel::create_discharge(verification_key, b"auth_check", None::<String>);
+        discharge.add_first_party_caveat(b"declared user alice");
+        let bound = primary.bind_discharge(&discharge);
+
+        let verifier = ContextVerifier::empty();
+        assert_eq!(
+            primary.verify(root_key, &verifier, std::slice::from_ref(&bound)),
+            Err(StroopwafelError::UnrecognizedCaveat {
+                index: 0,
+                origin: CaveatSource::Discharge {
+                    index: 0,
+                    identifier: b"auth_check".to_vec(),
+                },
+                predicate: "declared user alice".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_verify_passes_caveat_context() {
+        use std::sync::Mutex;
+
+        /// Records the index, source, verification key id and location of
+        /// every caveat it checks
+        type Record = (usize, CaveatSource, Option<(Vec<u8>, Option<String>)>);
+        struct Recorder(Mutex<Vec<Record>>);
+
+        impl CaveatVerifier for Recorder {
+            fn check(&self, caveat: &CaveatContext<'_>) -> CaveatOutcome {
+                let discharge = caveat.discharge.map(|d| {
+                    (
+                        d.verification_key_id.to_vec(),
+                        d.location.map(str::to_string),
+                    )
+                });
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push((caveat.index, caveat.source(), discharge));
+                CaveatOutcome::Satisfied
+            }
+        }
+
+        let root_key = b"root_secret";
+        let verification_key = b"verification_secret";
+
+        let mut primary = Stroopwafel::new(root_key, b"primary_id", None::<String>);
+        primary.add_first_party_caveat(b"account = alice");
+        primary.add_third_party_caveat(b"auth_check", verification_key, "https://auth.example.com");
+
+        let mut discharge =
+            Stroopwafel::create_discharge(verification_key, b"auth_check", None::<String>);
+        discharge.add_first_party_caveat(b"auth_level >= 5");
+        let other = Stroopwafel::create_discharge(b"other_key", b"unused", None::<String>);
+        let bound = primary.bind_discharge(&discharge);
+
+        let recorder = Recorder(Mutex::new(Vec::new()));
+        assert!(primary.verify(root_key, &recorder, &[other, bound]).is_ok());
+        assert_eq!(
+            recorder.0.into_inner().unwrap(),
+            vec![
+                (0, CaveatSource::Primary, None),
+                (
+                    0,
+                    CaveatSource::Discharge {
+                        index: 1,
+                        identifier: b"auth_check".to_vec(),
+                    },
+                    Some((
+                        verification_key.to_vec(),
+                        Some("https://auth.example.com".to_string())
+                    ))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_verify_routes_discharge_caveats_by_verification_key() {
+        use crate::verifier::DischargeRouter;
+
+        let root_key = b"root_secret";
+        let auth_key = b"auth_secret";
+
+        let mut primary = Stroopwafel::new(root_key, b"primary_id", None::<String>);
+        primary.add_first_party_caveat(b"action = read");
+        primary.add_third_party_caveat(b"login", auth_key, "https://auth.example.com");
+
+        let mut discharge = Stroopwafel::create_discharge(auth_key, b"login", None::<String>);
+        discharge.add_first_party_caveat(b"auth_level >= 5");
+        let bound = primary.bind_discharge(&discharge);
+
+        // The API context knows nothing about auth levels
+        let api = || ContextVerifier::empty().with("action", "read");
+
+        let routed = DischargeRouter::new(api())
+            .for_verification_key(auth_key, ContextVerifier::empty().with("auth_level", "5"));
+        assert!(
+            primary
+                .verify(root_key, &routed, std::slice::from_ref(&bound))
+                .is_ok()
+        );
+
+        // A discharge for an unregistered key fails closed
+        let unrouted =
+            DischargeRouter::new(api()).for_verification_key(b"other_key", AcceptAllVerifier);
+        assert!(matches!(
+            primary.verify(root_key, &unrouted, std::slice::from_ref(&bound)),
+            Err(StroopwafelError::CaveatViolated {
+                origin: CaveatSource::Discharge { index: 0, .. },
+                ..
+            })
+        ));
+
+        // A holder can claim the auth service's location for a caveat they
+        // discharge themselves, but not its verification key
+        let mut forged = primary.clone();
+        forged.add_third_party_caveat(b"self", b"holder_key", "https://auth.example.com");
+        let mut own = Stroopwafel::create_discharge(b"holder_key", b"self", None::<String>);
+        own.add_first_party_caveat(b"auth_level >= 5");
+        let own = forged.bind_discharge(&own);
+        let bound = forged.bind_discharge(&discharge);
+        assert!(matches!(
+            forged.verify(root_key, &routed, &[bound, own]),
+            Err(StroopwafelError::CaveatViolated {
+                origin: CaveatSource::Discharge { index: 1, .. },
+                ..
+            })
+        ));
+    }
+
+    /// Polls a future to completion on the current thread
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+        loop {
+            if let std::task::Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    /// Yields to the executor once before completing
+    struct YieldOnce(bool);
+
+    impl Future for YieldOnce {
+        type Output = ();
+
+        fn poll(
+            mut self: std::pin::Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<()> {
+            if self.0 {
+                std::task::Poll::Ready(())
+            } else {
+                self.0 = true;
+                cx.waker().wake_by_ref();
+                std::task::Poll::Pending
+            }
+        }
+    }
+
+    /// An in-memory stand-in for a membership database
+    struct MembershipDirectory {
+        members: std::collections::HashSet<String>,
+        lookups: std::sync::atomic::AtomicUsize,
+    }
+
+    impl MembershipDirectory {
+        fn new(orgs: &[&str]) -> Self {
+            Self {
+                members: orgs.iter().map(|org| org.to_string()).collect(),
+                lookups: std::sync::atomic::AtomicUsize::new(0),
+            }
+        }
+
+        fn lookups(&self) -> usize {
+            self.lookups.load(std::sync::atomic::Ordering::SeqCst)
+        }
+    }
+
+    impl AsyncVerifier for MembershipDirectory {
+        async fn verify_caveat(&self, predicate: &[u8]) -> Result<()> {
+            YieldOnce(false).await;
+            self.lookups
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+
+            let predicate = std::str::from_utf8(predicate).unwrap_or_default();
+            match predicate.strip_prefix("org_member = ") {
+                Some(org) if self.members.contains(org) => Ok(()),
+                _ => Err(StroopwafelError::CaveatViolation(format!(
+                    "Not a member: {predicate}"
+                ))),
+            }
+        }
+    }
+
+    #[test]
+    fn test_verify_async_with_async_checker() {
+        let root_key = b"secret";
+        let mut stroopwafel = Stroopwafel::new(root_key, b"identifier", None::<String>);
+        stroopwafel.add_first_party_caveat(b"org_member = acme");
+
+        let directory = MembershipDirectory::new(&["acme"]);
+        assert!(block_on(stroopwafel.verify_async(root_key, &directory, &[])).is_ok());
+        assert_eq!(directory.lookups(), 1);
+
+        let directory = MembershipDirectory::new(&["globex"]);
+        let result = block_on(stroopwafel.verify_async(root_key, &directory, &[]));
+        assert!(matches!(
+            result.unwrap_err(),
+            StroopwafelError::CaveatViolated {
+                index: 0,
+                origin: CaveatSource::Primary,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_verify_async_with_discharge() {
+        let root_key = b"root_secret";
+        let verification_key = b"verification_secret";
+
+        let mut primary = Stroopwafel::new(root_key, b"primary_id", None::<String>);
+        primary.add_third_party_caveat(b"auth_check", verification_key, "https://auth.example.com");
+
+        let mut discharge =
+            Stroopwafel::create_discharge(verification_key, b"auth_check", None::<String>);
+        discharge.add_first_party_caveat(b"org_member = acme");
+        let bound = primary.bind_discharge(&discharge);
+
+        let directory = MembershipDirectory::new(&["acme"]);
+        assert!(block_on(primary.verify_async(root_key, &directory, &[bound])).is_ok());
+        assert_eq!(directory.lookups(), 1);
+    }
+
+    #[test]
+    fn test_verify_async_passes_caveat_context() {
+        /// Accepts discharge caveats only from the auth service's key
+        struct TrustAuth;
+
+        impl AsyncVerifier for TrustAuth {
+            async fn verify_caveat(&self, _predicate: &[u8]) -> Result<()> {
+                Ok(())
+            }
+
+            fn check(
+                &self,
+                caveat: &CaveatContext<'_>,
+            ) -> impl Future<Output = CaveatOutcome> + Send {
+                let outcome = match caveat.discharge {
+                    Some(d) if d.verification_key_id != b"verification_secret" => {
+                        CaveatOutcome::Violated("Untrusted discharge".to_string())
+                    }
+                    _ => CaveatOutcome::Satisfied,
+                };
+                std::future::ready(outcome)
+            }
+        }
+
+        let root_key = b"root_secret";
+        let mut primary = Stroopwafel::new(root_key, b"primary_id", None::<String>);
+        primary.add_third_party_caveat(b"auth_check", b"verification_secret", "https://auth");
+        primary.add_third_party_caveat(b"other_check", b"other_secret", "https://auth");
+
+        let discharge = |key: &[u8], identifier: &[u8]| {
+            let mut discharge = Stroopwafel::create_discharge(key, identifier, None::<String>);
+            discharge.add_first_party_caveat(b"declared user alice");
+            primary.bind_discharge(&discharge)
+        };
+        let discharges = [
+            discharge(b"verification_secret", b"auth_check"),
+            discharge(b"other_secret", b"other_check"),
+        ];
+
+        let result = block_on(primary.verify_async(root_key, &TrustAuth, &discharges));
+        assert!(matches!(
+            result,
+            Err(StroopwafelError::CaveatViolated {
+                origin: CaveatSource::Discharge { index: 1, .. },
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_verify_async_checks_signature_before_lookups() {
+        let mut stroopwafel = Stroopwafel::new(b"secret", b"identifier", None::<String>);
+        stroopwafel.add_first_party_caveat(b"org_member = acme");
+
+        let directory = MembershipDirectory::new(&["acme"]);
+        let result = block_on(stroopwafel.verify_async(b"wrong_key", &directory, &[]));
+        assert_eq!(result, Err(StroopwafelError::InvalidSignature));
+        assert_eq!(directory.lookups(), 0);
+    }
+
+    #[test]
+    fn test_verify_async_matches_sync_verify() {
+        use crate::verifier::SyncAdapter;
+
+        let root_key = b"secret";
+        let mut stroopwafel = Stroopwafel::new(root_key, b"identifier", None::<String>);
+        stroopwafel.add_first_party_caveat(b"account = alice");
+        stroopwafel.add_first_party_caveat(b"action = read");
+
+        for action in ["read", "write"] {
+            let verifier = ContextVerifier::empty()
+                .with("account", "alice")
+                .with("action", action);
+            let sync_result = stroopwafel.verify(root_key, &verifier, &[]);
+
+            let adapter = SyncAdapter::new(verifier);
+            let async_result = block_on(stroopwafel.verify_async(root_key, &adapter, &[]));
+            assert_eq!(sync_result, async_result);
+        }
+    }
+
+    #[test]
+    fn test_verify_async_future_is_send() {
+        fn assert_send<T: Send>(_: &T) {}
+
+        let stroopwafel = Stroopwafel::new(b"secret", b"identifier", None::<String>);
+        let directory = MembershipDirectory::new(&[]);
+        let future = stroopwafel.verify_async(b"secret", &directory, &[]);
+        assert_send(&future);
+        assert!(block_on(future).is_ok());
+    }
 }
@@ -0,0 +1,445 @@
+use crate::predicate::{MissingKeyPolicy, Operator};
+use crate::timestamp::Timestamp;
+use crate::{Result, StroopwafelError};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Leading byte that marks a caveat identifier as a structured caveat.
+///
+/// `0xC1` can never start a valid UTF-8 string (and is unused by MessagePack
+/// itself), so structured caveats cannot be confused with string predicates
+/// living in the same token.
+pub const STRUCTURED_CAVEAT_TAG: u8 = 0xC1;
+
+/// A typed value carried by a structured caveat
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Value {
+    /// A boolean value
+    Boolean(bool),
+    /// A signed integer value
+    Integer(i64),
+    /// A UTF-8 string value
+    String(String),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Boolean(b) => write!(f, "{b}"),
+            Value::Integer(i) => write!(f, "{i}"),
+            Value::String(s) => f.write_str(s),
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Boolean(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Integer(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+/// A first-party caveat encoded as a binary `(key, operator, value)` tuple
+///
+/// Structured caveats are an alternative to string predicates such as
+/// `"account = alice"`. They are encoded as [`STRUCTURED_CAVEAT_TAG`] followed
+/// by a MessagePack array, and are evaluated directly from their typed fields
+/// without going through the text predicate grammar.
+///
+/// The encoded bytes are used as the caveat identifier, so the signature chain
+/// binds exactly what was minted. Structured and string caveats can be freely
+/// mixed in the same stroopwafel.
+///
+/// # Example
+/// ```
+/// use stroopwafel::Stroopwafel;
+/// use stroopwafel::predicate::Operator;
+/// use stroopwafel::structured::StructuredCaveat;
+/// use stroopwafel::verifier::ContextVerifier;
+///
+/// let root_key = b"secret";
+/// let mut token = Stroopwafel::new(root_key, b"identifier", None::<String>);
+/// token.add_first_party_caveat(b"account = alice");
+/// token.add_structured_caveat(&StructuredCaveat::new("level", Operator::GreaterThanOrEqual, 5));
+///
+/// let verifier = ContextVerifier::empty()
+///     .with("account", "alice")
+///     .with("level", "7");
+/// assert!(token.verify(root_key, &verifier, &[]).is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructuredCaveat {
+    /// The context key this caveat restricts
+    pub key: String,
+    /// The comparison operator
+    pub operator: Operator,
+    /// The typed value to compare against
+    pub value: Value,
+}
+
+impl StructuredCaveat {
+    /// Creates a new structured caveat
+    pub fn new(key: impl Into<String>, operator: Operator, value: impl Into<Value>) -> Self {
+        Self {
+            key: key.into(),
+            operator,
+            value: value.into(),
+        }
+    }
+
+    /// Returns true if the caveat bytes carry the structured caveat tag
+    pub fn is_structured(caveat_id: &[u8]) -> bool {
+        caveat_id.first() == Some(&STRUCTURED_CAVEAT_TAG)
+    }
+
+    /// Encodes this caveat into its canonical binary form
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![STRUCTURED_CAVEAT_TAG];
+        rmp_serde::encode::write(
+            &mut bytes,
+            &(&self.key, operator_code(self.operator), &self.value),
+        )
+        .expect("Encoding a structured caveat into a Vec cannot fail");
+        bytes
+    }
+
+    /// Decodes a structured caveat from its binary form
+    ///
+    /// # Errors
+    /// Returns an error if the tag is missing, the payload is not a valid
+    /// `(key, operator, value)` tuple, or there are trailing bytes.
+    pub fn decode(caveat_id: &[u8]) -> Result<Self> {
+        let mut payload = match caveat_id.split_first() {
+            Some((&STRUCTURED_CAVEAT_TAG, payload)) => payload,
+            _ => {
+                return Err(StroopwafelError::InvalidFormat(
+                    "Missing structured caveat tag".to_string(),
+                ));
+            }
+        };
+
+        let (key, code, value): (String, u8, Value) =
+            Deserialize::deserialize(&mut rmp_serde::Deserializer::new(&mut payload))
+                .map_err(|e| StroopwafelError::decode("structured caveat", e))?;
+
+        if !payload.is_empty() {
+            return Err(StroopwafelError::InvalidFormat(
+                "Trailing bytes after structured caveat".to_string(),
+            ));
+        }
+
+        let operator = operator_from_code(code).ok_or_else(|| {
+            StroopwafelError::InvalidFormat(format!("Unknown operator code: {code}"))
+        })?;
+
+        Ok(Self {
+            key,
+            operator,
+            value,
+        })
+    }
+
+    /// Evaluate this caveat against a context
+    ///
+    /// The context value for the key is interpreted according to the type of
+    /// the caveat's value. A missing key or a context value that does not
+    /// parse as that type fails the caveat. Integer values also match context
+    /// times in RFC 3339 form, as Unix seconds, and string values that are
+    /// both RFC 3339 times compare as instants. The set, prefix and subnet
+    /// operators only apply to string values.
+    pub fn evaluate(&self, context: &HashMap<String, String>) -> bool {
+        match context.get(&self.key) {
+            Some(actual) => self.evaluate_value(actual),
+            None => self
+                .operator
+                .evaluate_missing(&self.key, MissingKeyPolicy::Fail)
+                .unwrap_or(false),
+        }
+    }
+
+    /// Evaluate this caveat against the context value of its key
+    ///
+    /// The encoded value is ignored by the presence operators `exists` and
+    /// `!exists`.
+    pub fn evaluate_value(&self, actual: &str) -> bool {
+        if self.operator.is_unary() {
+            return self.operator.evaluate(actual, "");
+        }
+
+        match &self.value {
+            Value::Boolean(expected) => actual
+                .parse::<bool>()
+                .is_ok_and(|actual| self.operator.compare(&actual, expected)),
+            Value::Integer(expected) => match actual.parse::<i64>() {
+                Ok(actual) => self.operator.compare(&actual, expected),
+                // Time values in other formats compare against Unix seconds
+                Err(_) => Timestamp::parse(actual).is_some_and(|actual| {
+                    self.operator
+                        .compare(&actual, &Timestamp::from_unix_seconds(*expected))
+                }),
+            },
+            Value::String(expected) => {
+                // RFC 3339 times compare as instants; other strings lexicographically
+                match (
+                    Timestamp::parse_rfc3339(actual),
+                    Timestamp::parse_rfc3339(expected),
+                ) {
+                    (Some(actual), Some(expected)) if self.operator.is_comparison() => {
+                        self.operator.compare(&actual, &expected)
+                    }
+                    _ => self.operator.evaluate(actual, expected),
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for StructuredCaveat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.operator.is_unary() {
+            return write!(f, "{} {}", self.key, self.operator);
+        }
+        write!(f, "{} {} {}", self.key, self.operator, self.value)
+    }
+}
+
+/// Wire code for an operator inside a structured caveat
+fn operator_code(operator: Operator) -> u8 {
+    match operator {
+        Operator::Equal => 0,
+        Operator::NotEqual => 1,
+        Operator::LessThan => 2,
+        Operator::GreaterThan => 3,
+        Operator::LessThanOrEqual => 4,
+        Operator::GreaterThanOrEqual => 5,
+        Operator::In => 6,
+        Operator::StartsWith => 7,
+        Operator::InSubnet => 8,
+        Operator::Exists => 9,
+        Operator::NotExists => 10,
+    }
+}
+
+/// Inverse of [`operator_code`]
+fn operator_from_code(code: u8) -> Option<Operator> {
+    match code {
+        0 => Some(Operator::Equal),
+        1 => Some(Operator::NotEqual),
+        2 => Some(Operator::LessThan),
+        3 => Some(Operator::GreaterThan),
+        4 => Some(Operator::LessThanOrEqual),
+        5 => Some(Operator::GreaterThanOrEqual),
+        6 => Some(Operator::In),
+        7 => Some(Operator::StartsWith),
+        8 => Some(Operator::InSubnet),
+        9 => Some(Operator::Exists),
+        10 => Some(Operator::NotExists),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Stroopwafel;
+    use crate::verifier::{ContextVerifier, Verifier};
+
+    #[test]
+    fn test_encode_decode_roundtrip() {
+        let caveats = [
+            StructuredCaveat::new("account", Operator::Equal, "alice"),
+            StructuredCaveat::new("count", Operator::LessThan, 100),
+            StructuredCaveat::new("admin", Operator::NotEqual, true),
+            StructuredCaveat::new("balance", Operator::GreaterThanOrEqual, -42),
+        ];
+
+        for caveat in caveats {
+            let bytes = caveat.encode();
+            assert!(StructuredCaveat::is_structured(&bytes));
+            assert_eq!(StructuredCaveat::decode(&bytes).unwrap(), caveat);
+        }
+    }
+
+    #[test]
+    fn test_encoding_is_not_utf8() {
+        let bytes = StructuredCaveat::new("account", Operator::Equal, "alice").encode();
+        assert!(std::str::from_utf8(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_string_predicate_is_not_structured() {
+        assert!(!StructuredCaveat::is_structured(b"account = alice"));
+        assert!(!StructuredCaveat::is_structured(b""));
+    }
+
+    #[test]
+    fn test_decode_rejects_trailing_bytes() {
+        let mut bytes = StructuredCaveat::new("account", Operator::Equal, "alice").encode();
+        bytes.push(0x00);
+        assert!(StructuredCaveat::decode(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_decode_rejects_unknown_operator() {
+        let mut bytes = vec![STRUCTURED_CAVEAT_TAG];
+        rmp_serde::encode::write(&mut bytes, &("account", 42u8, "alice")).unwrap();
+        assert!(StructuredCaveat::decode(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_decode_rejects_garbage() {
+        assert!(StructuredCaveat::decode(b"account = alice").is_err());
+        assert!(StructuredCaveat::decode(&[STRUCTURED_CAVEAT_TAG, 0xff]).is_err());
+        assert!(StructuredCaveat::decode(&[STRUCTURED_CAVEAT_TAG]).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_integer() {
+        let caveat = StructuredCaveat::new("count", Operator::LessThan, 100);
+        let mut context = HashMap::new();
+
+        context.insert("count".to_string(), "50".to_string());
+        assert!(caveat.evaluate(&context));
+
+        context.insert("count".to_string(), "150".to_string());
+        assert!(!caveat.evaluate(&context));
+
+        // Not an integer
+        context.insert("count".to_string(), "fifty".to_string());
+        assert!(!caveat.evaluate(&context));
+    }
+
+    #[test]
+    fn test_evaluate_boolean() {
+        let caveat = StructuredCaveat::new("admin", Operator::Equal, true);
+        let mut context = HashMap::new();
+
+        context.insert("admin".to_string(), "true".to_string());
+        assert!(caveat.evaluate(&context));
+
+        context.insert("admin".to_string(), "false".to_string());
+        assert!(!caveat.evaluate(&context));
+    }
+
+    #[test]
+    fn test_evaluate_string_is_not_numeric() {
+        // A string-typed value compares lexicographically even if it looks numeric
+        let caveat = StructuredCaveat::new("version", Operator::LessThan, "10");
+        let mut context = HashMap::new();
+        context.insert("version".to_string(), "9".to_string());
+
+        assert!(!caveat.evaluate(&context));
+    }
+
+    #[test]
+    fn test_evaluate_times() {
+        let caveat = StructuredCaveat::new("time", Operator::LessThan, 1_735_689_600i64);
+        let mut context = HashMap::new();
+
+        context.insert("time".to_string(), "2024-12-31T23:59:59.5Z".to_string());
+        assert!(caveat.evaluate(&context));
+        context.insert("time".to_string(), "1735689599.5".to_string());
+        assert!(caveat.evaluate(&context));
+        context.insert("time".to_string(), "2025-01-01T01:00:00+01:00".to_string());
+        assert!(!caveat.evaluate(&context));
+
+        let caveat = StructuredCaveat::new("time", Operator::LessThan, "2025-01-01T00:00:00Z");
+        context.insert("time".to_string(), "2024-12-31T19:00:00-05:00".to_string());
+        assert!(!caveat.evaluate(&context));
+        context.insert("time".to_string(), "2024-12-31T18:59:59-05:00".to_string());
+        assert!(caveat.evaluate(&context));
+    }
+
+    #[test]
+    fn test_evaluate_missing_key() {
+        let caveat = StructuredCaveat::new("account", Operator::Equal, "alice");
+        assert!(!caveat.evaluate(&HashMap::new()));
+    }
+
+    #[test]
+    fn test_context_verifier_structured() {
+        let verifier = ContextVerifier::empty().with("count", "50");
+
+        let ok = StructuredCaveat::new("count", Operator::LessThan, 100).encode();
+        let bad = StructuredCaveat::new("count", Operator::GreaterThan, 100).encode();
+
+        assert!(verifier.verify_caveat(&ok).is_ok());
+        assert!(verifier.verify_caveat(&bad).is_err());
+    }
+
+    #[test]
+    fn test_mixed_caveats_in_token() {
+        let root_key = b"secret";
+        let mut token = Stroopwafel::new(root_key, b"identifier", None::<String>);
+        token.add_first_party_caveat(b"account = alice");
+        token.add_structured_caveat(&StructuredCaveat::new("count", Operator::LessThan, 100));
+        token.add_first_party_caveat(b"action = read");
+
+        assert!(token.caveats[1].is_structured());
+        assert!(!token.caveats[0].is_structured());
+
+        let verifier = ContextVerifier::empty()
+            .with("account", "alice")
+            .with("action", "read")
+            .with("count", "50");
+        assert!(token.verify(root_key, &verifier, &[]).is_ok());
+
+        let verifier = ContextVerifier::empty()
+            .with("account", "alice")
+            .with("action", "read")
+            .with("count", "500");
+        assert!(token.verify(root_key, &verifier, &[]).is_err());
+    }
+
+    #[test]
+    fn test_presence_operators() {
+        let exists = StructuredCaveat::new("session", Operator::Exists, false);
+        let decoded = StructuredCaveat::decode(&exists.encode()).unwrap();
+        assert_eq!(decoded, exists);
+        assert_eq!(decoded.to_string(), "session exists");
+
+        let mut context = HashMap::new();
+        assert!(!exists.evaluate(&context));
+        assert!(StructuredCaveat::new("session", Operator::NotExists, false).evaluate(&context));
+
+        context.insert("session".to_string(), "abc".to_string());
+        assert!(exists.evaluate(&context));
+        assert!(!StructuredCaveat::new("session", Operator::NotExists, false).evaluate(&context));
+    }
+
+    #[test]
+    fn test_signature_binds_encoded_bytes() {
+        let root_key = b"secret";
+        let mut token = Stroopwafel::new(root_key, b"identifier", None::<String>);
+        token.add_structured_caveat(&StructuredCaveat::new("count", Operator::LessThan, 100));
+
+        // Swapping the caveat for a looser one must break the signature
+        token.caveats[0].caveat_id =
+            StructuredCaveat::new("count", Operator::LessThan, 1000).encode();
+
+        let verifier = ContextVerifier::empty().with("count", "50");
+        assert!(matches!(
+            token.verify(root_key, &verifier, &[]),
+            Err(StroopwafelError::InvalidSignature)
+        ));
+    }
+}
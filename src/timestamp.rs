@@ -0,0 +1,356 @@
+use crate::{Result, StroopwafelError};
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const NANOS_PER_SECOND: i128 = 1_000_000_000;
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// An instant in time with nanosecond precision, used to compare time values
+///
+/// Time-valued predicates may be written in either of two formats, which are
+/// normalized to the same instant before comparing:
+///
+/// - Unix seconds, optionally with up to nine fractional digits
+///   (`1735689599`, `1735689599.5`)
+/// - RFC 3339 date-times with any UTC offset (`2024-12-31T23:59:59Z`,
+///   `2025-01-01T00:59:59.5+01:00`), or a bare date meaning midnight UTC
+///   (`2024-12-31`)
+///
+/// # Example
+/// ```
+/// use stroopwafel::timestamp::Timestamp;
+///
+/// let unix = Timestamp::parse("1735689599").unwrap();
+/// let utc = Timestamp::parse("2024-12-31T23:59:59Z").unwrap();
+/// let cet = Timestamp::parse("2025-01-01T00:59:59+01:00").unwrap();
+///
+/// assert_eq!(unix, utc);
+/// assert_eq!(utc, cet);
+/// assert!(Timestamp::parse("2024-12-31T23:59:59.5Z").unwrap() > unix);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp {
+    /// Nanoseconds since the Unix epoch
+    nanos: i128,
+}
+
+impl Timestamp {
+    /// Creates a timestamp from nanoseconds since the Unix epoch
+    pub fn from_unix_nanos(nanos: i128) -> Self {
+        Self { nanos }
+    }
+
+    /// Creates a timestamp from whole seconds since the Unix epoch
+    pub fn from_unix_seconds(seconds: i64) -> Self {
+        Self::from_unix_nanos(i128::from(seconds) * NANOS_PER_SECOND)
+    }
+
+    /// Converts a system time to a timestamp
+    ///
+    /// # Errors
+    /// Returns `StroopwafelError::InvalidTime` if `time` is before the Unix epoch.
+    pub fn from_system_time(time: SystemTime) -> Result<Self> {
+        let elapsed = time.duration_since(UNIX_EPOCH).map_err(|_| {
+            StroopwafelError::InvalidTime("time is before the Unix epoch".to_string())
+        })?;
+
+        // A u128 count of nanoseconds since 1970 does not overflow i128 for
+        // any representable SystemTime
+        Ok(Self::from_unix_nanos(elapsed.as_nanos() as i128))
+    }
+
+    /// Returns the nanoseconds since the Unix epoch
+    pub fn unix_nanos(&self) -> i128 {
+        self.nanos
+    }
+
+    /// Converts the timestamp to a system time
+    ///
+    /// Returns `None` for instants before the Unix epoch or beyond what
+    /// `SystemTime` can represent.
+    pub fn to_system_time(&self) -> Option<SystemTime> {
+        let seconds = u64::try_from(self.nanos.div_euclid(NANOS_PER_SECOND)).ok()?;
+        let nanos = self.nanos.rem_euclid(NANOS_PER_SECOND) as u32;
+        UNIX_EPOCH.checked_add(Duration::new(seconds, nanos))
+    }
+
+    /// Returns the timestamp moved by `nanos` nanoseconds, saturating at the
+    /// bounds of the representable range
+    pub fn saturating_add_nanos(&self, nanos: i128) -> Self {
+        Self::from_unix_nanos(self.nanos.saturating_add(nanos))
+    }
+
+    /// Parses Unix seconds or an RFC 3339 date-time
+    ///
+    /// Returns `None` if `value` is in neither format.
+    pub fn parse(value: &str) -> Option<Self> {
+        parse_unix(value).or_else(|| parse_rfc3339(value))
+    }
+
+    /// Parses an RFC 3339 date-time, or a bare date meaning midnight UTC
+    ///
+    /// Returns `None` for any other value, including Unix seconds.
+    pub fn parse_rfc3339(value: &str) -> Option<Self> {
+        parse_rfc3339(value)
+    }
+}
+
+impl fmt::Display for Timestamp {
+    /// Formats the timestamp as Unix seconds, with a fraction only when needed
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.nanos < 0 { "-" } else { "" };
+        let magnitude = self.nanos.unsigned_abs();
+        let seconds = magnitude / NANOS_PER_SECOND as u128;
+        let fraction = magnitude % NANOS_PER_SECOND as u128;
+
+        if fraction == 0 {
+            write!(f, "{sign}{seconds}")
+        } else {
+            let digits = format!("{fraction:09}");
+            write!(f, "{sign}{seconds}.{}", digits.trim_end_matches('0'))
+        }
+    }
+}
+
+/// Parses `[-]seconds[.fraction]` with at most nine fractional digits
+fn parse_unix(value: &str) -> Option<Timestamp> {
+    let (negative, unsigned) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value),
+    };
+    let (whole, fraction) = match unsigned.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (unsigned, None),
+    };
+
+    // Keep the count of digits small enough that the result fits in i128
+    if whole.is_empty() || whole.len() > 20 || !is_digits(whole) {
+        return None;
+    }
+    let mut nanos = whole.parse::<i128>().ok()? * NANOS_PER_SECOND;
+
+    if let Some(fraction) = fraction {
+        if fraction.is_empty() || fraction.len() > 9 || !is_digits(fraction) {
+            return None;
+        }
+        nanos += fraction_nanos(fraction);
+    }
+
+    Some(Timestamp::from_unix_nanos(if negative {
+        -nanos
+    } else {
+        nanos
+    }))
+}
+
+/// Parses an RFC 3339 date-time, or a full date meaning midnight UTC
+fn parse_rfc3339(value: &str) -> Option<Timestamp> {
+    let bytes = value.as_bytes();
+    if bytes.len() < 10 || bytes[4] != b'-' || bytes[7] != b'-' {
+        return None;
+    }
+
+    let year = digits(&bytes[0..4])?;
+    let month = digits(&bytes[5..7])?;
+    let day = digits(&bytes[8..10])?;
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+
+    if bytes.len() == 10 {
+        return Some(Timestamp::from_unix_seconds(days * SECONDS_PER_DAY));
+    }
+
+    // Time part: [Tt ]HH:MM:SS
+    if bytes.len() < 20 || !matches!(bytes[10], b'T' | b't' | b' ') {
+        return None;
+    }
+    if bytes[13] != b':' || bytes[16] != b':' {
+        return None;
+    }
+    let hour = digits(&bytes[11..13])?;
+    let minute = digits(&bytes[14..16])?;
+    // 60 allows for leap seconds
+    let second = digits(&bytes[17..19])?;
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    // Optional fraction of a second; digits beyond nanoseconds are dropped
+    let mut rest = &value[19..];
+    let mut fraction = 0;
+    if let Some(after_dot) = rest.strip_prefix('.') {
+        let len = after_dot.bytes().take_while(u8::is_ascii_digit).count();
+        if len == 0 {
+            return None;
+        }
+        fraction = fraction_nanos(&after_dot[..len.min(9)]);
+        rest = &after_dot[len..];
+    }
+
+    let offset_seconds = match rest.as_bytes() {
+        [b'Z' | b'z'] => 0,
+        [sign @ (b'+' | b'-'), h1, h2, b':', m1, m2] => {
+            let hours = digits(&[*h1, *h2])?;
+            let minutes = digits(&[*m1, *m2])?;
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+            let offset = hours * 3600 + minutes * 60;
+            if *sign == b'-' { -offset } else { offset }
+        }
+        _ => return None,
+    };
+
+    let seconds = days * SECONDS_PER_DAY + hour * 3600 + minute * 60 + second - offset_seconds;
+    Some(Timestamp::from_unix_nanos(
+        i128::from(seconds) * NANOS_PER_SECOND + fraction,
+    ))
+}
+
+fn is_digits(value: &str) -> bool {
+    value.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Parses a short run of ASCII digits
+fn digits(bytes: &[u8]) -> Option<i64> {
+    bytes.iter().try_fold(0i64, |acc, b| {
+        b.is_ascii_digit().then(|| acc * 10 + i64::from(b - b'0'))
+    })
+}
+
+/// Converts up to nine fractional digits to nanoseconds
+fn fraction_nanos(fraction: &str) -> i128 {
+    let padded = format!("{fraction:0<9}");
+    padded.parse().unwrap_or(0)
+}
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn ts(value: &str) -> Timestamp {
+        Timestamp::parse(value).unwrap_or_else(|| panic!("failed to parse {value}"))
+    }
+
+    #[test]
+    fn test_parse_unix_seconds() {
+        assert_eq!(ts("0").unix_nanos(), 0);
+        assert_eq!(
+            ts("1700000000"),
+            Timestamp::from_unix_seconds(1_700_000_000)
+        );
+        assert_eq!(ts("1.5").unix_nanos(), 1_500_000_000);
+        assert_eq!(ts("1.000000001").unix_nanos(), 1_000_000_001);
+        assert_eq!(ts("-1.5").unix_nanos(), -1_500_000_000);
+    }
+
+    #[test]
+    fn test_parse_rfc3339() {
+        assert_eq!(ts("1970-01-01T00:00:00Z").unix_nanos(), 0);
+        assert_eq!(ts("2024-12-31T23:59:59Z"), ts("1735689599"));
+        assert_eq!(ts("2024-12-31t23:59:59z"), ts("1735689599"));
+        assert_eq!(ts("2024-12-31 23:59:59Z"), ts("1735689599"));
+        assert_eq!(ts("2024-02-29T00:00:00Z"), ts("1709164800"));
+        assert_eq!(ts("1969-12-31T23:59:59Z"), ts("-1"));
+    }
+
+    #[test]
+    fn test_parse_rfc3339_offsets() {
+        let utc = ts("2025-01-01T00:00:00Z");
+        assert_eq!(ts("2025-01-01T01:00:00+01:00"), utc);
+        assert_eq!(ts("2024-12-31T18:30:00-05:30"), utc);
+        assert_eq!(ts("2025-01-01T00:00:00+00:00"), utc);
+        assert_eq!(ts("2025-01-01T00:00:00-00:00"), utc);
+    }
+
+    #[test]
+    fn test_parse_rfc3339_fractions() {
+        assert_eq!(ts("1970-01-01T00:00:00.5Z").unix_nanos(), 500_000_000);
+        assert_eq!(
+            ts("1970-01-01T00:00:00.123456789Z").unix_nanos(),
+            123_456_789
+        );
+        // Digits beyond nanoseconds are dropped
+        assert_eq!(
+            ts("1970-01-01T00:00:00.1234567891Z").unix_nanos(),
+            123_456_789
+        );
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(ts("2025-01-01"), ts("2025-01-01T00:00:00Z"));
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid() {
+        for value in [
+            "",
+            "abc",
+            "1e9",
+            "+5",
+            "1.",
+            ".5",
+            "1.1234567891",
+            "2025-13-01",
+            "2025-02-30",
+            "2023-02-29",
+            "2025-01-01T24:00:00Z",
+            "2025-01-01T00:00:00",
+            "2025-01-01T00:00:00.Z",
+            "2025-01-01T00:00:00+0100",
+            "2025-01-01T00:00:00+24:00",
+            "2025-01-01X00:00:00Z",
+        ] {
+            assert!(
+                Timestamp::parse(value).is_none(),
+                "{value} should not parse"
+            );
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(ts("1700000000").to_string(), "1700000000");
+        assert_eq!(ts("1700000000.250").to_string(), "1700000000.25");
+        assert_eq!(ts("-1.5").to_string(), "-1.5");
+        assert_eq!(ts(&ts("1.000000001").to_string()), ts("1.000000001"));
+    }
+
+    #[test]
+    fn test_from_system_time() {
+        let time = UNIX_EPOCH + Duration::new(1_700_000_000, 5);
+        assert_eq!(
+            Timestamp::from_system_time(time).unwrap().unix_nanos(),
+            1_700_000_000_000_000_005
+        );
+        assert!(Timestamp::from_system_time(UNIX_EPOCH - Duration::from_secs(1)).is_err());
+    }
+}
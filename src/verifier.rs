@@ -1,7 +1,13 @@
-use crate::predicate::Predicate;
+use crate::caveat::{CaveatContext, ParsedCaveat};
+use crate::clock::{Clock, SystemClock};
+use crate::predicate::{MissingKeyPolicy, Operator};
+use crate::restriction::TIME_KEY;
+use crate::timestamp::Timestamp;
 use crate::{Result, StroopwafelError};
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::Mutex;
+use std::time::Duration;
 
 /// A verifier checks whether caveat predicates are satisfied
 ///
@@ -16,6 +22,412 @@ pub trait Verifier {
     /// * `Ok(())` if the caveat is satisfied
     /// * `Err(StroopwafelError::CaveatViolation)` if the caveat is violated
     fn verify_caveat(&self, predicate: &[u8]) -> Result<()>;
+
+    /// Checks a single caveat predicate, distinguishing a violated caveat from
+    /// one this verifier does not apply to
+    ///
+    /// The default implementation maps the result of
+    /// [`verify_caveat`](Self::verify_caveat) with
+    /// [`CaveatOutcome::from_result`]. Verifiers that only understand some caveats should
+    /// override it and return [`CaveatOutcome::Unrecognized`] for the rest,
+    /// which lets combinators such as [`FirstMatch`] move on to the next
+    /// verifier.
+    fn check_caveat(&self, predicate: &[u8]) -> CaveatOutcome {
+        CaveatOutcome::from_result(self.verify_caveat(predicate))
+    }
+}
+
+/// A verifier that sees where each caveat sits, not just its predicate
+///
+/// [`Stroopwafel::verify`](crate::Stroopwafel::verify) calls
+/// [`check`](Self::check) with a [`CaveatContext`] telling whether the caveat
+/// belongs to the primary stroopwafel or to a discharge, its position, and
+/// for discharge caveats the discharge identifier and the verification key id
+/// of the third-party caveat it satisfies. This allows different rules for
+/// discharge caveats, e.g. trusting `declared` caveats only from discharges
+/// signed with the identity service's key.
+///
+/// Base such decisions on the verification key id or the discharge
+/// identifier, which are authenticated, and never on the location, which a
+/// token holder can rewrite (see [`DischargeContext`](crate::caveat::DischargeContext)).
+///
+/// Every [`Verifier`] is a `CaveatVerifier` that ignores the context.
+///
+/// # Example
+/// ```
+/// use stroopwafel::Stroopwafel;
+/// use stroopwafel::caveat::CaveatContext;
+/// use stroopwafel::verifier::{CaveatOutcome, CaveatVerifier};
+///
+/// const IDENTITY_KEY_ID: &[u8] = b"identity-service-key";
+///
+/// struct TrustIdentityService;
+///
+/// impl CaveatVerifier for TrustIdentityService {
+///     fn check(&self, caveat: &CaveatContext<'_>) -> CaveatOutcome {
+///         let from_identity = caveat
+///             .discharge
+///             .is_some_and(|d| d.verification_key_id == IDENTITY_KEY_ID);
+///
+///         match (caveat.predicate.starts_with(b"declared "), from_identity) {
+///             (true, true) => CaveatOutcome::Satisfied,
+///             (true, false) => CaveatOutcome::Violated("Untrusted declaration".to_string()),
+///             (false, _) => CaveatOutcome::Unrecognized,
+///         }
+///     }
+/// }
+///
+/// let root_key = b"secret";
+/// let mut token = Stroopwafel::new(root_key, b"identifier", None::<String>);
+/// token.add_first_party_caveat(b"declared user alice");
+///
+/// // Declarations in the primary are not trusted
+/// assert!(token.verify(root_key, &TrustIdentityService, &[]).is_err());
+/// ```
+pub trait CaveatVerifier {
+    /// Checks a single caveat, given where it sits
+    fn check(&self, caveat: &CaveatContext<'_>) -> CaveatOutcome;
+}
+
+impl<V: Verifier + ?Sized> CaveatVerifier for V {
+    fn check(&self, caveat: &CaveatContext<'_>) -> CaveatOutcome {
+        self.check_caveat(caveat.predicate)
+    }
+}
+
+/// A function-based [`CaveatVerifier`]
+///
+/// # Example
+/// ```
+/// use stroopwafel::caveat::CaveatContext;
+/// use stroopwafel::verifier::{CaveatOutcome, CaveatVerifier, FnCaveatVerifier};
+///
+/// // Only accept caveats of the primary stroopwafel
+/// let verifier = FnCaveatVerifier::new(|caveat| {
+///     if caveat.is_discharge() {
+///         CaveatOutcome::Violated("Discharge caveats are not allowed".to_string())
+///     } else {
+///         CaveatOutcome::Satisfied
+///     }
+/// });
+///
+/// assert!(verifier.check(&CaveatContext::primary(b"account = alice", 0)).is_satisfied());
+/// ```
+pub struct FnCaveatVerifier<F>
+where
+    F: Fn(&CaveatContext<'_>) -> CaveatOutcome,
+{
+    func: F,
+}
+
+impl<F> FnCaveatVerifier<F>
+where
+    F: Fn(&CaveatContext<'_>) -> CaveatOutcome,
+{
+    /// Creates a new function-based caveat verifier
+    pub fn new(func: F) -> Self {
+        Self { func }
+    }
+}
+
+impl<F> CaveatVerifier for FnCaveatVerifier<F>
+where
+    F: Fn(&CaveatContext<'_>) -> CaveatOutcome,
+{
+    fn check(&self, caveat: &CaveatContext<'_>) -> CaveatOutcome {
+        (self.func)(caveat)
+    }
+}
+
+/// A verifier that checks discharge caveats with the verifier of the service
+/// that issued the discharge
+///
+/// Caveats of the primary stroopwafel go to the primary verifier. Caveats of a
+/// discharge go to, in order of precedence:
+///
+/// 1. the verifier registered for the discharge identifier,
+/// 2. the verifier registered for the verification key id of the third-party
+///    caveat the discharge satisfies,
+/// 3. the verifier registered for the location of that caveat, which is not
+///    authenticated (see [`for_location`](Self::for_location)), or
+/// 4. the fallback verifier, if one was set.
+///
+/// Caveats of any other discharge are violations, so a discharge from an
+/// unknown service can never be checked against the wrong rules. Use
+/// [`with_fallback`](Self::with_fallback) or
+/// [`allow_unknown_discharges`](Self::allow_unknown_discharges) to opt out.
+///
+/// # Example
+/// ```
+/// use stroopwafel::Stroopwafel;
+/// use stroopwafel::verifier::{ContextVerifier, DischargeRouter};
+///
+/// let root_key = b"root_secret";
+/// let auth_key = b"auth_secret";
+///
+/// let mut token = Stroopwafel::new(root_key, b"identifier", None::<String>);
+/// token.add_first_party_caveat(b"action = read");
+/// token.add_third_party_caveat(b"login", auth_key, "https://auth.example.com");
+///
+/// let mut discharge = Stroopwafel::create_discharge(auth_key, b"login", None::<String>);
+/// discharge.add_first_party_caveat(b"auth_level >= 5");
+/// let discharge = token.bind_discharge(&discharge);
+///
+/// let verifier = DischargeRouter::new(ContextVerifier::empty().with("action", "read"))
+///     .for_verification_key(auth_key, ContextVerifier::empty().with("auth_level", "7"));
+///
+/// assert!(token.verify(root_key, &verifier, &[discharge]).is_ok());
+/// ```
+pub struct DischargeRouter {
+    primary: Box<dyn CaveatVerifier>,
+    identifiers: HashMap<Vec<u8>, Box<dyn CaveatVerifier>>,
+    verification_keys: HashMap<Vec<u8>, Box<dyn CaveatVerifier>>,
+    locations: HashMap<String, Box<dyn CaveatVerifier>>,
+    fallback: Option<Box<dyn CaveatVerifier>>,
+    unknown_to_primary: bool,
+}
+
+impl DischargeRouter {
+    /// Creates a router checking primary caveats with `primary`
+    pub fn new<V: CaveatVerifier + 'static>(primary: V) -> Self {
+        Self {
+            primary: Box::new(primary),
+            identifiers: HashMap::new(),
+            verification_keys: HashMap::new(),
+            locations: HashMap::new(),
+            fallback: None,
+            unknown_to_primary: false,
+        }
+    }
+
+    /// Checks caveats of discharges satisfying third-party caveats with this
+    /// verification key id
+    ///
+    /// The verification key id is bound into the primary stroopwafel's
+    /// signature and the discharge is signed with the key it stands for, so
+    /// only that key's holder can issue discharges routed here.
+    pub fn for_verification_key<V: CaveatVerifier + 'static>(
+        mut self,
+        verification_key_id: impl Into<Vec<u8>>,
+        verifier: V,
+    ) -> Self {
+        self.verification_keys
+            .insert(verification_key_id.into(), Box::new(verifier));
+        self
+    }
+
+    /// Checks caveats of discharges satisfying third-party caveats at `location`
+    ///
+    /// **Unauthenticated**: no signature covers the location of a third-party
+    /// caveat. A token holder can rewrite it, or add a third-party caveat of
+    /// their own claiming any location and discharge it themselves, and so
+    /// pick the verifier their discharge is checked with. Register verifiers
+    /// here only if their rules are safe for discharges from anyone; to trust
+    /// a service, use [`for_verification_key`](Self::for_verification_key).
+    pub fn for_location<V: CaveatVerifier + 'static>(
+        mut self,
+        location: impl Into<String>,
+        verifier: V,
+    ) -> Self {
+        self.locations.insert(location.into(), Box::new(verifier));
+        self
+    }
+
+    /// Checks caveats of the discharge with this identifier
+    ///
+    /// Takes precedence over the other registrations.
+    pub fn for_identifier<V: CaveatVerifier + 'static>(
+        mut self,
+        identifier: impl Into<Vec<u8>>,
+        verifier: V,
+    ) -> Self {
+        self.identifiers
+            .insert(identifier.into(), Box::new(verifier));
+        self
+    }
+
+    /// Checks caveats of discharges without a registered verifier with `verifier`
+    pub fn with_fallback<V: CaveatVerifier + 'static>(mut self, verifier: V) -> Self {
+        self.fallback = Some(Box::new(verifier));
+        self
+    }
+
+    /// Checks caveats of discharges without a registered verifier, and no
+    /// fallback, with the primary verifier
+    pub fn allow_unknown_discharges(mut self) -> Self {
+        self.unknown_to_primary = true;
+        self
+    }
+
+    /// Finds the verifier responsible for a caveat
+    fn verifier_for(&self, caveat: &CaveatContext<'_>) -> Option<&dyn CaveatVerifier> {
+        let Some(discharge) = caveat.discharge else {
+            return Some(self.primary.as_ref());
+        };
+
+        self.identifiers
+            .get(discharge.identifier)
+            .or_else(|| self.verification_keys.get(discharge.verification_key_id))
+            .or_else(|| discharge.location.and_then(|l| self.locations.get(l)))
+            .map(|verifier| verifier.as_ref())
+            .or(self.fallback.as_deref())
+            .or_else(|| self.unknown_to_primary.then_some(self.primary.as_ref()))
+    }
+}
+
+impl CaveatVerifier for DischargeRouter {
+    fn check(&self, caveat: &CaveatContext<'_>) -> CaveatOutcome {
+        match self.verifier_for(caveat) {
+            Some(verifier) => verifier.check(caveat),
+            None => CaveatOutcome::Violated(format!(
+                "No verifier for discharge '{}'",
+                String::from_utf8_lossy(caveat.discharge.map_or(&[][..], |d| d.identifier))
+            )),
+        }
+    }
+}
+
+/// The outcome of checking a caveat with [`Verifier::check_caveat`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaveatOutcome {
+    /// The caveat holds
+    Satisfied,
+    /// The caveat does not hold, with the reason
+    Violated(String),
+    /// The verifier does not apply to this caveat
+    Unrecognized,
+    /// The caveat could not be evaluated, e.g. because the context has no
+    /// value for its key or a lookup failed
+    ///
+    /// Unlike a violation, an error is never inverted by [`Not`], so a
+    /// negated caveat cannot pass for lack of information. Verification fails
+    /// with the error; a `CaveatViolation` is reported with the caveat's
+    /// position, like [`Violated`](Self::Violated).
+    Error(StroopwafelError),
+}
+
+impl CaveatOutcome {
+    /// Returns true if the caveat holds
+    pub fn is_satisfied(&self) -> bool {
+        matches!(self, CaveatOutcome::Satisfied)
+    }
+
+    /// Maps a [`Verifier::verify_caveat`] result to an outcome
+    ///
+    /// `CaveatViolation` is a violation, and errors saying the caveat could
+    /// not be parsed or decoded (`InvalidFormat`, `DeserializationError`,
+    /// `Decode`) mean the caveat is unrecognized. Any other error (e.g.
+    /// `MissingContext`, `InvalidTime` or `Storage`) is kept as an
+    /// [`Error`](Self::Error).
+    pub fn from_result(result: Result<()>) -> Self {
+        match result {
+            Ok(()) => CaveatOutcome::Satisfied,
+            Err(StroopwafelError::CaveatViolation(reason)) => CaveatOutcome::Violated(reason),
+            Err(
+                StroopwafelError::InvalidFormat(_)
+                | StroopwafelError::DeserializationError(_)
+                | StroopwafelError::Decode(_),
+            ) => CaveatOutcome::Unrecognized,
+            Err(e) => CaveatOutcome::Error(e),
+        }
+    }
+
+    /// Converts the outcome to a [`Verifier::verify_caveat`] result
+    ///
+    /// Only a satisfied caveat is accepted; unrecognized caveats are rejected.
+    pub fn into_result(self, predicate: &[u8]) -> Result<()> {
+        match self {
+            CaveatOutcome::Satisfied => Ok(()),
+            CaveatOutcome::Violated(reason) => Err(StroopwafelError::CaveatViolation(reason)),
+            CaveatOutcome::Unrecognized => Err(StroopwafelError::CaveatViolation(format!(
+                "Unrecognized caveat: {}",
+                String::from_utf8_lossy(predicate)
+            ))),
+            CaveatOutcome::Error(e) => Err(e),
+        }
+    }
+}
+
+/// An asynchronous counterpart to [`Verifier`] for caveat checks that need I/O
+///
+/// Use this for caveats such as `org_member = acme` that are resolved by a
+/// database or network lookup, and verify with
+/// [`Stroopwafel::verify_async`](crate::Stroopwafel::verify_async). Existing
+/// synchronous verifiers are wrapped with [`SyncAdapter`].
+///
+/// # Example
+/// ```
+/// use stroopwafel::verifier::AsyncVerifier;
+/// use stroopwafel::{Result, StroopwafelError};
+///
+/// struct OrgMembership;
+///
+/// impl AsyncVerifier for OrgMembership {
+///     async fn verify_caveat(&self, predicate: &[u8]) -> Result<()> {
+///         // Look the membership up in a database...
+///         if predicate == b"org_member = acme" {
+///             Ok(())
+///         } else {
+///             Err(StroopwafelError::CaveatViolation("Not a member".to_string()))
+///         }
+///     }
+/// }
+/// ```
+pub trait AsyncVerifier {
+    /// Verifies a single caveat predicate
+    ///
+    /// Returns a future resolving to the same results as
+    /// [`Verifier::verify_caveat`].
+    fn verify_caveat(&self, predicate: &[u8]) -> impl Future<Output = Result<()>> + Send;
+
+    /// Checks a single caveat predicate, like [`Verifier::check_caveat`]
+    ///
+    /// The default implementation maps the result of
+    /// [`verify_caveat`](Self::verify_caveat) with [`CaveatOutcome::from_result`].
+    fn check_caveat(&self, predicate: &[u8]) -> impl Future<Output = CaveatOutcome> + Send {
+        let result = self.verify_caveat(predicate);
+        async move { CaveatOutcome::from_result(result.await) }
+    }
+
+    /// Checks a single caveat given where it sits, like
+    /// [`CaveatVerifier::check`]
+    ///
+    /// The default implementation ignores the context and calls
+    /// [`check_caveat`](Self::check_caveat).
+    fn check(&self, caveat: &CaveatContext<'_>) -> impl Future<Output = CaveatOutcome> + Send {
+        self.check_caveat(caveat.predicate)
+    }
+}
+
+/// Adapts a synchronous [`Verifier`] for use where an [`AsyncVerifier`] is expected
+///
+/// The wrapped verifier runs inline when the future is created, so it should
+/// not block for long.
+#[derive(Debug, Clone, Default)]
+pub struct SyncAdapter<V> {
+    verifier: V,
+}
+
+impl<V: Verifier> SyncAdapter<V> {
+    /// Wraps a synchronous verifier
+    pub fn new(verifier: V) -> Self {
+        Self { verifier }
+    }
+
+    /// Returns the wrapped verifier
+    pub fn into_inner(self) -> V {
+        self.verifier
+    }
+}
+
+impl<V: Verifier> AsyncVerifier for SyncAdapter<V> {
+    fn verify_caveat(&self, predicate: &[u8]) -> impl Future<Output = Result<()>> + Send {
+        std::future::ready(self.verifier.verify_caveat(predicate))
+    }
+
+    fn check_caveat(&self, predicate: &[u8]) -> impl Future<Output = CaveatOutcome> + Send {
+        std::future::ready(self.verifier.check_caveat(predicate))
+    }
 }
 
 /// A simple verifier that accepts all caveats
@@ -89,7 +501,8 @@ where
 
 /// A composite verifier that tries multiple verifiers in sequence
 ///
-/// Each caveat must be verified by at least one of the verifiers.
+/// Each caveat must be verified by at least one of the verifiers. An empty
+/// composite accepts every caveat; prefer [`AnyOf`], which fails closed.
 pub struct CompositeVerifier {
     verifiers: Vec<Box<dyn Verifier>>,
 }
@@ -117,28 +530,395 @@ impl Default for CompositeVerifier {
 
 impl Verifier for CompositeVerifier {
     fn verify_caveat(&self, predicate: &[u8]) -> Result<()> {
+        self.check_caveat(predicate).into_result(predicate)
+    }
+
+    fn check_caveat(&self, predicate: &[u8]) -> CaveatOutcome {
         if self.verifiers.is_empty() {
-            return Ok(());
+            return CaveatOutcome::Satisfied;
         }
 
-        // Try each verifier until one succeeds
+        // Try each verifier until one succeeds, keeping the reasons of those
+        // that reject the caveat
+        let mut reasons = Vec::new();
+        let mut error = None;
         for verifier in &self.verifiers {
-            if verifier.verify_caveat(predicate).is_ok() {
-                return Ok(());
+            match verifier.check_caveat(predicate) {
+                CaveatOutcome::Satisfied => return CaveatOutcome::Satisfied,
+                CaveatOutcome::Violated(reason) => reasons.push(reason),
+                CaveatOutcome::Unrecognized => {}
+                CaveatOutcome::Error(e) => {
+                    error.get_or_insert(e);
+                }
             }
         }
 
-        // All verifiers failed
-        Err(StroopwafelError::CaveatViolation(
-            String::from_utf8_lossy(predicate).to_string(),
-        ))
+        match error {
+            Some(e) => CaveatOutcome::Error(e),
+            None if reasons.is_empty() => CaveatOutcome::Unrecognized,
+            None => CaveatOutcome::Violated(reasons.join("; ")),
+        }
+    }
+}
+
+/// A verifier that requires every inner verifier to be satisfied
+///
+/// The first violation or error wins; otherwise the caveat is unrecognized if
+/// any inner verifier does not recognize it. An empty `AllOf` rejects every caveat.
+///
+/// # Example
+/// ```
+/// use stroopwafel::verifier::{AllOf, ContextVerifier, FnVerifier, Verifier};
+///
+/// let verifier = AllOf::new()
+///     .add_verifier(ContextVerifier::empty().with("level", "10"))
+///     .add_verifier(FnVerifier::new(|predicate| {
+///         if predicate.starts_with(b"level") {
+///             Ok(())
+///         } else {
+///             Err(stroopwafel::StroopwafelError::CaveatViolation("Unexpected caveat".into()))
+///         }
+///     }));
+///
+/// assert!(verifier.verify_caveat(b"level >= 5").is_ok());
+/// assert!(verifier.verify_caveat(b"level >= 50").is_err());
+/// ```
+pub struct AllOf {
+    verifiers: Vec<Box<dyn Verifier>>,
+}
+
+impl AllOf {
+    /// Creates an empty `AllOf`, which rejects every caveat
+    pub fn new() -> Self {
+        Self {
+            verifiers: Vec::new(),
+        }
+    }
+
+    /// Adds a verifier that must be satisfied
+    pub fn add_verifier<V: Verifier + 'static>(mut self, verifier: V) -> Self {
+        self.verifiers.push(Box::new(verifier));
+        self
     }
 }
 
+impl Default for AllOf {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Verifier for AllOf {
+    fn verify_caveat(&self, predicate: &[u8]) -> Result<()> {
+        self.check_caveat(predicate).into_result(predicate)
+    }
+
+    fn check_caveat(&self, predicate: &[u8]) -> CaveatOutcome {
+        if self.verifiers.is_empty() {
+            return CaveatOutcome::Violated("AllOf has no verifiers".to_string());
+        }
+
+        let mut outcome = CaveatOutcome::Satisfied;
+        for verifier in &self.verifiers {
+            match verifier.check_caveat(predicate) {
+                CaveatOutcome::Satisfied => {}
+                failed @ (CaveatOutcome::Violated(_) | CaveatOutcome::Error(_)) => return failed,
+                CaveatOutcome::Unrecognized => outcome = CaveatOutcome::Unrecognized,
+            }
+        }
+
+        outcome
+    }
+}
+
+/// A verifier that requires at least one inner verifier to be satisfied
+///
+/// Unlike [`CompositeVerifier`], an empty `AnyOf` rejects every caveat. If no
+/// verifier is satisfied, the first error wins; otherwise the caveat is
+/// violated when any verifier reports a violation and unrecognized otherwise.
+pub struct AnyOf {
+    verifiers: Vec<Box<dyn Verifier>>,
+}
+
+impl AnyOf {
+    /// Creates an empty `AnyOf`, which rejects every caveat
+    pub fn new() -> Self {
+        Self {
+            verifiers: Vec::new(),
+        }
+    }
+
+    /// Adds a verifier that may satisfy the caveat
+    pub fn add_verifier<V: Verifier + 'static>(mut self, verifier: V) -> Self {
+        self.verifiers.push(Box::new(verifier));
+        self
+    }
+}
+
+impl Default for AnyOf {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Verifier for AnyOf {
+    fn verify_caveat(&self, predicate: &[u8]) -> Result<()> {
+        self.check_caveat(predicate).into_result(predicate)
+    }
+
+    fn check_caveat(&self, predicate: &[u8]) -> CaveatOutcome {
+        if self.verifiers.is_empty() {
+            return CaveatOutcome::Violated("AnyOf has no verifiers".to_string());
+        }
+
+        let mut reasons = Vec::new();
+        let mut error = None;
+        for verifier in &self.verifiers {
+            match verifier.check_caveat(predicate) {
+                CaveatOutcome::Satisfied => return CaveatOutcome::Satisfied,
+                CaveatOutcome::Violated(reason) => reasons.push(reason),
+                CaveatOutcome::Unrecognized => {}
+                CaveatOutcome::Error(e) => {
+                    error.get_or_insert(e);
+                }
+            }
+        }
+
+        match error {
+            Some(e) => CaveatOutcome::Error(e),
+            None if reasons.is_empty() => CaveatOutcome::Unrecognized,
+            None => CaveatOutcome::Violated(reasons.join("; ")),
+        }
+    }
+}
+
+/// A verifier that inverts another verifier
+///
+/// A satisfied caveat becomes a violation and a violated caveat is satisfied.
+/// Unrecognized caveats stay unrecognized and errors stay errors, so `Not`
+/// never accepts a caveat the inner verifier does not understand or could not
+/// evaluate (e.g. for lack of a context value).
+pub struct Not<V> {
+    verifier: V,
+}
+
+impl<V: Verifier> Not<V> {
+    /// Inverts `verifier`
+    pub fn new(verifier: V) -> Self {
+        Self { verifier }
+    }
+}
+
+impl<V: Verifier> Verifier for Not<V> {
+    fn verify_caveat(&self, predicate: &[u8]) -> Result<()> {
+        self.check_caveat(predicate).into_result(predicate)
+    }
+
+    fn check_caveat(&self, predicate: &[u8]) -> CaveatOutcome {
+        match self.verifier.check_caveat(predicate) {
+            CaveatOutcome::Satisfied => CaveatOutcome::Violated(format!(
+                "Negated caveat holds: {}",
+                String::from_utf8_lossy(predicate)
+            )),
+            CaveatOutcome::Violated(_) => CaveatOutcome::Satisfied,
+            other @ (CaveatOutcome::Unrecognized | CaveatOutcome::Error(_)) => other,
+        }
+    }
+}
+
+/// A verifier that defers to the first inner verifier recognizing a caveat
+///
+/// Verifiers are consulted in order; the first one returning anything other
+/// than [`CaveatOutcome::Unrecognized`] decides. If none recognizes the caveat,
+/// including when the chain is empty, it is unrecognized and therefore
+/// rejected by [`verify_caveat`](Verifier::verify_caveat).
+///
+/// Only verifiers that override [`Verifier::check_caveat`] can pass a caveat
+/// on; any other verifier decides every caveat it is asked about.
+pub struct FirstMatch {
+    verifiers: Vec<Box<dyn Verifier>>,
+}
+
+impl FirstMatch {
+    /// Creates an empty chain, which rejects every caveat
+    pub fn new() -> Self {
+        Self {
+            verifiers: Vec::new(),
+        }
+    }
+
+    /// Appends a verifier to the chain
+    pub fn add_verifier<V: Verifier + 'static>(mut self, verifier: V) -> Self {
+        self.verifiers.push(Box::new(verifier));
+        self
+    }
+}
+
+impl Default for FirstMatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Verifier for FirstMatch {
+    fn verify_caveat(&self, predicate: &[u8]) -> Result<()> {
+        self.check_caveat(predicate).into_result(predicate)
+    }
+
+    fn check_caveat(&self, predicate: &[u8]) -> CaveatOutcome {
+        self.verifiers
+            .iter()
+            .map(|verifier| verifier.check_caveat(predicate))
+            .find(|outcome| *outcome != CaveatOutcome::Unrecognized)
+            .unwrap_or(CaveatOutcome::Unrecognized)
+    }
+}
+
+/// A verifier that routes each caveat to a checker registered for its key
+///
+/// The key of a caveat is the key of its predicate (e.g. `account` in
+/// `"account = alice"`), or for caveats that are not predicates, the first
+/// whitespace-separated word (the condition name, e.g. `declared` in
+/// `"declared user alice"`). The caveat is then handed, unchanged, to:
+///
+/// 1. the checker registered for exactly that key, or
+/// 2. the checker registered for the longest matching namespace prefix
+///    (e.g. `bank:` for `bank:balance >= 10`), or
+/// 3. the fallback checker, if one was set.
+///
+/// Caveats with no matching checker are rejected, so a checker can only ever
+/// satisfy the caveats it was registered for.
+///
+/// # Example
+/// ```
+/// use stroopwafel::verifier::{AcceptAllVerifier, ContextVerifier, KeyedVerifier, Verifier};
+///
+/// let verifier = KeyedVerifier::new()
+///     .register("account", ContextVerifier::empty().with("account", "alice"))
+///     .register_namespace("bank:", ContextVerifier::empty().with("bank:balance", "100"));
+///
+/// assert!(verifier.verify_caveat(b"account = alice").is_ok());
+/// assert!(verifier.verify_caveat(b"bank:balance >= 10").is_ok());
+/// assert!(verifier.verify_caveat(b"account = bob").is_err());
+///
+/// // No checker is registered for "action"
+/// assert!(verifier.verify_caveat(b"action = read").is_err());
+/// ```
+pub struct KeyedVerifier {
+    checkers: HashMap<String, Box<dyn Verifier>>,
+    namespaces: Vec<(String, Box<dyn Verifier>)>,
+    fallback: Option<Box<dyn Verifier>>,
+}
+
+impl KeyedVerifier {
+    /// Creates a keyed verifier with no registered checkers
+    pub fn new() -> Self {
+        Self {
+            checkers: HashMap::new(),
+            namespaces: Vec::new(),
+            fallback: None,
+        }
+    }
+
+    /// Registers a checker for caveats with exactly this key
+    ///
+    /// Registering the same key twice replaces the earlier checker.
+    pub fn register<V: Verifier + 'static>(mut self, key: impl Into<String>, verifier: V) -> Self {
+        self.checkers.insert(key.into(), Box::new(verifier));
+        self
+    }
+
+    /// Registers a checker for every key starting with `prefix` (e.g. `"bank:"`)
+    ///
+    /// Exact key registrations take precedence, and among namespaces the
+    /// longest matching prefix wins.
+    pub fn register_namespace<V: Verifier + 'static>(
+        mut self,
+        prefix: impl Into<String>,
+        verifier: V,
+    ) -> Self {
+        let prefix = prefix.into();
+        self.namespaces.retain(|(existing, _)| *existing != prefix);
+        self.namespaces.push((prefix, Box::new(verifier)));
+        // Keep the longest prefixes first so the first match is the most specific
+        self.namespaces
+            .sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        self
+    }
+
+    /// Sends caveats with unknown keys to `verifier` instead of rejecting them
+    pub fn with_fallback<V: Verifier + 'static>(mut self, verifier: V) -> Self {
+        self.fallback = Some(Box::new(verifier));
+        self
+    }
+
+    /// Finds the checker responsible for a caveat key
+    fn checker_for(&self, key: &str) -> Option<&dyn Verifier> {
+        if let Some(checker) = self.checkers.get(key) {
+            return Some(checker.as_ref());
+        }
+
+        self.namespaces
+            .iter()
+            .find(|(prefix, _)| key.starts_with(prefix.as_str()))
+            .map(|(_, checker)| checker.as_ref())
+            .or(self.fallback.as_deref())
+    }
+}
+
+impl Default for KeyedVerifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Verifier for KeyedVerifier {
+    fn verify_caveat(&self, predicate: &[u8]) -> Result<()> {
+        let key = caveat_key(predicate).ok_or_else(|| {
+            StroopwafelError::CaveatViolation(format!(
+                "Cannot determine key of caveat: {}",
+                String::from_utf8_lossy(predicate)
+            ))
+        })?;
+
+        match self.checker_for(&key) {
+            Some(checker) => checker.verify_caveat(predicate),
+            None => Err(StroopwafelError::CaveatViolation(format!(
+                "No checker registered for caveat key '{key}'"
+            ))),
+        }
+    }
+
+    fn check_caveat(&self, predicate: &[u8]) -> CaveatOutcome {
+        match caveat_key(predicate).and_then(|key| self.checker_for(&key)) {
+            Some(checker) => checker.check_caveat(predicate),
+            None => CaveatOutcome::Unrecognized,
+        }
+    }
+}
+
+/// Extracts the dispatch key of a caveat
+///
+/// This is the predicate key for parseable caveats, and otherwise the first
+/// word of a UTF-8 caveat.
+fn caveat_key(predicate: &[u8]) -> Option<String> {
+    if let Ok(parsed) = ParsedCaveat::parse(predicate) {
+        return Some(parsed.key().to_string());
+    }
+
+    std::str::from_utf8(predicate)
+        .ok()?
+        .split_whitespace()
+        .next()
+        .map(str::to_string)
+}
+
 /// A context-based verifier that evaluates predicates against a context map
 ///
 /// This verifier parses caveat predicates (e.g., "account = alice", "time < 2025-12-31")
-/// and evaluates them against a provided context.
+/// and evaluates them against a provided context. Structured caveats (see
+/// [`StructuredCaveat`](crate::structured::StructuredCaveat)) are decoded and
+/// evaluated against the same context.
 ///
 /// # Example
 /// ```
@@ -160,36 +940,174 @@ impl Verifier for CompositeVerifier {
 /// ```
 pub struct ContextVerifier {
     context: HashMap<String, String>,
+    providers: HashMap<String, LazyValue>,
+    clock: Option<Box<dyn Clock>>,
+    clock_skew: Duration,
+    missing_keys: MissingKeyPolicy,
+}
+
+/// Computes a context value on demand for [`ContextVerifier`]
+///
+/// Closures returning `Option<String>` are providers. Return `None` when the
+/// key has no value, which fails caveats on the key like a missing entry.
+pub trait ContextProvider: Send + Sync {
+    /// Computes the value
+    fn provide(&self) -> Option<String>;
+}
+
+impl<F> ContextProvider for F
+where
+    F: Fn() -> Option<String> + Send + Sync,
+{
+    fn provide(&self) -> Option<String> {
+        self()
+    }
+}
+
+/// A provider together with its memoized value
+struct LazyValue {
+    provider: Box<dyn ContextProvider>,
+    value: Mutex<Option<Option<String>>>,
+}
+
+impl LazyValue {
+    fn get(&self) -> Option<String> {
+        if let Some(value) = &*self.value.lock().unwrap_or_else(|e| e.into_inner()) {
+            return value.clone();
+        }
+
+        // The provider runs without the lock held, so a slow lookup does not
+        // block verifications that only read other memoized values
+        let value = self.provider.provide();
+        self.value
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get_or_insert(value)
+            .clone()
+    }
+
+    fn reset(&self) {
+        *self.value.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
 }
 
 impl ContextVerifier {
     /// Creates a new context verifier with the given context
     pub fn new(context: HashMap<String, String>) -> Self {
-        Self { context }
+        Self {
+            context,
+            providers: HashMap::new(),
+            clock: None,
+            clock_skew: Duration::ZERO,
+            missing_keys: MissingKeyPolicy::default(),
+        }
     }
 
     /// Creates a new context verifier with an empty context
     pub fn empty() -> Self {
-        Self {
-            context: HashMap::new(),
-        }
+        Self::new(HashMap::new())
     }
 
     /// Adds a key-value pair to the context
     pub fn with(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
-        self.context.insert(key.into(), value.into());
+        self.set(key, value);
         self
     }
 
     /// Sets a key-value pair in the context
     pub fn set(&mut self, key: impl Into<String>, value: impl Into<String>) {
-        self.context.insert(key.into(), value.into());
+        let key = key.into();
+        self.providers.remove(&key);
+        self.context.insert(key, value.into());
+    }
+
+    /// Adds a context value computed by `provider` the first time a caveat
+    /// uses `key`
+    ///
+    /// The value is memoized, so the provider runs at most once until
+    /// [`reset`](Self::reset) is called, however many caveats and tokens use
+    /// it. A verifier built per request needs nothing more; a long-lived
+    /// verifier keeps returning the first value it computed, so call `reset`
+    /// between verifications whose values may differ. Replaces any value set
+    /// for `key` before.
+    ///
+    /// # Example
+    /// ```
+    /// use stroopwafel::verifier::{ContextVerifier, Verifier};
+    ///
+    /// let verifier = ContextVerifier::empty()
+    ///     .with("account", "alice")
+    ///     .with_provider("groups", || {
+    ///         // An expensive lookup, skipped for tokens without a "groups" caveat
+    ///         Some("admins,staff".to_string())
+    ///     });
+    ///
+    /// assert!(verifier.verify_caveat(b"account = alice").is_ok());
+    /// ```
+    pub fn with_provider(
+        mut self,
+        key: impl Into<String>,
+        provider: impl ContextProvider + 'static,
+    ) -> Self {
+        let key = key.into();
+        self.context.remove(&key);
+        self.providers.insert(
+            key,
+            LazyValue {
+                provider: Box::new(provider),
+                value: Mutex::new(None),
+            },
+        );
+        self
+    }
+
+    /// Forgets the memoized provider values, so each provider runs again the
+    /// next time a caveat uses its key
+    ///
+    /// Call this between verifications when reusing a verifier whose
+    /// providers may compute different values over time. Verifications
+    /// running concurrently with a reset may see either the old or the new
+    /// values.
+    ///
+    /// # Example
+    /// ```
+    /// use stroopwafel::verifier::{ContextVerifier, Verifier};
+    /// use std::sync::atomic::{AtomicU64, Ordering};
+    ///
+    /// static LEVEL: AtomicU64 = AtomicU64::new(3);
+    ///
+    /// let verifier = ContextVerifier::empty()
+    ///     .with_lazy("level", || LEVEL.load(Ordering::SeqCst).to_string());
+    /// assert!(verifier.verify_caveat(b"level < 5").is_ok());
+    ///
+    /// LEVEL.store(7, Ordering::SeqCst);
+    /// assert!(verifier.verify_caveat(b"level < 5").is_ok());
+    ///
+    /// verifier.reset();
+    /// assert!(verifier.verify_caveat(b"level < 5").is_err());
+    /// ```
+    pub fn reset(&self) {
+        for lazy in self.providers.values() {
+            lazy.reset();
+        }
+    }
+
+    /// Adds a context value computed by `value` the first time a caveat uses
+    /// `key`, like [`with_provider`](Self::with_provider) for values that are
+    /// always present
+    pub fn with_lazy<F, V>(self, key: impl Into<String>, value: F) -> Self
+    where
+        F: Fn() -> V + Send + Sync + 'static,
+        V: Into<String>,
+    {
+        self.with_provider(key, move || Some(value().into()))
     }
 
     /// Creates a context verifier with the current system time.
     ///
-    /// This is a convenience method for time-based caveat validation. It adds
-    /// a "time" key with the current Unix timestamp.
+    /// This is a convenience method for time-based caveat validation. The
+    /// "time" key resolves to the current Unix timestamp, read from the
+    /// [`SystemClock`] whenever a caveat uses it.
     ///
     /// # Example
     /// ```
@@ -207,56 +1125,189 @@ impl ContextVerifier {
     /// assert!(token.verify(root_key, &verifier, &[]).is_ok());
     /// ```
     pub fn with_current_time() -> Self {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("System time before UNIX epoch")
-            .as_secs();
+        Self::empty().with_time()
+    }
+
+    /// Adds the current system time to an existing context verifier.
+    ///
+    /// This makes the "time" key resolve to the current Unix timestamp.
+    ///
+    /// # Example
+    /// ```
+    /// use stroopwafel::verifier::ContextVerifier;
+    ///
+    /// let verifier = ContextVerifier::empty()
+    ///     .with("account", "alice")
+    ///     .with_time();
+    /// ```
+    pub fn with_time(self) -> Self {
+        self.with_clock(SystemClock)
+    }
+
+    /// Resolves the "time" key from `clock` whenever a caveat uses it
+    ///
+    /// An explicit "time" entry in the context takes precedence. A clock set
+    /// before the Unix epoch makes time caveats fail instead of panicking.
+    ///
+    /// # Example
+    /// ```
+    /// use stroopwafel::clock::ManualClock;
+    /// use stroopwafel::verifier::{ContextVerifier, Verifier};
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// let clock = Arc::new(ManualClock::at_unix(1_000));
+    /// let verifier = ContextVerifier::empty().with_clock(Arc::clone(&clock));
+    /// assert!(verifier.verify_caveat(b"time < 1060").is_ok());
+    ///
+    /// clock.advance(Duration::from_secs(60));
+    /// assert!(verifier.verify_caveat(b"time < 1060").is_err());
+    /// ```
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Some(Box::new(clock));
+        self
+    }
 
-        Self::empty().with("time", now.to_string())
+    /// Tolerates clocks that are off by up to `skew`
+    ///
+    /// Expiry caveats (`time <` / `time <=`) are checked against the clock
+    /// time minus `skew`, and not-before caveats (`time >` / `time >=`)
+    /// against the clock time plus `skew`, so both pass within the tolerance.
+    /// Other comparisons use the clock time unchanged.
+    pub fn with_clock_skew(mut self, skew: Duration) -> Self {
+        self.clock_skew = skew;
+        self
     }
 
-    /// Adds the current system time to an existing context verifier.
+    /// Sets how caveats evaluate when their key has no value
     ///
-    /// This sets the "time" key to the current Unix timestamp.
+    /// Keys are missing when no explicit value, provider value, or clock
+    /// supplies them. The default, [`MissingKeyPolicy::Fail`], fails the
+    /// caveat. `exists` and `!exists` caveats test for presence under every
+    /// policy.
     ///
     /// # Example
     /// ```
-    /// use stroopwafel::verifier::ContextVerifier;
+    /// use stroopwafel::predicate::MissingKeyPolicy;
+    /// use stroopwafel::verifier::{ContextVerifier, Verifier};
     ///
-    /// let verifier = ContextVerifier::empty()
-    ///     .with("account", "alice")
-    ///     .with_time();
+    /// let verifier = ContextVerifier::empty().with_missing_key_policy(MissingKeyPolicy::Null);
+    /// assert!(verifier.verify_caveat(b"role != admin").is_ok());
+    /// assert!(verifier.verify_caveat(b"role = admin").is_err());
+    /// assert!(verifier.verify_caveat(b"role !exists").is_ok());
     /// ```
-    pub fn with_time(self) -> Self {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("System time before UNIX epoch")
-            .as_secs();
+    pub fn with_missing_key_policy(mut self, policy: MissingKeyPolicy) -> Self {
+        self.missing_keys = policy;
+        self
+    }
+
+    /// Looks up the context value for a caveat's key
+    ///
+    /// Explicit values take precedence over providers, and both over the clock.
+    fn value_for(&self, caveat: &ParsedCaveat) -> Result<Option<Cow<'_, str>>> {
+        let key = caveat.key();
+        if let Some(value) = self.context.get(key) {
+            return Ok(Some(Cow::Borrowed(value)));
+        }
+        if let Some(lazy) = self.providers.get(key) {
+            return Ok(lazy.get().map(Cow::Owned));
+        }
+
+        match &self.clock {
+            Some(clock) if key == TIME_KEY => {
+                let now = self.clock_time(clock.as_ref(), caveat.operator())?;
+                Ok(Some(Cow::Owned(now.to_string())))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the clock time to compare a time caveat against, adjusted
+    /// for skew in the direction that favors the caveat
+    fn clock_time(&self, clock: &dyn Clock, operator: Operator) -> Result<Timestamp> {
+        let now = Timestamp::from_system_time(clock.now())?;
+        let skew = i128::try_from(self.clock_skew.as_nanos()).unwrap_or(i128::MAX);
 
-        self.with("time", now.to_string())
+        Ok(match operator {
+            Operator::LessThan | Operator::LessThanOrEqual => now.saturating_add_nanos(-skew),
+            Operator::GreaterThan | Operator::GreaterThanOrEqual => now.saturating_add_nanos(skew),
+            _ => now,
+        })
     }
 }
 
 impl Verifier for ContextVerifier {
     fn verify_caveat(&self, predicate_bytes: &[u8]) -> Result<()> {
-        let predicate_str = std::str::from_utf8(predicate_bytes)
-            .map_err(|e| StroopwafelError::InvalidFormat(e.to_string()))?;
+        let caveat = ParsedCaveat::parse(predicate_bytes)?;
+
+        match self.evaluate(&caveat)? {
+            Evaluation::Holds => Ok(()),
+            Evaluation::Fails | Evaluation::FailsWithoutValue => {
+                Err(StroopwafelError::CaveatViolation(violation(&caveat)))
+            }
+        }
+    }
 
-        let predicate = Predicate::parse(predicate_str)?;
+    /// Like [`verify_caveat`](Verifier::verify_caveat), except that a caveat
+    /// failing only because its key has no value is an
+    /// [`Error`](CaveatOutcome::Error) rather than a violation, so negating
+    /// it does not make it pass
+    fn check_caveat(&self, predicate_bytes: &[u8]) -> CaveatOutcome {
+        let caveat = match ParsedCaveat::parse(predicate_bytes) {
+            Ok(caveat) => caveat,
+            Err(e) => return CaveatOutcome::from_result(Err(e)),
+        };
 
-        if predicate.evaluate(&self.context) {
-            Ok(())
-        } else {
-            Err(StroopwafelError::CaveatViolation(format!(
-                "Predicate '{predicate_str}' failed"
-            )))
+        match self.evaluate(&caveat) {
+            Ok(Evaluation::Holds) => CaveatOutcome::Satisfied,
+            Ok(Evaluation::Fails) => CaveatOutcome::Violated(violation(&caveat)),
+            Ok(Evaluation::FailsWithoutValue) => {
+                CaveatOutcome::Error(StroopwafelError::CaveatViolation(violation(&caveat)))
+            }
+            Err(e) => CaveatOutcome::Error(e),
         }
     }
 }
 
+/// How a caveat evaluates against a [`ContextVerifier`]
+enum Evaluation {
+    Holds,
+    Fails,
+    /// The caveat fails because its key has no value under
+    /// [`MissingKeyPolicy::Fail`]
+    FailsWithoutValue,
+}
+
+impl ContextVerifier {
+    /// Evaluates a parsed caveat against the context
+    fn evaluate(&self, caveat: &ParsedCaveat) -> Result<Evaluation> {
+        let (satisfied, has_value) = match self.value_for(caveat)? {
+            Some(actual) => (caveat.evaluate_value(&actual), true),
+            None => (caveat.evaluate_missing(self.missing_keys)?, false),
+        };
+
+        Ok(if satisfied {
+            Evaluation::Holds
+        } else if has_value
+            || self.missing_keys != MissingKeyPolicy::Fail
+            || caveat.operator().is_unary()
+        {
+            Evaluation::Fails
+        } else {
+            Evaluation::FailsWithoutValue
+        })
+    }
+}
+
+/// Returns the reason a caveat is violated
+fn violation(caveat: &ParsedCaveat) -> String {
+    format!("Predicate '{caveat}' failed")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::caveat::DischargeContext;
 
     #[test]
     fn test_accept_all_verifier() {
@@ -320,6 +1371,510 @@ mod tests {
         assert!(verifier.verify_caveat(b"charlie").is_err());
     }
 
+    /// Recognizes only caveats starting with `prefix`, accepting them
+    struct Prefixed(&'static [u8], bool);
+
+    impl Verifier for Prefixed {
+        fn verify_caveat(&self, predicate: &[u8]) -> Result<()> {
+            self.check_caveat(predicate).into_result(predicate)
+        }
+
+        fn check_caveat(&self, predicate: &[u8]) -> CaveatOutcome {
+            match (predicate.starts_with(self.0), self.1) {
+                (false, _) => CaveatOutcome::Unrecognized,
+                (true, true) => CaveatOutcome::Satisfied,
+                (true, false) => CaveatOutcome::Violated("prefixed".to_string()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_caveat_outcome_conversions() {
+        assert_eq!(CaveatOutcome::from_result(Ok(())), CaveatOutcome::Satisfied);
+        assert_eq!(
+            CaveatOutcome::from_result(Err(StroopwafelError::CaveatViolation("no".into()))),
+            CaveatOutcome::Violated("no".to_string())
+        );
+        assert!(CaveatOutcome::Satisfied.into_result(b"x").is_ok());
+        assert!(CaveatOutcome::Unrecognized.into_result(b"x").is_err());
+        assert_eq!(
+            RejectAllVerifier.check_caveat(b"a = b"),
+            CaveatOutcome::Violated("a = b".to_string())
+        );
+    }
+
+    #[test]
+    fn test_unparseable_caveats_are_unrecognized() {
+        let verifier = ContextVerifier::empty().with("account", "alice");
+        assert_eq!(
+            verifier.check_caveat(b"garbage"),
+            CaveatOutcome::Unrecognized
+        );
+        assert_eq!(
+            verifier.check_caveat(&[0xff, 0xfe]),
+            CaveatOutcome::Unrecognized
+        );
+        assert!(matches!(
+            verifier.check_caveat(b"account = bob"),
+            CaveatOutcome::Violated(_)
+        ));
+    }
+
+    #[test]
+    fn test_composite_verifier_keeps_outcomes() {
+        let verifier = CompositeVerifier::new()
+            .add_verifier(Prefixed(b"org", true))
+            .add_verifier(ContextVerifier::empty().with("account", "alice"));
+
+        assert_eq!(
+            verifier.check_caveat(b"org = acme"),
+            CaveatOutcome::Satisfied
+        );
+        assert_eq!(
+            verifier.check_caveat(b"garbage"),
+            CaveatOutcome::Unrecognized
+        );
+        assert!(matches!(
+            verifier.check_caveat(b"account = bob"),
+            CaveatOutcome::Violated(_)
+        ));
+    }
+
+    #[test]
+    fn test_keyed_verifier_unknown_keys_are_unrecognized() {
+        let verifier = KeyedVerifier::new()
+            .register("account", ContextVerifier::empty().with("account", "alice"));
+
+        assert_eq!(
+            verifier.check_caveat(b"action = read"),
+            CaveatOutcome::Unrecognized
+        );
+        assert!(matches!(
+            verifier.check_caveat(b"account = bob"),
+            CaveatOutcome::Violated(_)
+        ));
+    }
+
+    #[test]
+    fn test_verifiers_are_caveat_verifiers() {
+        let verifier = ContextVerifier::empty().with("account", "alice");
+        let discharge = DischargeContext {
+            index: 0,
+            identifier: b"auth",
+            verification_key_id: b"auth_key",
+            location: Some("https://auth.example.com"),
+        };
+
+        // The context is ignored by plain verifiers
+        assert!(
+            verifier
+                .check(&CaveatContext::primary(b"account = alice", 0))
+                .is_satisfied()
+        );
+        assert!(
+            verifier
+                .check(&CaveatContext::discharge(b"account = alice", 3, discharge))
+                .is_satisfied()
+        );
+        assert_eq!(
+            verifier.check(&CaveatContext::primary(b"garbage", 0)),
+            CaveatOutcome::Unrecognized
+        );
+    }
+
+    #[test]
+    fn test_fn_caveat_verifier() {
+        let verifier = FnCaveatVerifier::new(|caveat| match caveat.discharge {
+            Some(d) if d.verification_key_id == b"identity_key" => CaveatOutcome::Satisfied,
+            _ => CaveatOutcome::Violated("Not from the identity service".to_string()),
+        });
+
+        let trusted = DischargeContext {
+            index: 0,
+            identifier: b"id",
+            verification_key_id: b"identity_key",
+            location: Some("https://id.example.com"),
+        };
+        let untrusted = DischargeContext {
+            verification_key_id: b"other_key",
+            ..trusted
+        };
+        // The location is not authenticated and does not matter
+        let relocated = DischargeContext {
+            location: Some("https://evil.example.com"),
+            ..trusted
+        };
+
+        let caveat = b"declared user alice";
+        assert!(
+            verifier
+                .check(&CaveatContext::discharge(caveat, 0, trusted))
+                .is_satisfied()
+        );
+        assert!(
+            !verifier
+                .check(&CaveatContext::discharge(caveat, 0, untrusted))
+                .is_satisfied()
+        );
+        assert!(
+            verifier
+                .check(&CaveatContext::discharge(caveat, 0, relocated))
+                .is_satisfied()
+        );
+        assert!(
+            !verifier
+                .check(&CaveatContext::primary(caveat, 0))
+                .is_satisfied()
+        );
+    }
+
+    #[test]
+    fn test_discharge_router() {
+        let auth = DischargeContext {
+            index: 0,
+            identifier: b"login",
+            verification_key_id: b"auth_key",
+            location: Some("https://auth.example.com"),
+        };
+        let billing = DischargeContext {
+            index: 1,
+            identifier: b"plan",
+            verification_key_id: b"billing_key",
+            location: Some("https://billing.example.com"),
+        };
+
+        let verifier = DischargeRouter::new(ContextVerifier::empty().with("action", "read"))
+            .for_verification_key("auth_key", ContextVerifier::empty().with("auth_level", "7"))
+            .for_identifier("plan", ContextVerifier::empty().with("plan", "pro"));
+
+        // Primary caveats use the primary verifier
+        assert!(
+            verifier
+                .check(&CaveatContext::primary(b"action = read", 0))
+                .is_satisfied()
+        );
+        assert!(
+            !verifier
+                .check(&CaveatContext::primary(b"auth_level >= 5", 0))
+                .is_satisfied()
+        );
+
+        // Discharge caveats use the verifier of their issuer
+        assert!(
+            verifier
+                .check(&CaveatContext::discharge(b"auth_level >= 5", 0, auth))
+                .is_satisfied()
+        );
+        assert!(
+            !verifier
+                .check(&CaveatContext::discharge(b"action = read", 0, auth))
+                .is_satisfied()
+        );
+        assert!(
+            verifier
+                .check(&CaveatContext::discharge(b"plan = pro", 0, billing))
+                .is_satisfied()
+        );
+    }
+
+    #[test]
+    fn test_discharge_router_precedence() {
+        let discharge = DischargeContext {
+            index: 0,
+            identifier: b"login",
+            verification_key_id: b"auth_key",
+            location: Some("https://auth.example.com"),
+        };
+        let caveat = CaveatContext::discharge(b"a = b", 0, discharge);
+
+        let verifier = DischargeRouter::new(RejectAllVerifier)
+            .for_location("https://auth.example.com", RejectAllVerifier)
+            .for_verification_key("auth_key", RejectAllVerifier)
+            .for_identifier("login", AcceptAllVerifier);
+        assert!(verifier.check(&caveat).is_satisfied());
+
+        // The verification key is authenticated, the location is not
+        let verifier = DischargeRouter::new(RejectAllVerifier)
+            .for_location("https://auth.example.com", RejectAllVerifier)
+            .for_verification_key("auth_key", AcceptAllVerifier);
+        assert!(verifier.check(&caveat).is_satisfied());
+
+        let verifier = DischargeRouter::new(RejectAllVerifier)
+            .for_location("https://auth.example.com", AcceptAllVerifier);
+        assert!(verifier.check(&caveat).is_satisfied());
+    }
+
+    #[test]
+    fn test_discharge_router_unknown_discharges() {
+        let unknown = DischargeContext {
+            index: 0,
+            identifier: b"other",
+            verification_key_id: b"other_key",
+            location: Some("https://unknown.example.com"),
+        };
+        let caveat = CaveatContext::discharge(b"a = b", 0, unknown);
+
+        // Fails closed by default, even with an accepting primary verifier
+        let verifier = DischargeRouter::new(AcceptAllVerifier);
+        assert!(matches!(
+            verifier.check(&caveat),
+            CaveatOutcome::Violated(_)
+        ));
+
+        let verifier = DischargeRouter::new(RejectAllVerifier).with_fallback(AcceptAllVerifier);
+        assert!(verifier.check(&caveat).is_satisfied());
+
+        let verifier = DischargeRouter::new(AcceptAllVerifier).allow_unknown_discharges();
+        assert!(verifier.check(&caveat).is_satisfied());
+    }
+
+    #[test]
+    fn test_all_of() {
+        let verifier = AllOf::new()
+            .add_verifier(ContextVerifier::empty().with("level", "10"))
+            .add_verifier(ContextVerifier::empty().with("level", "7"));
+
+        assert!(verifier.verify_caveat(b"level >= 5").is_ok());
+        assert!(matches!(
+            verifier.check_caveat(b"level >= 8"),
+            CaveatOutcome::Violated(_)
+        ));
+
+        let partial = AllOf::new()
+            .add_verifier(AcceptAllVerifier)
+            .add_verifier(Prefixed(b"org", true));
+        assert_eq!(
+            partial.check_caveat(b"level >= 5"),
+            CaveatOutcome::Unrecognized
+        );
+        assert!(partial.verify_caveat(b"level >= 5").is_err());
+    }
+
+    #[test]
+    fn test_any_of() {
+        let verifier = AnyOf::new()
+            .add_verifier(ContextVerifier::empty().with("role", "admin"))
+            .add_verifier(ContextVerifier::empty().with("role", "editor"));
+
+        assert!(verifier.verify_caveat(b"role = editor").is_ok());
+        assert!(matches!(
+            verifier.check_caveat(b"role = viewer"),
+            CaveatOutcome::Violated(_)
+        ));
+
+        let unknown = AnyOf::new().add_verifier(Prefixed(b"org", true));
+        assert_eq!(
+            unknown.check_caveat(b"role = admin"),
+            CaveatOutcome::Unrecognized
+        );
+    }
+
+    #[test]
+    fn test_not() {
+        let verifier = Not::new(ContextVerifier::empty().with("account", "mallory"));
+        assert!(verifier.verify_caveat(b"account = mallory").is_err());
+        assert!(verifier.verify_caveat(b"account = alice").is_ok());
+
+        let unknown = Not::new(Prefixed(b"org", true));
+        assert_eq!(
+            unknown.check_caveat(b"account = alice"),
+            CaveatOutcome::Unrecognized
+        );
+        assert!(unknown.verify_caveat(b"account = alice").is_err());
+    }
+
+    #[test]
+    fn test_not_over_missing_key() {
+        for policy in [MissingKeyPolicy::Fail, MissingKeyPolicy::Error] {
+            let verifier = Not::new(ContextVerifier::empty().with_missing_key_policy(policy));
+            assert!(matches!(
+                verifier.check_caveat(b"account = mallory"),
+                CaveatOutcome::Error(_)
+            ));
+            assert!(verifier.verify_caveat(b"account = mallory").is_err());
+        }
+
+        // Presence tests and the null policy give definite answers
+        let verifier = Not::new(ContextVerifier::empty());
+        assert!(verifier.verify_caveat(b"account exists").is_ok());
+        let verifier =
+            Not::new(ContextVerifier::empty().with_missing_key_policy(MissingKeyPolicy::Null));
+        assert!(verifier.verify_caveat(b"account = mallory").is_ok());
+    }
+
+    #[test]
+    fn test_not_over_erroring_verifier() {
+        let failing = || {
+            FnVerifier::new(|_: &[u8]| Err(StroopwafelError::Storage("unavailable".to_string())))
+        };
+        let verifier = Not::new(failing());
+        assert_eq!(
+            verifier.check_caveat(b"org = acme"),
+            CaveatOutcome::Error(StroopwafelError::Storage("unavailable".to_string()))
+        );
+        assert!(matches!(
+            verifier.verify_caveat(b"org = acme"),
+            Err(StroopwafelError::Storage(_))
+        ));
+
+        // Errors are not outvoted by violations either
+        let verifier = Not::new(
+            AnyOf::new()
+                .add_verifier(RejectAllVerifier)
+                .add_verifier(failing()),
+        );
+        assert!(verifier.verify_caveat(b"org = acme").is_err());
+
+        let root_key = b"secret";
+        let mut token = crate::Stroopwafel::new(root_key, b"identifier", None::<String>);
+        token.add_first_party_caveat(b"org = acme");
+        assert!(matches!(
+            token.verify(root_key, &verifier, &[]),
+            Err(StroopwafelError::Storage(_))
+        ));
+    }
+
+    #[test]
+    fn test_first_match() {
+        let verifier = FirstMatch::new()
+            .add_verifier(Prefixed(b"org", false))
+            .add_verifier(Prefixed(b"level", true))
+            .add_verifier(AcceptAllVerifier);
+
+        // Decided by the first verifier recognizing the caveat
+        assert!(verifier.verify_caveat(b"org = acme").is_err());
+        assert!(verifier.verify_caveat(b"level >= 5").is_ok());
+        assert!(verifier.verify_caveat(b"anything").is_ok());
+
+        let verifier = FirstMatch::new().add_verifier(Prefixed(b"org", true));
+        assert_eq!(
+            verifier.check_caveat(b"level >= 5"),
+            CaveatOutcome::Unrecognized
+        );
+        assert!(verifier.verify_caveat(b"level >= 5").is_err());
+    }
+
+    #[test]
+    fn test_empty_combinators_fail_closed() {
+        assert!(AllOf::new().verify_caveat(b"a = b").is_err());
+        assert!(AnyOf::new().verify_caveat(b"a = b").is_err());
+        assert!(FirstMatch::new().verify_caveat(b"a = b").is_err());
+    }
+
+    #[test]
+    fn test_nested_combinators() {
+        let verifier = AllOf::new()
+            .add_verifier(
+                AnyOf::new()
+                    .add_verifier(ContextVerifier::empty().with("account", "alice"))
+                    .add_verifier(ContextVerifier::empty().with("account", "bob")),
+            )
+            .add_verifier(Not::new(
+                ContextVerifier::empty().with("account", "mallory"),
+            ));
+
+        assert!(verifier.verify_caveat(b"account = alice").is_ok());
+        assert!(verifier.verify_caveat(b"account = mallory").is_err());
+        // Holds for alice, but also for mallory
+        assert!(verifier.verify_caveat(b"account != bob").is_err());
+    }
+
+    #[test]
+    fn test_keyed_verifier_routes_by_key() {
+        let verifier = KeyedVerifier::new()
+            .register("account", ContextVerifier::empty().with("account", "alice"))
+            .register("action", ContextVerifier::empty().with("action", "read"));
+
+        assert!(verifier.verify_caveat(b"account = alice").is_ok());
+        assert!(verifier.verify_caveat(b"action = read").is_ok());
+        assert!(verifier.verify_caveat(b"account = bob").is_err());
+        assert!(verifier.verify_caveat(b"action = write").is_err());
+    }
+
+    #[test]
+    fn test_keyed_verifier_rejects_unknown_keys() {
+        let verifier = KeyedVerifier::new().register("account", AcceptAllVerifier);
+
+        assert!(verifier.verify_caveat(b"account = anyone").is_ok());
+
+        let result = verifier.verify_caveat(b"role = admin");
+        assert!(matches!(result, Err(StroopwafelError::CaveatViolation(_))));
+
+        assert!(verifier.verify_caveat(b"").is_err());
+        assert!(
+            KeyedVerifier::new()
+                .verify_caveat(b"account = alice")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_keyed_verifier_checker_isolation() {
+        // A permissive checker for one key cannot satisfy caveats for another
+        let verifier = KeyedVerifier::new()
+            .register("debug", AcceptAllVerifier)
+            .register("account", ContextVerifier::empty().with("account", "alice"));
+
+        assert!(verifier.verify_caveat(b"account = mallory").is_err());
+    }
+
+    #[test]
+    fn test_keyed_verifier_namespaces() {
+        let verifier = KeyedVerifier::new()
+            .register_namespace("bank:", RejectAllVerifier)
+            .register_namespace("bank:savings:", AcceptAllVerifier)
+            .register("bank:audit", AcceptAllVerifier);
+
+        assert!(verifier.verify_caveat(b"bank:balance >= 10").is_err());
+        assert!(verifier.verify_caveat(b"bank:savings:rate < 5").is_ok());
+        assert!(verifier.verify_caveat(b"bank:audit = yes").is_ok());
+        assert!(verifier.verify_caveat(b"shop:cart = 1").is_err());
+    }
+
+    #[test]
+    fn test_keyed_verifier_fallback() {
+        let verifier = KeyedVerifier::new()
+            .register("account", RejectAllVerifier)
+            .with_fallback(AcceptAllVerifier);
+
+        assert!(verifier.verify_caveat(b"anything = goes").is_ok());
+        assert!(verifier.verify_caveat(b"account = alice").is_err());
+    }
+
+    #[test]
+    fn test_keyed_verifier_condition_names() {
+        let verifier = KeyedVerifier::new().register(
+            "declared",
+            FnVerifier::new(|p| {
+                if p == b"declared user alice" {
+                    Ok(())
+                } else {
+                    Err(StroopwafelError::CaveatViolation(
+                        "not declared".to_string(),
+                    ))
+                }
+            }),
+        );
+
+        assert!(verifier.verify_caveat(b"declared user alice").is_ok());
+        assert!(verifier.verify_caveat(b"declared user bob").is_err());
+    }
+
+    #[test]
+    fn test_keyed_verifier_structured_caveats() {
+        use crate::predicate::Operator;
+        use crate::structured::StructuredCaveat;
+
+        let verifier =
+            KeyedVerifier::new().register("count", ContextVerifier::empty().with("count", "5"));
+
+        let ok = StructuredCaveat::new("count", Operator::LessThan, 10).encode();
+        let unknown = StructuredCaveat::new("level", Operator::LessThan, 10).encode();
+
+        assert!(verifier.verify_caveat(&ok).is_ok());
+        assert!(verifier.verify_caveat(&unknown).is_err());
+    }
+
     #[test]
     fn test_context_verifier_basic() {
         let mut context = HashMap::new();
@@ -412,4 +1967,255 @@ mod tests {
         let caveat = format!("time < {future}");
         assert!(verifier.verify_caveat(caveat.as_bytes()).is_ok());
     }
+
+    #[test]
+    fn test_context_verifier_with_manual_clock() {
+        use crate::clock::ManualClock;
+        use std::sync::Arc;
+
+        let clock = Arc::new(ManualClock::at_unix(1_000));
+        let verifier = ContextVerifier::empty().with_clock(Arc::clone(&clock));
+
+        assert!(verifier.verify_caveat(b"time < 1001").is_ok());
+        assert!(verifier.verify_caveat(b"time >= 1000").is_ok());
+
+        clock.advance(Duration::from_secs(1));
+        assert!(verifier.verify_caveat(b"time < 1001").is_err());
+    }
+
+    #[test]
+    fn test_context_verifier_clock_skew() {
+        use crate::clock::ManualClock;
+
+        let verifier = ContextVerifier::empty()
+            .with_clock(ManualClock::at_unix(1_000))
+            .with_clock_skew(Duration::from_secs(30));
+
+        // Expired 20 seconds ago, within tolerance
+        assert!(verifier.verify_caveat(b"time < 980").is_ok());
+        assert!(verifier.verify_caveat(b"time < 960").is_err());
+
+        // Valid from 20 seconds from now, within tolerance
+        assert!(verifier.verify_caveat(b"time >= 1020").is_ok());
+        assert!(verifier.verify_caveat(b"time >= 1040").is_err());
+
+        // Equality is not adjusted
+        assert!(verifier.verify_caveat(b"time = 1000").is_ok());
+    }
+
+    #[test]
+    fn test_context_verifier_clock_before_epoch() {
+        use crate::clock::ManualClock;
+        use std::time::UNIX_EPOCH;
+
+        let clock = ManualClock::new(UNIX_EPOCH - Duration::from_secs(1));
+        let verifier = ContextVerifier::empty().with_clock(clock);
+
+        assert!(matches!(
+            verifier.verify_caveat(b"time < 9999999999"),
+            Err(StroopwafelError::InvalidTime(_))
+        ));
+        assert!(matches!(
+            verifier.check_caveat(b"time < 9999999999"),
+            CaveatOutcome::Error(StroopwafelError::InvalidTime(_))
+        ));
+    }
+
+    #[test]
+    fn test_context_verifier_explicit_time_wins() {
+        use crate::clock::ManualClock;
+
+        let verifier = ContextVerifier::empty()
+            .with_clock(ManualClock::at_unix(1_000))
+            .with("time", "5");
+        assert!(verifier.verify_caveat(b"time < 10").is_ok());
+    }
+
+    #[test]
+    fn test_context_verifier_clock_with_rfc3339_caveats() {
+        use crate::clock::ManualClock;
+        use std::time::UNIX_EPOCH;
+
+        // 2024-12-31T23:59:59.5Z
+        let clock = ManualClock::new(UNIX_EPOCH + Duration::from_millis(1_735_689_599_500));
+        let verifier = ContextVerifier::empty().with_clock(clock);
+
+        assert!(
+            verifier
+                .verify_caveat(b"time < 2025-01-01T00:00:00Z")
+                .is_ok()
+        );
+        assert!(
+            verifier
+                .verify_caveat(b"time < 2025-01-01T01:00:00+01:00")
+                .is_ok()
+        );
+        assert!(
+            verifier
+                .verify_caveat(b"time > 2024-12-31T23:59:59Z")
+                .is_ok()
+        );
+        assert!(
+            verifier
+                .verify_caveat(b"time < 2024-12-31T23:59:59.5Z")
+                .is_err()
+        );
+        assert!(verifier.verify_caveat(b"time < 1735689600").is_ok());
+        assert!(verifier.verify_caveat(b"time < 1735689599").is_err());
+    }
+
+    /// Returns a provider of `value` and a counter of how often it ran
+    fn counting_provider(
+        value: &'static str,
+    ) -> (
+        impl Fn() -> Option<String> + Send + Sync + 'static,
+        std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    ) {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let calls = std::sync::Arc::new(AtomicUsize::new(0));
+        let counter = std::sync::Arc::clone(&calls);
+        let provider = move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Some(value.to_string())
+        };
+        (provider, calls)
+    }
+
+    #[test]
+    fn test_context_provider_runs_only_when_needed() {
+        use std::sync::atomic::Ordering;
+
+        let (provider, calls) = counting_provider("admins,staff");
+        let verifier = ContextVerifier::empty()
+            .with("account", "alice")
+            .with_provider("groups", provider);
+
+        assert!(verifier.verify_caveat(b"account = alice").is_ok());
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        assert!(verifier.verify_caveat(b"groups ^= admins").is_ok());
+        assert!(verifier.verify_caveat(b"groups != nobody").is_ok());
+        assert!(verifier.verify_caveat(b"groups = nobody").is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_context_provider_memoized_across_token_caveats() {
+        use crate::Stroopwafel;
+        use std::sync::atomic::Ordering;
+
+        let root_key = b"secret";
+        let mut token = Stroopwafel::new(root_key, b"identifier", None::<String>);
+        token.add_first_party_caveat(b"level >= 5");
+        token.add_first_party_caveat(b"level <= 10");
+
+        let (provider, calls) = counting_provider("7");
+        let verifier = ContextVerifier::empty().with_provider("level", provider);
+
+        assert!(token.verify(root_key, &verifier, &[]).is_ok());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_context_provider_reset() {
+        use crate::Stroopwafel;
+        use std::sync::atomic::Ordering;
+
+        let root_key = b"secret";
+        let mut token = Stroopwafel::new(root_key, b"identifier", None::<String>);
+        token.add_first_party_caveat(b"level >= 5");
+
+        let (provider, calls) = counting_provider("7");
+        let verifier = ContextVerifier::empty()
+            .with("account", "alice")
+            .with_provider("level", provider);
+
+        assert!(token.verify(root_key, &verifier, &[]).is_ok());
+        assert!(token.verify(root_key, &verifier, &[]).is_ok());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        verifier.reset();
+        assert!(token.verify(root_key, &verifier, &[]).is_ok());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // Explicit values are not memoized and survive a reset
+        assert!(verifier.verify_caveat(b"account = alice").is_ok());
+    }
+
+    #[test]
+    fn test_context_provider_without_value() {
+        let verifier = ContextVerifier::empty().with_provider("org", || None);
+        assert!(verifier.verify_caveat(b"org = acme").is_err());
+        assert!(verifier.verify_caveat(b"org != acme").is_err());
+    }
+
+    #[test]
+    fn test_context_with_lazy_and_replacement() {
+        let verifier = ContextVerifier::empty().with_lazy("level", || 7.to_string());
+        assert!(verifier.verify_caveat(b"level > 5").is_ok());
+
+        // Later values replace earlier ones, whichever kind they are
+        let verifier = ContextVerifier::empty()
+            .with_lazy("account", || "alice")
+            .with("account", "bob");
+        assert!(verifier.verify_caveat(b"account = bob").is_ok());
+
+        let verifier = ContextVerifier::empty()
+            .with("account", "bob")
+            .with_lazy("account", || "alice");
+        assert!(verifier.verify_caveat(b"account = alice").is_ok());
+    }
+
+    #[test]
+    fn test_context_provider_trait_object() {
+        struct Membership;
+
+        impl ContextProvider for Membership {
+            fn provide(&self) -> Option<String> {
+                Some("acme".to_string())
+            }
+        }
+
+        let verifier = ContextVerifier::empty().with_provider("org", Membership);
+        assert!(verifier.verify_caveat(b"org = acme").is_ok());
+    }
+
+    #[test]
+    fn test_context_missing_key_policy() {
+        let verifier = ContextVerifier::empty();
+        assert!(verifier.verify_caveat(b"role != admin").is_err());
+        assert!(verifier.verify_caveat(b"role !exists").is_ok());
+
+        let verifier = ContextVerifier::empty().with_missing_key_policy(MissingKeyPolicy::Null);
+        assert!(verifier.verify_caveat(b"role != admin").is_ok());
+        assert!(verifier.verify_caveat(b"role = admin").is_err());
+
+        let verifier = ContextVerifier::empty()
+            .with("tier", "gold")
+            .with_missing_key_policy(MissingKeyPolicy::Error);
+        assert!(verifier.verify_caveat(b"tier = gold").is_ok());
+        assert!(verifier.verify_caveat(b"tier exists").is_ok());
+        assert!(verifier.verify_caveat(b"role exists").is_err());
+        assert!(matches!(
+            verifier.verify_caveat(b"role = admin"),
+            Err(StroopwafelError::MissingContext { key }) if key == "role"
+        ));
+        assert_eq!(
+            verifier.check_caveat(b"role = admin"),
+            CaveatOutcome::Error(StroopwafelError::MissingContext {
+                key: "role".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_context_provider_satisfies_exists() {
+        let verifier = ContextVerifier::empty()
+            .with_lazy("session", || "abc")
+            .with_provider("user", || None);
+        assert!(verifier.verify_caveat(b"session exists").is_ok());
+        assert!(verifier.verify_caveat(b"user !exists").is_ok());
+        assert!(verifier.verify_caveat(b"user exists").is_err());
+    }
 }
@@ -0,0 +1,734 @@
+use crate::caveat::CaveatParts;
+use crate::crypto::SIGNATURE_SIZE;
+use crate::options::VerifyOptions;
+use crate::stroopwafel::Token;
+use crate::verifier::CaveatVerifier;
+use crate::{Caveat, Result, Stroopwafel, StroopwafelError};
+use rmp_serde::decode::Error as DecodeError;
+use std::borrow::Cow;
+use std::io;
+
+/// A stroopwafel borrowed from its MessagePack encoding
+///
+/// [`Stroopwafel::from_msgpack`] copies every identifier and caveat into its
+/// own `Vec<u8>`, and every location into a `String`. A view decodes the same
+/// bytes but borrows from the input buffer wherever the encoding allows, so
+/// a token can be verified straight off the wire. [`Stroopwafel::to_msgpack`]
+/// encodes byte strings as arrays of integers, which are borrowed when every
+/// byte is below `0x80` (e.g. text predicates) and copied otherwise; byte
+/// strings encoded as MessagePack binary are always borrowed.
+///
+/// Views decode any input [`Stroopwafel::from_msgpack`] accepts into the
+/// same token, and verify with the same results.
+///
+/// # Example
+/// ```
+/// use stroopwafel::Stroopwafel;
+/// use stroopwafel::verifier::ContextVerifier;
+/// use stroopwafel::view::StroopwafelRef;
+///
+/// let root_key = b"secret";
+/// let mut token = Stroopwafel::new(root_key, b"identifier", None::<String>);
+/// token.add_first_party_caveat(b"account = alice");
+/// let bytes = token.to_msgpack().unwrap();
+///
+/// let view = StroopwafelRef::from_msgpack(&bytes).unwrap();
+/// let verifier = ContextVerifier::empty().with("account", "alice");
+/// assert!(view.verify(root_key, &verifier, &[]).is_ok());
+/// assert_eq!(view.into_owned(), token);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StroopwafelRef<'a> {
+    /// Optional location hint for the target service, not verified
+    pub location: Option<&'a str>,
+
+    /// Public identifier for this stroopwafel
+    pub identifier: Cow<'a, [u8]>,
+
+    /// List of caveats (restrictions) attached to this stroopwafel
+    pub caveats: Vec<CaveatRef<'a>>,
+
+    /// HMAC-SHA3-256 signature (32 bytes)
+    pub signature: [u8; SIGNATURE_SIZE],
+}
+
+/// A caveat borrowed from the encoding of a [`StroopwafelRef`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaveatRef<'a> {
+    /// The caveat identifier (the predicate or condition)
+    pub caveat_id: Cow<'a, [u8]>,
+
+    /// Optional verification key identifier (for third-party caveats)
+    pub verification_key_id: Option<Cow<'a, [u8]>>,
+
+    /// Optional location hint for this caveat, not verified
+    pub location: Option<&'a str>,
+}
+
+impl<'a> StroopwafelRef<'a> {
+    /// Decodes a view of a stroopwafel from MessagePack, borrowing from `data`
+    ///
+    /// # Errors
+    /// Returns `StroopwafelError::Decode` for input
+    /// [`Stroopwafel::from_msgpack`] rejects.
+    pub fn from_msgpack(data: &'a [u8]) -> Result<Self> {
+        Reader { rest: data }
+            .stroopwafel()
+            .map_err(|e| StroopwafelError::decode("MessagePack", e))
+    }
+
+    /// Returns the number of caveats in this stroopwafel
+    pub fn caveat_count(&self) -> usize {
+        self.caveats.len()
+    }
+
+    /// Copies the view into an owned stroopwafel
+    pub fn into_owned(self) -> Stroopwafel {
+        Stroopwafel {
+            location: self.location.map(str::to_string),
+            identifier: self.identifier.into_owned(),
+            caveats: self
+                .caveats
+                .into_iter()
+                .map(CaveatRef::into_owned)
+                .collect(),
+            signature: self.signature,
+        }
+    }
+
+    /// Verifies this stroopwafel like [`Stroopwafel::verify`]
+    pub fn verify(
+        &self,
+        root_key: &[u8],
+        verifier: &impl CaveatVerifier,
+        discharges: &[StroopwafelRef<'_>],
+    ) -> Result<()> {
+        self.verify_with_options(root_key, verifier, discharges, &VerifyOptions::default())
+    }
+
+    /// Verifies this stroopwafel like [`Stroopwafel::verify_with_options`]
+    pub fn verify_with_options(
+        &self,
+        root_key: &[u8],
+        verifier: &impl CaveatVerifier,
+        discharges: &[StroopwafelRef<'_>],
+        options: &VerifyOptions,
+    ) -> Result<()> {
+        self.verify_token(root_key, verifier, discharges, options)
+    }
+}
+
+impl CaveatRef<'_> {
+    /// Copies the view into an owned caveat
+    pub fn into_owned(self) -> Caveat {
+        Caveat {
+            caveat_id: self.caveat_id.into_owned(),
+            verification_key_id: self.verification_key_id.map(Cow::into_owned),
+            location: self.location.map(str::to_string),
+        }
+    }
+
+    fn parts(&self) -> CaveatParts<'_> {
+        CaveatParts {
+            caveat_id: &self.caveat_id,
+            verification_key_id: self.verification_key_id.as_deref(),
+            location: self.location,
+        }
+    }
+}
+
+impl<'a> From<&'a Stroopwafel> for StroopwafelRef<'a> {
+    fn from(stroopwafel: &'a Stroopwafel) -> Self {
+        Self {
+            location: stroopwafel.location.as_deref(),
+            identifier: Cow::Borrowed(&stroopwafel.identifier),
+            caveats: stroopwafel.caveats.iter().map(CaveatRef::from).collect(),
+            signature: stroopwafel.signature,
+        }
+    }
+}
+
+impl<'a> From<&'a Caveat> for CaveatRef<'a> {
+    fn from(caveat: &'a Caveat) -> Self {
+        Self {
+            caveat_id: Cow::Borrowed(&caveat.caveat_id),
+            verification_key_id: caveat.verification_key_id.as_deref().map(Cow::Borrowed),
+            location: caveat.location.as_deref(),
+        }
+    }
+}
+
+impl Token for StroopwafelRef<'_> {
+    fn identifier(&self) -> &[u8] {
+        &self.identifier
+    }
+
+    fn signature(&self) -> &[u8; SIGNATURE_SIZE] {
+        &self.signature
+    }
+
+    fn caveat_count(&self) -> usize {
+        self.caveats.len()
+    }
+
+    fn caveat_parts(&self) -> impl Iterator<Item = CaveatParts<'_>> {
+        self.caveats.iter().map(CaveatRef::parts)
+    }
+}
+
+const STROOPWAFEL_FIELDS: [&str; 4] = ["location", "identifier", "caveats", "signature"];
+const CAVEAT_FIELDS: [&str; 3] = ["caveat_id", "verification_key_id", "location"];
+
+/// How a struct is encoded: as an array of its fields in order, or as a map
+/// from field names (or indices) to fields
+enum Layout {
+    Array(u32),
+    Map(u32),
+}
+
+/// How a sequence is encoded: as an array of values, or as binary, which
+/// sequences of integers also accept
+enum Sequence<'a> {
+    Array(u32),
+    Binary(&'a [u8]),
+}
+
+/// Decodes the MessagePack layout `rmp_serde` gives the owned types,
+/// borrowing from the input
+struct Reader<'a> {
+    rest: &'a [u8],
+}
+
+type Decoded<T> = std::result::Result<T, DecodeError>;
+
+impl<'a> Reader<'a> {
+    fn stroopwafel(&mut self) -> Decoded<StroopwafelRef<'a>> {
+        let mut location = None;
+        let mut identifier = None;
+        let mut caveats = None;
+        let mut signature = None;
+
+        self.fields(&STROOPWAFEL_FIELDS, |reader, field| {
+            match field {
+                0 => location = Some(reader.optional(Self::str)?),
+                1 => identifier = Some(reader.bytes()?),
+                2 => caveats = Some(reader.caveats()?),
+                _ => signature = Some(reader.signature()?),
+            }
+            Ok(())
+        })?;
+
+        Ok(StroopwafelRef {
+            location: location.flatten(),
+            identifier: identifier.ok_or_else(|| missing("identifier"))?,
+            caveats: caveats.ok_or_else(|| missing("caveats"))?,
+            signature: signature.ok_or_else(|| missing("signature"))?,
+        })
+    }
+
+    fn caveats(&mut self) -> Decoded<Vec<CaveatRef<'a>>> {
+        let len = match self.sequence()? {
+            Sequence::Array(len) => len,
+            Sequence::Binary([]) => 0,
+            Sequence::Binary(_) => return Err(mismatch("a caveat")),
+        };
+        // Every caveat takes at least one byte
+        let mut caveats = Vec::with_capacity((len as usize).min(self.rest.len()));
+        for _ in 0..len {
+            caveats.push(self.caveat()?);
+        }
+        Ok(caveats)
+    }
+
+    fn caveat(&mut self) -> Decoded<CaveatRef<'a>> {
+        let mut caveat_id = None;
+        let mut verification_key_id = None;
+        let mut location = None;
+
+        self.fields(&CAVEAT_FIELDS, |reader, field| {
+            match field {
+                0 => caveat_id = Some(reader.bytes()?),
+                1 => verification_key_id = Some(reader.optional(Self::bytes)?),
+                _ => location = Some(reader.optional(Self::str)?),
+            }
+            Ok(())
+        })?;
+
+        Ok(CaveatRef {
+            caveat_id: caveat_id.ok_or_else(|| missing("caveat_id"))?,
+            verification_key_id: verification_key_id.flatten(),
+            location: location.flatten(),
+        })
+    }
+
+    /// Reads the fields of a struct, calling `field` with the index of each
+    /// known field; unknown map entries are skipped
+    fn fields(
+        &mut self,
+        names: &[&str],
+        mut field: impl FnMut(&mut Self, usize) -> Decoded<()>,
+    ) -> Decoded<()> {
+        match self.layout()? {
+            Layout::Array(len) => {
+                if len as usize != names.len() {
+                    return Err(DecodeError::LengthMismatch(len));
+                }
+                for index in 0..names.len() {
+                    field(self, index)?;
+                }
+            }
+            Layout::Map(len) => {
+                let mut seen = vec![false; names.len()];
+                for _ in 0..len {
+                    match self.field_index(names)? {
+                        Some(index) if seen[index] => {
+                            return Err(DecodeError::Syntax(format!(
+                                "duplicate field `{}`",
+                                names[index]
+                            )));
+                        }
+                        Some(index) => {
+                            seen[index] = true;
+                            field(self, index)?;
+                        }
+                        None => self.skip()?,
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a map key naming a field, by name or by index
+    ///
+    /// Indices must use an unsigned integer encoding, as with `rmp_serde`.
+    fn field_index(&mut self, names: &[&str]) -> Decoded<Option<usize>> {
+        let index = match self.peek()? {
+            0xa0..=0xbf | 0xd9..=0xdb | 0xc4..=0xc6 => {
+                let name = self.raw_str()?;
+                names.iter().position(|n| n.as_bytes() == name)
+            }
+            0x00..=0x7f | 0xcc..=0xcf => {
+                let index = self.integer()?;
+                usize::try_from(index).ok().filter(|&i| i < names.len())
+            }
+            _ => return Err(mismatch("a field name")),
+        };
+        Ok(index)
+    }
+
+    fn layout(&mut self) -> Decoded<Layout> {
+        match self.marker()? {
+            m @ 0x90..=0x9f => Ok(Layout::Array(u32::from(m & 0x0f))),
+            0xdc => Ok(Layout::Array(self.uint(2)? as u32)),
+            0xdd => Ok(Layout::Array(self.uint(4)? as u32)),
+            m @ 0x80..=0x8f => Ok(Layout::Map(u32::from(m & 0x0f))),
+            0xde => Ok(Layout::Map(self.uint(2)? as u32)),
+            0xdf => Ok(Layout::Map(self.uint(4)? as u32)),
+            _ => Err(mismatch("a struct")),
+        }
+    }
+
+    fn sequence(&mut self) -> Decoded<Sequence<'a>> {
+        let len = match self.peek()? {
+            0xc4 => self.uint_after_marker(1)?,
+            0xc5 => self.uint_after_marker(2)?,
+            0xc6 => self.uint_after_marker(4)?,
+            _ => match self.layout()? {
+                Layout::Array(len) => return Ok(Sequence::Array(len)),
+                Layout::Map(_) => return Err(mismatch("an array")),
+            },
+        };
+        self.take(len).map(Sequence::Binary)
+    }
+
+    /// Reads `nil` as `None`, anything else with `read`
+    fn optional<T>(&mut self, read: impl FnOnce(&mut Self) -> Decoded<T>) -> Decoded<Option<T>> {
+        if self.peek()? == 0xc0 {
+            self.rest = &self.rest[1..];
+            return Ok(None);
+        }
+        read(self).map(Some)
+    }
+
+    /// Reads a byte string, encoded as binary or as an array of integers
+    fn bytes(&mut self) -> Decoded<Cow<'a, [u8]>> {
+        let len = match self.sequence()? {
+            Sequence::Array(len) => len as usize,
+            Sequence::Binary(raw) => return Ok(Cow::Borrowed(raw)),
+        };
+
+        // Bytes below 0x80 are encoded as themselves (positive fixints)
+        if let Some(raw) = self.rest.get(..len)
+            && raw.iter().all(|&b| b < 0x80)
+        {
+            self.rest = &self.rest[len..];
+            return Ok(Cow::Borrowed(raw));
+        }
+
+        let mut bytes = Vec::with_capacity(len.min(self.rest.len()));
+        for _ in 0..len {
+            bytes.push(self.byte()?);
+        }
+        Ok(Cow::Owned(bytes))
+    }
+
+    fn signature(&mut self) -> Decoded<[u8; SIGNATURE_SIZE]> {
+        let len = match self.sequence()? {
+            Sequence::Array(len) => len,
+            Sequence::Binary(raw) => {
+                return raw.try_into().map_err(|_| mismatch("32 signature bytes"));
+            }
+        };
+        if len as usize != SIGNATURE_SIZE {
+            return Err(DecodeError::LengthMismatch(len));
+        }
+        let mut signature = [0; SIGNATURE_SIZE];
+        for byte in &mut signature {
+            *byte = self.byte()?;
+        }
+        Ok(signature)
+    }
+
+    fn byte(&mut self) -> Decoded<u8> {
+        u8::try_from(self.integer()?).map_err(|_| DecodeError::OutOfRange)
+    }
+
+    fn str(&mut self) -> Decoded<&'a str> {
+        std::str::from_utf8(self.raw_str()?).map_err(DecodeError::Utf8Error)
+    }
+
+    /// Reads the bytes of a string (or binary, which strings also accept)
+    fn raw_str(&mut self) -> Decoded<&'a [u8]> {
+        let len = match self.marker()? {
+            m @ 0xa0..=0xbf => u64::from(m & 0x1f),
+            0xd9 | 0xc4 => self.uint(1)?,
+            0xda | 0xc5 => self.uint(2)?,
+            0xdb | 0xc6 => self.uint(4)?,
+            _ => return Err(mismatch("a string")),
+        };
+        self.take(len)
+    }
+
+    /// Reads any integer
+    fn integer(&mut self) -> Decoded<i128> {
+        let value = match self.marker()? {
+            m @ 0x00..=0x7f => i128::from(m),
+            m @ 0xe0..=0xff => i128::from(m as i8),
+            0xcc => i128::from(self.uint(1)?),
+            0xcd => i128::from(self.uint(2)?),
+            0xce => i128::from(self.uint(4)?),
+            0xcf => i128::from(self.uint(8)?),
+            0xd0 => i128::from(self.uint(1)? as u8 as i8),
+            0xd1 => i128::from(self.uint(2)? as u16 as i16),
+            0xd2 => i128::from(self.uint(4)? as u32 as i32),
+            0xd3 => i128::from(self.uint(8)? as i64),
+            _ => return Err(mismatch("an integer")),
+        };
+        Ok(value)
+    }
+
+    /// Skips one value of any type
+    fn skip(&mut self) -> Decoded<()> {
+        let mut pending: u64 = 1;
+        while pending > 0 {
+            pending -= 1;
+            let skip = match self.marker()? {
+                0x00..=0x7f | 0xe0..=0xff | 0xc0 | 0xc2 | 0xc3 => 0,
+                m @ 0x80..=0x8f => {
+                    pending += 2 * u64::from(m & 0x0f);
+                    0
+                }
+                m @ 0x90..=0x9f => {
+                    pending += u64::from(m & 0x0f);
+                    0
+                }
+                m @ 0xa0..=0xbf => u64::from(m & 0x1f),
+                0xc4 | 0xd9 => self.uint(1)?,
+                0xc5 | 0xda => self.uint(2)?,
+                0xc6 | 0xdb => self.uint(4)?,
+                0xc7 => self.uint(1)? + 1,
+                0xc8 => self.uint(2)? + 1,
+                0xc9 => self.uint(4)? + 1,
+                0xcc | 0xd0 => 1,
+                0xcd | 0xd1 => 2,
+                0xca | 0xce | 0xd2 => 4,
+                0xcb | 0xcf | 0xd3 => 8,
+                0xd4 => 2,
+                0xd5 => 3,
+                0xd6 => 5,
+                0xd7 => 9,
+                0xd8 => 17,
+                0xdc => {
+                    pending += self.uint(2)?;
+                    0
+                }
+                0xdd => {
+                    pending += self.uint(4)?;
+                    0
+                }
+                0xde => {
+                    pending += 2 * self.uint(2)?;
+                    0
+                }
+                0xdf => {
+                    pending += 2 * self.uint(4)?;
+                    0
+                }
+                _ => return Err(mismatch("a value")),
+            };
+            self.take(skip)?;
+            // Every value takes at least one byte
+            if pending > self.rest.len() as u64 {
+                return Err(eof(DecodeError::InvalidMarkerRead));
+            }
+        }
+        Ok(())
+    }
+
+    fn peek(&self) -> Decoded<u8> {
+        self.rest
+            .first()
+            .copied()
+            .ok_or_else(|| eof(DecodeError::InvalidMarkerRead))
+    }
+
+    fn marker(&mut self) -> Decoded<u8> {
+        let marker = self.peek()?;
+        self.rest = &self.rest[1..];
+        Ok(marker)
+    }
+
+    /// Skips a marker and reads the big-endian length of `size` bytes after it
+    fn uint_after_marker(&mut self, size: u64) -> Decoded<u64> {
+        self.marker()?;
+        self.uint(size)
+    }
+
+    /// Reads a big-endian unsigned integer of `size` bytes
+    fn uint(&mut self, size: u64) -> Decoded<u64> {
+        Ok(self
+            .take(size)?
+            .iter()
+            .fold(0, |value, &b| (value << 8) | u64::from(b)))
+    }
+
+    fn take(&mut self, len: u64) -> Decoded<&'a [u8]> {
+        let len = usize::try_from(len)
+            .ok()
+            .filter(|&len| len <= self.rest.len())
+            .ok_or_else(|| eof(DecodeError::InvalidDataRead))?;
+        let (taken, rest) = self.rest.split_at(len);
+        self.rest = rest;
+        Ok(taken)
+    }
+}
+
+fn eof(error: fn(io::Error) -> DecodeError) -> DecodeError {
+    error(io::ErrorKind::UnexpectedEof.into())
+}
+
+fn mismatch(expected: &str) -> DecodeError {
+    DecodeError::Syntax(format!("expected {expected}"))
+}
+
+fn missing(field: &str) -> DecodeError {
+    DecodeError::Syntax(format!("missing field `{field}`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::UnrecognizedPolicy;
+    use crate::verifier::{AcceptAllVerifier, ContextVerifier};
+    use serde::Serialize;
+
+    const ROOT_KEY: &[u8] = b"root_secret";
+
+    fn token() -> Stroopwafel {
+        let mut token = Stroopwafel::new(ROOT_KEY, b"identifier", Some("https://example.com"));
+        token.add_first_party_caveat(b"account = alice");
+        token.add_first_party_caveat(vec![0x00, 0x7f, 0x80, 0xff]);
+        token.add_third_party_caveat(b"auth", b"verification_secret", "https://auth.example.com");
+        token
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let token = token();
+        let bytes = token.to_msgpack().unwrap();
+        let view = StroopwafelRef::from_msgpack(&bytes).unwrap();
+
+        assert_eq!(view, StroopwafelRef::from(&token));
+        assert_eq!(view.into_owned(), token);
+    }
+
+    #[test]
+    fn test_borrows_ascii_bytes() {
+        let token = token();
+        let bytes = token.to_msgpack().unwrap();
+        let view = StroopwafelRef::from_msgpack(&bytes).unwrap();
+
+        assert!(matches!(view.identifier, Cow::Borrowed(_)));
+        assert!(matches!(view.caveats[0].caveat_id, Cow::Borrowed(_)));
+        // Bytes from 0x80 take two bytes each in the encoding
+        assert!(matches!(view.caveats[1].caveat_id, Cow::Owned(_)));
+    }
+
+    #[test]
+    fn test_named_fields() {
+        let token = token();
+        let bytes = rmp_serde::to_vec_named(&token).unwrap();
+        let view = StroopwafelRef::from_msgpack(&bytes).unwrap();
+        assert_eq!(view.into_owned(), token);
+
+        // Unknown fields are skipped and missing locations default to none
+        #[derive(Serialize)]
+        struct Extended<'a> {
+            extra: (Vec<u32>, f64, Option<&'a str>),
+            identifier: &'a [u8],
+            caveats: Vec<u8>,
+            signature: [u8; SIGNATURE_SIZE],
+        }
+        let extended = Extended {
+            extra: (vec![1, 70_000], 0.5, Some("ignored")),
+            identifier: b"identifier",
+            caveats: vec![],
+            signature: [7; SIGNATURE_SIZE],
+        };
+        let bytes = rmp_serde::to_vec_named(&extended).unwrap();
+        let owned = Stroopwafel::from_msgpack(&bytes).unwrap();
+        let view = StroopwafelRef::from_msgpack(&bytes).unwrap();
+        assert_eq!(view.into_owned(), owned);
+    }
+
+    #[test]
+    fn test_binary_fields() {
+        /// Serializes as MessagePack binary rather than an array
+        struct Binary<'a>(&'a [u8]);
+
+        impl Serialize for Binary<'_> {
+            fn serialize<S: serde::Serializer>(
+                &self,
+                serializer: S,
+            ) -> std::result::Result<S::Ok, S::Error> {
+                serializer.serialize_bytes(self.0)
+            }
+        }
+
+        let token = token();
+        let encoded = (
+            token.location.as_deref(),
+            Binary(&token.identifier),
+            token
+                .caveats
+                .iter()
+                .map(|c| {
+                    (
+                        Binary(&c.caveat_id),
+                        c.verification_key_id.as_deref().map(Binary),
+                        c.location.as_deref(),
+                    )
+                })
+                .collect::<Vec<_>>(),
+            Binary(&token.signature),
+        );
+        let bytes = rmp_serde::to_vec(&encoded).unwrap();
+
+        let view = StroopwafelRef::from_msgpack(&bytes).unwrap();
+        assert!(matches!(view.caveats[1].caveat_id, Cow::Borrowed(_)));
+        assert_eq!(
+            view.into_owned(),
+            Stroopwafel::from_msgpack(&bytes).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_signature_header_corrupted_to_binary() {
+        // The signature is encoded as an array of 32 integers. Corrupting its
+        // array16 header (dc 00 20) into bin16 (c5 00 20) leaves an encoding
+        // the owned decoder accepts, reading the next 32 raw bytes as the
+        // signature and ignoring the rest.
+        let mut bytes = token().to_msgpack().unwrap();
+        let header = bytes
+            .windows(3)
+            .rposition(|window| window == [0xdc, 0x00, 0x20])
+            .unwrap();
+        bytes[header] = 0xc5;
+
+        let owned = Stroopwafel::from_msgpack(&bytes).unwrap();
+        assert_eq!(owned.signature[..], bytes[header + 3..header + 35]);
+        let view = StroopwafelRef::from_msgpack(&bytes).unwrap();
+        assert_eq!(view.into_owned(), owned);
+
+        // Binary of any other length is not a signature
+        bytes[header + 2] = 0x1f;
+        assert!(Stroopwafel::from_msgpack(&bytes).is_err());
+        assert!(StroopwafelRef::from_msgpack(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_rejects_what_owned_rejects() {
+        let bytes = token().to_msgpack().unwrap();
+        let mut inputs = vec![
+            vec![],
+            vec![0xc0],
+            rmp_serde::to_vec(&("identifier", 1, 2)).unwrap(),
+            rmp_serde::to_vec_named(&std::collections::HashMap::from([("location", "x")])).unwrap(),
+        ];
+        for len in [1, bytes.len() / 2, bytes.len() - 1] {
+            inputs.push(bytes[..len].to_vec());
+        }
+
+        for input in inputs {
+            assert!(Stroopwafel::from_msgpack(&input).is_err());
+            assert!(matches!(
+                StroopwafelRef::from_msgpack(&input),
+                Err(StroopwafelError::Decode(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_verify_matches_owned() {
+        let verification_key = b"verification_secret";
+        let primary = token();
+        let mut discharge =
+            Stroopwafel::create_discharge(verification_key, b"auth", None::<String>);
+        discharge.add_first_party_caveat(b"account = alice");
+        let discharge = primary.bind_discharge(&discharge);
+
+        let primary_bytes = primary.to_msgpack().unwrap();
+        let discharge_bytes = discharge.to_msgpack().unwrap();
+        let view = StroopwafelRef::from_msgpack(&primary_bytes).unwrap();
+        let discharge_views = [StroopwafelRef::from_msgpack(&discharge_bytes).unwrap()];
+        let discharges = [discharge];
+
+        let alice = ContextVerifier::empty().with("account", "alice");
+        let bob = ContextVerifier::empty().with("account", "bob");
+        for options in [
+            VerifyOptions::new(),
+            VerifyOptions::new().with_unrecognized_policy(UnrecognizedPolicy::Accept),
+        ] {
+            for verifier in [&alice, &bob] {
+                assert_eq!(
+                    view.verify_with_options(ROOT_KEY, verifier, &discharge_views, &options),
+                    primary.verify_with_options(ROOT_KEY, verifier, &discharges, &options)
+                );
+                assert_eq!(
+                    view.verify_with_options(ROOT_KEY, verifier, &[], &options),
+                    primary.verify_with_options(ROOT_KEY, verifier, &[], &options)
+                );
+            }
+        }
+
+        assert!(
+            view.verify(ROOT_KEY, &AcceptAllVerifier, &discharge_views)
+                .is_ok()
+        );
+        assert_eq!(
+            view.verify(b"wrong", &AcceptAllVerifier, &discharge_views),
+            Err(StroopwafelError::InvalidSignature)
+        );
+    }
+}
@@ -1,5 +1,11 @@
 use proptest::prelude::*;
-use stroopwafel::{Stroopwafel, verifier::AcceptAllVerifier};
+use stroopwafel::{
+    Stroopwafel,
+    predicate::{Operator, Predicate},
+    timestamp::Timestamp,
+    verifier::AcceptAllVerifier,
+    view::StroopwafelRef,
+};
 
 // Configuration for crypto library: run many more cases than default (100)
 // For security-critical code, we want extensive coverage
@@ -196,6 +202,57 @@ fn prop_msgpack_roundtrip() {
     });
 }
 
+/// Property: A borrowed view decodes and verifies like the owned stroopwafel
+#[test]
+fn prop_view_matches_owned() {
+    let config = proptest_config();
+    proptest!(config, |(
+        root_key in prop::collection::vec(any::<u8>(), 1..128),
+        identifier in prop::collection::vec(any::<u8>(), 1..128),
+        caveats in prop::collection::vec(prop::collection::vec(any::<u8>(), 1..64), 0..5)
+    )| {
+        let mut original = Stroopwafel::new(&root_key, identifier, Some("https://example.com"));
+
+        for caveat in caveats {
+            original.add_first_party_caveat(caveat);
+        }
+
+        let msgpack = original.to_msgpack().unwrap();
+        let view = StroopwafelRef::from_msgpack(&msgpack).unwrap();
+
+        prop_assert!(view.verify(&root_key, &AcceptAllVerifier, &[]).is_ok());
+        prop_assert_eq!(view.into_owned(), original);
+    });
+}
+
+/// Property: A borrowed view accepts exactly the encodings the owned
+/// stroopwafel accepts
+#[test]
+fn prop_view_rejects_like_owned() {
+    let config = proptest_config();
+    proptest!(config, |(
+        identifier in prop::collection::vec(any::<u8>(), 1..32),
+        caveats in prop::collection::vec(prop::collection::vec(any::<u8>(), 1..16), 0..3),
+        position in any::<prop::sample::Index>(),
+        byte in any::<u8>()
+    )| {
+        let mut original = Stroopwafel::new(b"key", identifier, Some("https://example.com"));
+
+        for caveat in caveats {
+            original.add_first_party_caveat(caveat);
+        }
+
+        // Corrupt one byte of a valid encoding
+        let mut msgpack = original.to_msgpack().unwrap();
+        let index = position.index(msgpack.len());
+        msgpack[index] = byte;
+
+        let owned = Stroopwafel::from_msgpack(&msgpack).ok();
+        let view = StroopwafelRef::from_msgpack(&msgpack).ok().map(StroopwafelRef::into_owned);
+        prop_assert_eq!(view, owned);
+    });
+}
+
 /// Property: Hex serialization roundtrip should preserve the stroopwafel
 #[test]
 fn prop_hex_roundtrip() {
@@ -304,3 +361,100 @@ fn prop_caveat_count() {
         }
     });
 }
+
+fn operator_strategy() -> impl Strategy<Value = Operator> {
+    prop_oneof![
+        Just(Operator::Equal),
+        Just(Operator::NotEqual),
+        Just(Operator::LessThan),
+        Just(Operator::GreaterThan),
+        Just(Operator::LessThanOrEqual),
+        Just(Operator::GreaterThanOrEqual),
+        Just(Operator::In),
+        Just(Operator::StartsWith),
+        Just(Operator::InSubnet),
+        Just(Operator::Exists),
+        Just(Operator::NotExists),
+    ]
+}
+
+/// Property: Displaying an operator and parsing it back yields the same operator
+#[test]
+fn prop_operator_display_roundtrip() {
+    let config = proptest_config();
+    proptest!(config, |(operator in operator_strategy())| {
+        let parsed: Operator = operator.to_string().parse().unwrap();
+        prop_assert_eq!(parsed, operator);
+    });
+}
+
+/// Property: parse(display(p)) == p for any well-formed predicate
+#[test]
+fn prop_predicate_display_roundtrip() {
+    let config = proptest_config();
+    proptest!(config, |(
+        key in "[A-Za-z_][A-Za-z0-9_.:-]{0,15}",
+        operator in operator_strategy(),
+        value in "\\S(.{0,30}\\S)?"
+    )| {
+        // Presence operators take no value
+        let value = if operator.is_unary() { String::new() } else { value };
+        let predicate = Predicate { key, operator, value };
+
+        let displayed = predicate.to_string();
+        let parsed = Predicate::parse(&displayed).unwrap();
+        prop_assert_eq!(&parsed, &predicate);
+
+        // Display is canonical: a second round trip is stable
+        prop_assert_eq!(parsed.to_string(), displayed);
+
+        // Serde uses the same canonical form
+        let json = serde_json::to_string(&predicate).unwrap();
+        let deserialized: Predicate = serde_json::from_str(&json).unwrap();
+        prop_assert_eq!(deserialized, predicate);
+    });
+}
+
+/// Property: an instant written as Unix seconds and as RFC 3339 UTC parses to
+/// the same timestamp, and Display round-trips
+#[test]
+fn prop_timestamp_formats_agree() {
+    let config = proptest_config();
+    proptest!(config, |(
+        seconds in 0i64..253_402_300_800, // up to 9999-12-31
+        nanos in 0u32..1_000_000_000
+    )| {
+        let unix = Timestamp::parse(&format!("{seconds}.{nanos:09}")).unwrap();
+        prop_assert_eq!(
+            unix.unix_nanos(),
+            i128::from(seconds) * 1_000_000_000 + i128::from(nanos)
+        );
+        prop_assert_eq!(Timestamp::parse(&unix.to_string()), Some(unix));
+
+        let days = seconds.div_euclid(86_400);
+        let secs_of_day = seconds.rem_euclid(86_400);
+        let (year, month, day) = civil_from_days(days);
+        let rfc3339 = format!(
+            "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{nanos:09}Z",
+            secs_of_day / 3600,
+            secs_of_day % 3600 / 60,
+            secs_of_day % 60
+        );
+        prop_assert_eq!(Timestamp::parse(&rfc3339), Some(unix));
+    });
+}
+
+/// Converts days since 1970-01-01 to a proleptic Gregorian (year, month, day)
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}